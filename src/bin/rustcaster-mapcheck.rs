@@ -0,0 +1,273 @@
+//! Checks maps for mistakes that would break a level, and prints statistics about each of them.
+//! Exits with 0 if every map passed, 1 if any map has problems and 2 if a map couldn't be loaded,
+//! so it can be run before committing changes to maps.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs::File;
+use std::process;
+use rustcaster::game::map::{Map, CellType, SpawnType, ParsedCell, is_map_problem};
+use rustcaster::game::triggers::TriggerAction;
+use rustcaster::textures::{wall_section_name, WALL_SECTIONS, WALL_ATLAS_PATH};
+
+const EXIT_PASSED: i32 = 0;
+const EXIT_PROBLEMS: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
+fn main() {
+    let mut atlas_path = WALL_ATLAS_PATH.to_owned();
+    let mut map_ids = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--atlas" => match args.next() {
+                Some(x) => atlas_path = x,
+                None => exit_with_usage(),
+            },
+
+            x if x.starts_with("--") => exit_with_usage(),
+            _ => map_ids.push(arg),
+        }
+    }
+
+    if map_ids.is_empty() {
+        exit_with_usage();
+    }
+
+    let atlas_size = read_png_size(&atlas_path);
+    if atlas_size.is_none() {
+        eprintln!("Couldn't read the wall atlas '{}', so texture sections aren't checked against its size", atlas_path);
+    }
+
+    let mut exit_code = EXIT_PASSED;
+    for id in &map_ids {
+        let map = match Map::load(id) {
+            Ok(x) => x,
+
+            // Maps without a player spawn or with spawns inside walls are refused when loading,
+            // but are still broken levels rather than broken files
+            Err(err) if is_map_problem(&err) => {
+                println!("{}: can't be played", id);
+                println!("  problem: {}", err);
+                if exit_code == EXIT_PASSED {
+                    exit_code = EXIT_PROBLEMS;
+                }
+
+                continue;
+            }
+
+            Err(err) => {
+                eprintln!("{}: failed to load: {}", id, err);
+                exit_code = EXIT_ERROR;
+                continue;
+            }
+        };
+
+        let problems = check_map(&map, atlas_size);
+        print_stats(&map);
+        for problem in &problems {
+            println!("  problem: {}", problem);
+        }
+
+        if !problems.is_empty() && exit_code == EXIT_PASSED {
+            exit_code = EXIT_PROBLEMS;
+        }
+    }
+
+    process::exit(exit_code);
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("Usage: rustcaster-mapcheck [--atlas <wall atlas png>] <map>...");
+    process::exit(EXIT_ERROR);
+}
+
+fn read_png_size(path: &str) -> Option<(u32, u32)> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let (info, _) = decoder.read_info().ok()?;
+    Some((info.width, info.height))
+}
+
+/// Returns a description of everything wrong with the map
+fn check_map(map: &Map, atlas_size: Option<(u32, u32)>) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_boundary(map, &mut problems);
+    check_reachable(map, &mut problems);
+    check_doors(map, &mut problems);
+    check_textures(map, atlas_size, &mut problems);
+    problems
+}
+
+/// Anything on the edge of the map that can be walked through, or opened to be walked through,
+/// lets the player walk off the map
+fn check_boundary(map: &Map, problems: &mut Vec<String>) {
+    for row in 0..map.height {
+        for col in 0..map.width {
+            let on_edge = row == 0 || col == 0 || row == map.height - 1 || col == map.width - 1;
+            if on_edge && can_open(map.cell_at(row, col)) {
+                problems.push(format!("Hole in the boundary at row {}, column {}", row, col));
+            }
+        }
+    }
+}
+
+/// Finds empty cells the player can't get to from their spawn, even after opening every door and
+/// secret wall
+fn check_reachable(map: &Map, problems: &mut Vec<String>) {
+    let unreachable = match reachable_cells(map) {
+        Some(reachable) => (0..map.height)
+            .flat_map(|row| (0..map.width).map(move |col| (row, col)))
+            .filter(|(row, col)| map.cell_at(*row, *col) == Some(CellType::Empty) && !reachable.contains(&(*row, *col)))
+            .collect::<Vec<_>>(),
+
+        None => return,
+    };
+
+    if let Some((row, col)) = unreachable.first() {
+        problems.push(format!("{} open cells can't be reached from the player spawn, starting at row {}, column {}",
+                              unreachable.len(), row, col));
+    }
+}
+
+/// Doors need a wall on each side to be hung between, either to their left and right or above and
+/// below them
+fn check_doors(map: &Map, problems: &mut Vec<String>) {
+    let is_wall = |row: Option<usize>, col: Option<usize>| match (row, col) {
+        (Some(row), Some(col)) => match map.cell_at(row, col) {
+            Some(CellType::BrickWall) | Some(CellType::BlueWall) | Some(CellType::WoodWall) | Some(CellType::Exit) => true,
+            Some(_) => false,
+
+            // Doors on the edge of the map are already reported as holes in the boundary
+            None => true,
+        },
+
+        _ => true,
+    };
+
+    for row in 0..map.height {
+        for col in 0..map.width {
+            if let Some(CellType::Door { .. }) = map.cell_at(row, col) {
+                let horizontal = is_wall(Some(row), col.checked_sub(1)) && is_wall(Some(row), Some(col + 1));
+                let vertical = is_wall(row.checked_sub(1), Some(col)) && is_wall(Some(row + 1), Some(col));
+                if !horizontal && !vertical {
+                    problems.push(format!("Door at row {}, column {} doesn't have walls on both sides", row, col));
+                }
+            }
+        }
+    }
+}
+
+/// Every wall the map can show needs a section in the wall atlas that fits inside the image
+fn check_textures(map: &Map, atlas_size: Option<(u32, u32)>, problems: &mut Vec<String>) {
+    let mut used = BTreeSet::new();
+    for row in 0..map.height {
+        for col in 0..map.width {
+            match map.cell_at(row, col) {
+                Some(CellType::Empty) | None => (),
+                Some(x) => { used.insert(x.name()); }
+            }
+        }
+    }
+
+    // Floors, ceilings and walls put up by triggers are drawn with the same sections
+    let textures = map.floor_texture.iter().chain(map.ceiling_texture.iter());
+    let trigger_walls = map.triggers.iter().filter_map(|x| match x.action {
+        TriggerAction::ToggleWall { cell_type, .. } => Some(cell_type),
+        _ => None,
+    });
+
+    for cell_type in textures.copied().chain(trigger_walls) {
+        used.insert(cell_type.name());
+    }
+
+    for name in used {
+        let cell_type = CellType::from_name(name).unwrap();
+        let section_name = match wall_section_name(cell_type) {
+            Some(x) => x,
+            None => {
+                problems.push(format!("{} cells have no wall atlas section to be drawn with", name));
+                continue;
+            }
+        };
+
+        match (WALL_SECTIONS.iter().find(|x| x.0 == section_name), atlas_size) {
+            (None, _) => problems.push(format!("{} cells use the unknown wall atlas section '{}'", name, section_name)),
+            (Some((_, x, y, width, height)), Some((atlas_width, atlas_height))) => {
+                if x + width > atlas_width || y + height > atlas_height {
+                    problems.push(format!("Wall atlas section '{}' used by {} cells is outside of the {}x{} image",
+                                          section_name, name, atlas_width, atlas_height));
+                }
+            }
+
+            (Some(_), None) => (),
+        }
+    }
+}
+
+fn print_stats(map: &Map) {
+    let mut cells = BTreeMap::new();
+    for row in 0..map.height {
+        for col in 0..map.width {
+            if let Some(cell_type) = map.cell_at(row, col) {
+                *cells.entry(cell_type.name()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut spawns = BTreeMap::new();
+    for spawn in &map.spawns {
+        *spawns.entry(ParsedCell::Spawn(spawn.entity).name()).or_insert(0) += 1;
+    }
+
+    println!("{}: {}x{}, {} triggers{}", map.id, map.width, map.height, map.triggers.len(),
+             map.script.as_ref().map(|x| format!(", script {}", x)).unwrap_or_default());
+    println!("  cells: {}", join_counts(&cells));
+    println!("  spawns: {}", join_counts(&spawns));
+    if let Some(reachable) = reachable_cells(map) {
+        let open = (0..map.height)
+            .flat_map(|row| (0..map.width).map(move |col| (row, col)))
+            .filter(|(row, col)| can_open(map.cell_at(*row, *col)))
+            .count();
+
+        println!("  reachable: {} of {} open cells", reachable.len(), open);
+    }
+}
+
+fn join_counts(counts: &BTreeMap<&str, usize>) -> String {
+    counts.iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Every cell the player can get to from where they spawn, opening any doors and secret walls on
+/// the way, or `None` if the map has no player spawn
+fn reachable_cells(map: &Map) -> Option<BTreeSet<(usize, usize)>> {
+    let spawn = map.spawns.iter().find(|x| x.entity == SpawnType::Player)?;
+    let mut reachable = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    reachable.insert((spawn.row, spawn.col));
+    queue.push_back((spawn.row, spawn.col));
+    while let Some((row, col)) = queue.pop_front() {
+        let neighbours = [
+            (row.checked_sub(1), Some(col)),
+            (Some(row + 1), Some(col)),
+            (Some(row), col.checked_sub(1)),
+            (Some(row), Some(col + 1)),
+        ];
+
+        for neighbour in neighbours.iter() {
+            if let (Some(row), Some(col)) = *neighbour {
+                if can_open(map.cell_at(row, col)) && reachable.insert((row, col)) {
+                    queue.push_back((row, col));
+                }
+            }
+        }
+    }
+
+    Some(reachable)
+}
+
+/// Whether the player can walk through the cell, or open it to walk through it
+fn can_open(cell: Option<CellType>) -> bool {
+    matches!(cell, Some(CellType::Empty) | Some(CellType::Door { .. }) | Some(CellType::SecretWall))
+}
@@ -0,0 +1,139 @@
+//! Draws a map from above to a PNG image without starting the game, optionally with its spawns,
+//! or with the entities and the path the player took at the end of a replay.
+
+use std::io;
+use std::process;
+use rustcaster::core::vector::Vector;
+use rustcaster::game::GameState;
+use rustcaster::game::map::Map;
+use rustcaster::game::replay::Replay;
+use rustcaster::map_image::MapImage;
+
+const DEFAULT_PIXELS_PER_CELL: u32 = 8;
+const MAX_PIXELS_PER_CELL: u32 = 64;
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_ERROR: i32 = 2;
+
+struct ExportOptions {
+    pixels_per_cell: u32,
+    spawns: bool,
+    entities: bool,
+    replay_path: Option<String>,
+    map_id: Option<String>,
+    output_path: String,
+}
+
+fn main() {
+    let options = parse_args();
+    match export(&options) {
+        Ok((width, height)) => {
+            println!("Wrote {}x{} image to {}", width, height, options.output_path);
+            process::exit(EXIT_SUCCESS);
+        }
+
+        Err(err) => {
+            eprintln!("Failed to export map: {}", err);
+            process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+fn parse_args() -> ExportOptions {
+    let mut pixels_per_cell = DEFAULT_PIXELS_PER_CELL;
+    let (mut spawns, mut entities) = (false, false);
+    let mut replay_path = None;
+    let mut positional = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scale" => match args.next().and_then(|x| x.parse().ok()) {
+                Some(x) if (1..=MAX_PIXELS_PER_CELL).contains(&x) => pixels_per_cell = x,
+                _ => exit_with_usage(),
+            },
+
+            "--spawns" => spawns = true,
+            "--entities" => entities = true,
+            "--replay" => match args.next() {
+                Some(x) => replay_path = Some(x),
+                None => exit_with_usage(),
+            },
+
+            x if x.starts_with("--") => exit_with_usage(),
+            _ => positional.push(arg),
+        }
+    }
+
+    // A replay names its own maps, so only the output is given with one
+    let (map_id, output_path) = match (replay_path.is_some(), positional.len()) {
+        (false, 2) => (Some(positional.remove(0)), positional.remove(0)),
+        (true, 1) => (None, positional.remove(0)),
+        _ => exit_with_usage(),
+    };
+
+    ExportOptions { pixels_per_cell, spawns, entities, replay_path, map_id, output_path }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("Usage: rustcaster-mapexport [--scale <pixels per cell>] [--spawns] [--entities] <map> <output png>");
+    eprintln!("       rustcaster-mapexport [--scale <pixels per cell>] [--spawns] [--entities] --replay <file> <output png>");
+    process::exit(EXIT_ERROR);
+}
+
+/// Returns the size of the image that was written
+fn export(options: &ExportOptions) -> io::Result<(u32, u32)> {
+    let (map, game_state, path) = match (&options.replay_path, &options.map_id) {
+        (Some(replay_path), _) => {
+            let (game_state, path) = play_replay(&Replay::load(replay_path)?)?;
+            (game_state.map.clone(), Some(game_state), path)
+        }
+
+        (None, Some(map_id)) => {
+            let map = Map::load(map_id)?;
+            let game_state = if options.entities { Some(GameState::new(map.clone(), 0)?) } else { None };
+            (map, game_state, Vec::new())
+        }
+
+        (None, None) => unreachable!(),
+    };
+
+    let mut image = MapImage::new(&map, options.pixels_per_cell);
+    if options.spawns {
+        image.draw_spawns(&map);
+    }
+
+    image.draw_path(&path);
+    if let Some(game_state) = game_state {
+        if options.entities {
+            image.draw_entities(&game_state.entities);
+        }
+
+        if options.replay_path.is_some() {
+            image.draw_player(game_state.player.position, game_state.player.collision_size);
+        }
+    }
+
+    image.save(&options.output_path)?;
+    Ok((image.width, image.height))
+}
+
+/// Plays the replay, keeping every position the player was at in the level they finished in
+fn play_replay(replay: &Replay) -> io::Result<(GameState, Vec<Vector>)> {
+    let mut path: Vec<Vector> = Vec::new();
+    let mut level_seconds = 0.0;
+    let game_state = replay.play_with(|game_state| {
+        // The clock starts again whenever the next level is started or the level is restarted
+        if game_state.level_seconds < level_seconds {
+            path.clear();
+        }
+
+        level_seconds = game_state.level_seconds;
+        let position = game_state.player.position;
+        match path.last() {
+            Some(last) if last.x == position.x && last.y == position.y => (),
+            _ => path.push(position),
+        }
+    })?;
+
+    Ok((game_state, path))
+}
@@ -1,3 +1,4 @@
 pub mod radians;
 pub mod degrees;
-pub mod vector;
\ No newline at end of file
+pub mod vector;
+pub mod random;
\ No newline at end of file
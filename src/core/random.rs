@@ -0,0 +1,43 @@
+/// Small xorshift based generator.  Game logic must only pull randomness from this so that a
+/// run can be reproduced from its seed.
+#[derive(Copy, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on a zero state
+        let state = if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed };
+        Random { state }
+    }
+
+    /// The current internal state, which can be passed to `new()` to resume the sequence
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a value in the range of 0.0 (inclusive) to 1.0 (exclusive)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1_u64 << 24) as f32
+    }
+
+    /// Returns a value in the range of 0 (inclusive) to `max` (exclusive)
+    pub fn next_below(&mut self, max: u32) -> u32 {
+        if max == 0 {
+            return 0;
+        }
+
+        (self.next_u64() % max as u64) as u32
+    }
+}
@@ -6,6 +6,12 @@ pub struct Vector {
     pub y: f32,
 }
 
+impl Vector {
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+}
+
 impl Add for Vector {
     type Output = Vector;
 
@@ -0,0 +1,267 @@
+use std::path::Path;
+use std::time::Duration;
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use sdl2::rect::Rect;
+use crate::game::{GameState, ActiveInputs};
+use crate::game::map::{Map, ParsedCell, SpawnType, CellType, GLYPHS, DEFAULT_MAP_ID};
+use crate::game::pathfinding::CellPosition;
+use crate::game::tiled::is_tiled_path;
+use crate::game::wolf3d::is_wolf3d_path;
+use crate::game::generation::is_generated_id;
+
+/// Where the built in map is saved, since it has no file of its own
+const DEFAULT_MAP_SAVE_PATH: &str = "default.map";
+
+/// The brush palette along the bottom is this fraction of the screen's height
+const PALETTE_HEIGHT_DIVISOR: u32 = 8;
+
+/// Only this many edits can be undone
+const MAX_UNDO_STEPS: usize = 100;
+
+/// Edits a map by painting the contents of cells with the mouse
+pub struct Editor {
+    pub map: Map,
+
+    /// Index into `GLYPHS` of what the left mouse button paints
+    pub brush: usize,
+    pub hovered: Option<CellPosition>,
+
+    /// A game started from the cell being previewed, only used to render its view
+    pub preview: Option<GameState>,
+
+    /// Shown in the palette until something else happens, such as confirming the map was saved
+    pub message: Option<String>,
+
+    /// Whether the map has been edited since the editor was opened
+    pub changed: bool,
+    stroke: Option<Stroke>,
+    undo: Vec<Map>,
+    redo: Vec<Map>,
+}
+
+/// Where the map's cells are drawn, which is also used to find the cell under the mouse
+pub struct EditorLayout {
+    pub map_area: Rect,
+    pub palette_area: Rect,
+    pub cell_size: u32,
+}
+
+/// A mouse drag that paints every cell it passes over
+struct Stroke {
+    contents: ParsedCell,
+
+    /// The map is only pushed onto the undo stack once the stroke actually changes something
+    saved_undo: bool,
+}
+
+impl Editor {
+    pub fn new(map: Map) -> Self {
+        Editor {
+            map,
+            brush: 1,
+            hovered: None,
+            preview: None,
+            message: None,
+            changed: false,
+            stroke: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn brush_contents(&self) -> ParsedCell {
+        GLYPHS[self.brush].1
+    }
+
+    pub fn handle_event(&mut self, event: &Event, screen_width: u32, screen_height: u32) {
+        let layout = EditorLayout::new(&self.map, screen_width, screen_height);
+        match *event {
+            Event::MouseMotion { x, y, .. } => {
+                self.hovered = layout.cell_at(&self.map, x, y);
+                self.paint_hovered();
+            }
+
+            Event::MouseButtonDown { mouse_btn, x, y, .. } if self.preview.is_none() => {
+                let contents = match mouse_btn {
+                    MouseButton::Left => self.brush_contents(),
+                    MouseButton::Right => ParsedCell::Environmental(CellType::Empty),
+                    _ => return,
+                };
+
+                self.stroke = Some(Stroke { contents, saved_undo: false });
+                self.hovered = layout.cell_at(&self.map, x, y);
+                self.paint_hovered();
+            }
+
+            Event::MouseButtonUp { .. } => self.stroke = None,
+            Event::MouseWheel { y, .. } => self.cycle_brush(-y),
+            Event::KeyDown { keycode: Some(key), keymod, .. } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                match key {
+                    Keycode::Z => self.undo(),
+                    Keycode::Y => self.redo(),
+                    Keycode::S => self.save(),
+                    _ => (),
+                }
+            }
+
+            Event::KeyUp { keycode: Some(Keycode::LeftBracket), .. } => self.cycle_brush(-1),
+            Event::KeyUp { keycode: Some(Keycode::RightBracket), .. } => self.cycle_brush(1),
+            Event::KeyUp { keycode: Some(Keycode::V), .. } => self.toggle_preview(),
+            _ => (),
+        }
+    }
+
+    /// Turns the preview's view with the same keys the player turns with
+    pub fn tick(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
+        if let Some(preview) = &mut self.preview {
+            let turn_amount = preview.player.turn_speed * time_since_last_frame.as_secs_f32();
+            if inputs.turn_left {
+                preview.player.facing = preview.player.facing - turn_amount;
+            }
+
+            if inputs.turn_right {
+                preview.player.facing = preview.player.facing + turn_amount;
+            }
+        }
+    }
+
+    fn cycle_brush(&mut self, amount: i32) {
+        let count = GLYPHS.len() as i32;
+        self.brush = (self.brush as i32 + amount).rem_euclid(count) as usize;
+    }
+
+    fn paint_hovered(&mut self) {
+        let (cell, stroke) = match (self.hovered, &mut self.stroke) {
+            (Some(cell), Some(stroke)) => (cell, stroke),
+            _ => return,
+        };
+
+        if self.map.contents_at(cell.row, cell.col) == Some(stroke.contents) {
+            return;
+        }
+
+        if !stroke.saved_undo {
+            stroke.saved_undo = true;
+            self.undo.push(self.map.clone());
+            if self.undo.len() > MAX_UNDO_STEPS {
+                self.undo.remove(0);
+            }
+
+            self.redo.clear();
+        }
+
+        // There can only be one place for the player to start
+        if stroke.contents == ParsedCell::Spawn(SpawnType::Player) {
+            self.map.spawns.retain(|x| x.entity != SpawnType::Player);
+        }
+
+        self.map.set_contents(cell.row, cell.col, stroke.contents);
+        self.changed = true;
+        self.message = None;
+    }
+
+    fn undo(&mut self) {
+        if let Some(map) = self.undo.pop() {
+            self.redo.push(std::mem::replace(&mut self.map, map));
+            self.changed = true;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(map) = self.redo.pop() {
+            self.undo.push(std::mem::replace(&mut self.map, map));
+            self.changed = true;
+        }
+    }
+
+    /// Saves over the map's file, or a new file for the built in map and maps imported from other
+    /// formats
+    fn save(&mut self) {
+        if self.map.id == DEFAULT_MAP_ID {
+            self.map.id = DEFAULT_MAP_SAVE_PATH.to_owned();
+        } else if is_tiled_path(&self.map.id) {
+            self.map.id = Path::new(&self.map.id).with_extension("map").to_string_lossy().into_owned();
+        } else if is_wolf3d_path(&self.map.id) {
+            // Each map in GAMEMAPS gets its own file, named after its index
+            self.map.id = format!("{}.map", self.map.id.replace('#', "_"));
+        } else if is_generated_id(&self.map.id) {
+            self.map.id = format!("{}.map", self.map.id.replace(':', "_"));
+        }
+
+        self.message = match self.map.save(&self.map.id) {
+            Ok(()) => Some(format!("Saved to '{}'", self.map.id)),
+            Err(err) => Some(format!("Failed to save to '{}': {}", self.map.id, err)),
+        };
+    }
+
+    /// Starts a preview from the hovered cell, or ends the preview if one is showing
+    fn toggle_preview(&mut self) {
+        if self.preview.take().is_some() {
+            return;
+        }
+
+        let cell = match self.hovered {
+            Some(x) => x,
+            None => return,
+        };
+
+        if !matches!(self.map.contents_at(cell.row, cell.col), Some(ParsedCell::Environmental(CellType::Empty))
+                     | Some(ParsedCell::Spawn(SpawnType::Player))) {
+            self.message = Some("The view can only be previewed from an empty cell".to_owned());
+            return;
+        }
+
+        // The preview is a fresh game with the player starting in the hovered cell
+        let mut map = self.map.clone();
+        map.spawns.retain(|x| x.entity != SpawnType::Player);
+        map.set_contents(cell.row, cell.col, ParsedCell::Spawn(SpawnType::Player));
+        match GameState::new(map, 0) {
+            Ok(x) => self.preview = Some(x),
+            Err(err) => self.message = Some(err.to_string()),
+        }
+    }
+}
+
+impl EditorLayout {
+    /// Fits the map into the screen above the palette, with square cells
+    pub fn new(map: &Map, screen_width: u32, screen_height: u32) -> Self {
+        let palette_height = screen_height / PALETTE_HEIGHT_DIVISOR;
+        let available_height = screen_height - palette_height;
+        let cell_size = (screen_width / map.width as u32).min(available_height / map.height as u32).max(1);
+        let map_width = cell_size * map.width as u32;
+        let map_height = cell_size * map.height as u32;
+
+        EditorLayout {
+            map_area: Rect::new((screen_width as i32 - map_width as i32) / 2,
+                                (available_height as i32 - map_height as i32) / 2,
+                                map_width,
+                                map_height),
+
+            palette_area: Rect::new(0, available_height as i32, screen_width, palette_height),
+            cell_size,
+        }
+    }
+
+    pub fn cell_rect(&self, row: usize, col: usize) -> Rect {
+        Rect::new(self.map_area.x() + (col as u32 * self.cell_size) as i32,
+                  self.map_area.y() + (row as u32 * self.cell_size) as i32,
+                  self.cell_size,
+                  self.cell_size)
+    }
+
+    pub fn cell_at(&self, map: &Map, x: i32, y: i32) -> Option<CellPosition> {
+        if !self.map_area.contains_point((x, y)) {
+            return None;
+        }
+
+        let row = ((y - self.map_area.y()) as u32 / self.cell_size) as usize;
+        let col = ((x - self.map_area.x()) as u32 / self.cell_size) as usize;
+        if row < map.height && col < map.width {
+            Some(CellPosition { row, col })
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sleeping can overshoot by a millisecond or more, so the end of each wait is spent spinning
+const SPIN_DURATION: Duration = Duration::from_millis(2);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameLimit {
+    /// Waits for the display's vertical sync when presenting each frame
+    VSync,
+
+    /// Sleeps between frames to stay at the specified frames per second
+    Capped(u32),
+
+    /// Renders frames as fast as possible, for benchmarking
+    Uncapped,
+}
+
+/// Keeps the main loop from running faster than the frame limit allows
+pub struct FrameLimiter {
+    frame_duration: Option<Duration>,
+    next_frame_at: Instant,
+}
+
+impl FrameLimit {
+    /// Parses `vsync`, `uncapped` or a frames per second cap
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "vsync" => Some(FrameLimit::VSync),
+            "uncapped" => Some(FrameLimit::Uncapped),
+            x => match x.parse() {
+                Ok(0) | Err(_) => None,
+                Ok(fps) => Some(FrameLimit::Capped(fps)),
+            },
+        }
+    }
+}
+
+impl FrameLimiter {
+    pub fn new(limit: FrameLimit) -> Self {
+        let frame_duration = match limit {
+            FrameLimit::Capped(fps) => Some(Duration::from_secs(1) / fps),
+
+            // VSync is handled by the renderer when the frame is presented
+            FrameLimit::VSync | FrameLimit::Uncapped => None,
+        };
+
+        FrameLimiter {
+            frame_duration,
+            next_frame_at: Instant::now(),
+        }
+    }
+
+    /// Waits until it is time to start the next frame
+    pub fn wait(&mut self) {
+        let frame_duration = match self.frame_duration {
+            Some(x) => x,
+            None => return,
+        };
+
+        self.next_frame_at += frame_duration;
+        let now = Instant::now();
+        if self.next_frame_at <= now {
+            // Running behind, so start over from now rather than rushing to catch up
+            self.next_frame_at = now;
+            return;
+        }
+
+        let remaining = self.next_frame_at - now;
+        if remaining > SPIN_DURATION {
+            thread::sleep(remaining - SPIN_DURATION);
+        }
+
+        while Instant::now() < self.next_frame_at {
+            std::hint::spin_loop();
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use std::f32::consts::PI;
+use crate::core::radians::Radians;
+use crate::core::vector::Vector;
+use crate::core::random::Random;
+use crate::game::{apply_wall_collisions, cell_center};
+use crate::game::entities::{Entity, EntityKind, Behaviour, Sprite, Player};
+use crate::game::map::Map;
+use crate::game::raycast::has_line_of_sight;
+use crate::game::pathfinding::{Pathfinder, CellPosition};
+
+const SIGHT_DISTANCE: f32 = 40.0;
+const HALF_FIELD_OF_VIEW: f32 = PI / 3.0;
+const NEARBY_DISTANCE: f32 = 6.0;
+const ATTACK_DISTANCE: f32 = 25.0;
+const MIN_CHASE_DISTANCE: f32 = 4.0;
+const ATTACK_SECONDS: f32 = 0.6;
+const ATTACK_COOLDOWN_SECONDS: f32 = 1.5;
+const PAIN_SECONDS: f32 = 0.3;
+const DEATH_SECONDS: f32 = 0.5;
+const LOSE_TRACK_SECONDS: f32 = 5.0;
+const WALK_FRAME_SECONDS: f32 = 0.25;
+const SHOT_BASE_DAMAGE: i32 = 5;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EnemyState { Idle, Patrol, Chase, Attack, Pain, Death }
+
+/// How an enemy hurts the player
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EnemyAttack {
+    /// An instant shot that is less accurate the further away the player is
+    Hitscan,
+
+    /// A slow projectile the player can dodge
+    Fireball,
+}
+
+/// Something the enemy did this tick that affects the rest of the game
+pub enum EnemyAction {
+    DamagePlayer(i32),
+    LaunchProjectile { kind: EntityKind, position: Vector, facing: Radians },
+}
+
+/// The sprites shown for each of the enemy's states
+struct EnemySprites {
+    stand: Sprite,
+    walk: [Sprite; 2],
+    aim: Sprite,
+    fire: Sprite,
+    pain: Sprite,
+    dying: Sprite,
+    dead: Sprite,
+}
+
+const GUARD_SPRITES: EnemySprites = EnemySprites {
+    stand: Sprite::GuardStand,
+    walk: [Sprite::GuardWalk1, Sprite::GuardWalk2],
+    aim: Sprite::GuardAim,
+    fire: Sprite::GuardFire,
+    pain: Sprite::GuardPain,
+    dying: Sprite::GuardDying,
+    dead: Sprite::GuardDead,
+};
+
+const MUTANT_SPRITES: EnemySprites = EnemySprites {
+    stand: Sprite::MutantStand,
+    walk: [Sprite::MutantWalk1, Sprite::MutantWalk2],
+    aim: Sprite::MutantAim,
+    fire: Sprite::MutantFire,
+    pain: Sprite::MutantPain,
+    dying: Sprite::MutantDying,
+    dead: Sprite::MutantDead,
+};
+
+#[derive(Copy, Clone)]
+pub struct Enemy {
+    pub state: EnemyState,
+
+    /// The state to go back to once the enemy has lost track of the player
+    pub resting_state: EnemyState,
+    pub attack: EnemyAttack,
+
+    /// How long the enemy has been in its current state
+    pub state_seconds: f32,
+    pub attack_cooldown: f32,
+    pub seconds_since_seen_player: f32,
+    pub last_known_player_position: Vector,
+
+    /// Used to notice when the enemy has taken damage since the last tick
+    pub health_last_tick: i32,
+}
+
+impl EnemyState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnemyState::Idle => "idle",
+            EnemyState::Patrol => "patrol",
+            EnemyState::Chase => "chase",
+            EnemyState::Attack => "attack",
+            EnemyState::Pain => "pain",
+            EnemyState::Death => "death",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "idle" => Some(EnemyState::Idle),
+            "patrol" => Some(EnemyState::Patrol),
+            "chase" => Some(EnemyState::Chase),
+            "attack" => Some(EnemyState::Attack),
+            "pain" => Some(EnemyState::Pain),
+            "death" => Some(EnemyState::Death),
+            _ => None,
+        }
+    }
+}
+
+impl EnemyAttack {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnemyAttack::Hitscan => "hitscan",
+            EnemyAttack::Fireball => "fireball",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hitscan" => Some(EnemyAttack::Hitscan),
+            "fireball" => Some(EnemyAttack::Fireball),
+            _ => None,
+        }
+    }
+}
+
+impl Enemy {
+    pub fn new(resting_state: EnemyState, attack: EnemyAttack, health: i32) -> Self {
+        Enemy {
+            state: resting_state,
+            resting_state,
+            attack,
+            state_seconds: 0.0,
+            attack_cooldown: 0.0,
+            seconds_since_seen_player: LOSE_TRACK_SECONDS,
+            last_known_player_position: Vector { x: 0.0, y: 0.0 },
+            health_last_tick: health,
+        }
+    }
+
+    fn change_state(&mut self, state: EnemyState) {
+        self.state = state;
+        self.state_seconds = 0.0;
+    }
+}
+
+/// Runs the enemy's state machine for a single tick, returning what the enemy did to the player
+/// if it attacked.
+pub fn tick_enemy(entity: &mut Entity,
+                  map: &Map,
+                  pathfinder: &mut Pathfinder,
+                  random: &mut Random,
+                  player: &Player,
+                  seconds: f32) -> Option<EnemyAction> {
+    let mut enemy = match entity.behaviour {
+        Behaviour::Enemy(x) => x,
+        _ => return None,
+    };
+
+    let player_position = player.position;
+    let mut action = None;
+    let previous_state_seconds = enemy.state_seconds;
+    enemy.state_seconds += seconds;
+    enemy.attack_cooldown = (enemy.attack_cooldown - seconds).max(0.0);
+
+    // Taking damage interrupts whatever the enemy was doing
+    if enemy.state != EnemyState::Death {
+        if entity.health <= 0 {
+            enemy.change_state(EnemyState::Death);
+        } else if entity.health < enemy.health_last_tick {
+            enemy.change_state(EnemyState::Pain);
+            enemy.seconds_since_seen_player = 0.0;
+            enemy.last_known_player_position = player_position;
+        }
+    }
+
+    enemy.health_last_tick = entity.health;
+
+    let can_see_player = enemy.state != EnemyState::Death && can_see(entity, map, player);
+    if can_see_player {
+        enemy.seconds_since_seen_player = 0.0;
+        enemy.last_known_player_position = player_position;
+    } else {
+        enemy.seconds_since_seen_player += seconds;
+    }
+
+    match enemy.state {
+        EnemyState::Idle => {
+            if can_see_player {
+                enemy.change_state(EnemyState::Chase);
+            }
+        }
+
+        EnemyState::Patrol => {
+            if can_see_player {
+                enemy.change_state(EnemyState::Chase);
+            } else if move_forward(entity, map, seconds) {
+                // Walked into a wall, so head back the way we came
+                entity.facing = entity.facing + PI;
+            }
+        }
+
+        EnemyState::Chase => {
+            let distance = (player_position - entity.position).length();
+            if enemy.seconds_since_seen_player >= LOSE_TRACK_SECONDS {
+                enemy.change_state(enemy.resting_state);
+            } else if can_see_player && distance <= ATTACK_DISTANCE && enemy.attack_cooldown <= 0.0 {
+                face_towards(entity, player_position);
+                enemy.change_state(EnemyState::Attack);
+            } else {
+                let target = enemy.last_known_player_position;
+                if (target - entity.position).length() > MIN_CHASE_DISTANCE {
+                    let waypoint = next_waypoint(entity, map, pathfinder, target);
+                    face_towards(entity, waypoint);
+                    move_forward(entity, map, seconds);
+                }
+            }
+        }
+
+        EnemyState::Attack => {
+            face_towards(entity, player_position);
+
+            // The shot goes off as the firing frame is first shown
+            let fire_at = ATTACK_SECONDS / 2.0;
+            if previous_state_seconds < fire_at && enemy.state_seconds >= fire_at {
+                action = match enemy.attack {
+                    EnemyAttack::Hitscan => shoot_at_player(entity, map, random, player).map(EnemyAction::DamagePlayer),
+                    EnemyAttack::Fireball => Some(EnemyAction::LaunchProjectile {
+                        kind: EntityKind::Fireball,
+                        position: entity.position,
+                        facing: entity.facing,
+                    }),
+                };
+            }
+
+            if enemy.state_seconds >= ATTACK_SECONDS {
+                enemy.attack_cooldown = ATTACK_COOLDOWN_SECONDS;
+                enemy.change_state(EnemyState::Chase);
+            }
+        }
+
+        EnemyState::Pain => {
+            if enemy.state_seconds >= PAIN_SECONDS {
+                enemy.change_state(EnemyState::Chase);
+            }
+        }
+
+        EnemyState::Death => (),
+    }
+
+    if enemy.state != EnemyState::Chase {
+        entity.path.clear();
+    }
+
+    entity.solid = enemy.state != EnemyState::Death;
+    entity.sprite = enemy_sprite(entity.kind, &enemy);
+    entity.behaviour = Behaviour::Enemy(enemy);
+
+    action
+}
+
+/// Enemies notice the player when the player is in front of them and not hidden by a wall, or
+/// when the player gets close enough that they would be heard.
+fn can_see(entity: &Entity, map: &Map, player: &Player) -> bool {
+    if !player.is_alive() {
+        return false;
+    }
+
+    let delta = player.position - entity.position;
+    let distance = delta.length();
+    if distance > SIGHT_DISTANCE {
+        return false;
+    }
+
+    let mut angle_from_facing = delta.y.atan2(delta.x) - entity.facing.0;
+    if angle_from_facing > PI {
+        angle_from_facing -= 2.0 * PI;
+    } else if angle_from_facing < -PI {
+        angle_from_facing += 2.0 * PI;
+    }
+
+    if distance > NEARBY_DISTANCE && angle_from_facing.abs() > HALF_FIELD_OF_VIEW {
+        return false;
+    }
+
+    has_line_of_sight(map, entity.position, player.position)
+}
+
+/// Enemies are less accurate the further away the player is
+fn shoot_at_player(entity: &Entity, map: &Map, random: &mut Random, player: &Player) -> Option<i32> {
+    if !player.is_alive() || !has_line_of_sight(map, entity.position, player.position) {
+        return None;
+    }
+
+    let distance = (player.position - entity.position).length();
+    let hit_chance = 1.0 - (distance / ATTACK_DISTANCE).min(1.0) * 0.6;
+    if random.next_f32() >= hit_chance {
+        return None;
+    }
+
+    Some(SHOT_BASE_DAMAGE + random.next_below(SHOT_BASE_DAMAGE as u32 * 2) as i32)
+}
+
+/// Returns where the entity should walk towards to reach the target.  When the target is in plain
+/// sight the entity walks straight at it, otherwise it follows a path around the walls.
+fn next_waypoint(entity: &mut Entity, map: &Map, pathfinder: &mut Pathfinder, target: Vector) -> Vector {
+    entity.path.clear();
+    if has_line_of_sight(map, entity.position, target) {
+        return target;
+    }
+
+    let start = CellPosition::from_position(map, entity.position);
+    let goal = CellPosition::from_position(map, target);
+    let path = match (start, goal) {
+        (Some(start), Some(goal)) => pathfinder.find_path(map, start, goal, entity.path_movement),
+        _ => None,
+    };
+
+    match path {
+        Some(path) if path.len() > 1 => {
+            entity.path.extend_from_slice(path);
+            cell_center(map, path[1].row, path[1].col)
+        }
+
+        _ => target,
+    }
+}
+
+fn face_towards(entity: &mut Entity, target: Vector) {
+    let delta = target - entity.position;
+    entity.facing = Radians(delta.y.atan2(delta.x)).clamp();
+}
+
+/// Moves the entity along its facing, returning true if it bumped into a wall
+fn move_forward(entity: &mut Entity, map: &Map, seconds: f32) -> bool {
+    let velocity = Vector {
+        x: entity.facing.0.cos() * entity.move_speed,
+        y: entity.facing.0.sin() * entity.move_speed,
+    };
+
+    entity.position = entity.position + (velocity * seconds);
+    apply_wall_collisions(map, &mut entity.position, entity.collision_size)
+}
+
+fn enemy_sprite(kind: EntityKind, enemy: &Enemy) -> Sprite {
+    let sprites = match kind {
+        EntityKind::Mutant => &MUTANT_SPRITES,
+        _ => &GUARD_SPRITES,
+    };
+
+    match enemy.state {
+        EnemyState::Idle => sprites.stand,
+        EnemyState::Patrol | EnemyState::Chase => {
+            sprites.walk[(enemy.state_seconds / WALK_FRAME_SECONDS) as usize % 2]
+        }
+
+        EnemyState::Attack if enemy.state_seconds < ATTACK_SECONDS / 2.0 => sprites.aim,
+        EnemyState::Attack => sprites.fire,
+        EnemyState::Pain => sprites.pain,
+        EnemyState::Death if enemy.state_seconds < DEATH_SECONDS => sprites.dying,
+        EnemyState::Death => sprites.dead,
+    }
+}
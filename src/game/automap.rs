@@ -0,0 +1,69 @@
+use crate::core::radians::Radians;
+use crate::core::vector::Vector;
+use crate::game::FOV_DEGREES;
+use crate::game::map::Map;
+use crate::game::raycast::cast_ray;
+
+/// How many rays are cast across the player's view each tick to find the cells they can see
+const EXPLORE_RAY_COUNT: u32 = 180;
+
+/// Cells the player has seen, which are the only ones the automap reveals
+#[derive(Clone)]
+pub struct ExploredCells {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl ExploredCells {
+    /// Starts with none of the map's cells explored
+    pub fn new(map: &Map) -> Self {
+        ExploredCells {
+            width: map.width,
+            height: map.height,
+            cells: vec![false; map.width * map.height],
+        }
+    }
+
+    pub fn is_explored(&self, row: usize, col: usize) -> bool {
+        row < self.height && col < self.width && self.cells[row * self.width + col]
+    }
+
+    pub fn explore(&mut self, row: usize, col: usize) {
+        if row < self.height && col < self.width {
+            self.cells[row * self.width + col] = true;
+        }
+    }
+
+    /// Explores every cell the player's view rays pass through, along with the walls they hit
+    pub fn explore_visible(&mut self, map: &Map, position: Vector, facing: Radians) {
+        let units_per_cell = map.units_per_cell as f32;
+        let step = units_per_cell / 4.0;
+        let first_ray_at = facing - FOV_DEGREES.to_radians() / 2.0;
+        let radians_per_ray = FOV_DEGREES.to_radians() / EXPLORE_RAY_COUNT as f32;
+        for index in 0..=EXPLORE_RAY_COUNT {
+            let angle = first_ray_at + (radians_per_ray * index as f32);
+            let ray = cast_ray(map, position, angle);
+            if ray.distance == 0.0 {
+                continue;
+            }
+
+            // Going slightly past where the ray stopped puts the point inside the wall it hit
+            let direction = Vector { x: angle.0.cos(), y: angle.0.sin() };
+            let end = ray.distance + 0.1;
+            let mut distance: f32 = 0.0;
+            loop {
+                let point = position + direction * distance.min(end);
+                if point.x >= 0.0 && point.y >= 0.0 {
+                    self.explore((point.y / units_per_cell) as usize, (point.x / units_per_cell) as usize);
+                }
+
+                if distance >= end {
+                    break;
+                }
+
+                distance += step;
+            }
+        }
+    }
+}
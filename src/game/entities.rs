@@ -1,5 +1,10 @@
 use crate::core::radians::Radians;
 use crate::core::vector::Vector;
+use crate::game::ai::{Enemy, EnemyState, EnemyAttack};
+use crate::game::projectiles::Projectile;
+use crate::game::pathfinding::{CellPosition, Movement};
+use crate::game::weapons::WeaponKind;
+use crate::game::items::{Inventory, Item, KeyColor};
 
 pub struct Player {
     pub position: Vector,
@@ -8,6 +13,22 @@ pub struct Player {
     pub velocity: Vector,
     pub turn_speed: f32,
     pub move_speed: f32,
+    pub health: i32,
+    pub max_health: i32,
+    pub ammo: u32,
+    pub weapons: Vec<WeaponKind>,
+    pub weapon: WeaponKind,
+    pub weapon_cooldown: f32,
+    pub seconds_since_fired: f32,
+
+    /// Whether fire was held last tick, so non-automatic weapons need the trigger released
+    pub trigger_held: bool,
+    pub seconds_since_death: f32,
+
+    /// Used to flash the screen when the player is hurt or picks something up
+    pub seconds_since_damaged: f32,
+    pub seconds_since_pickup: f32,
+    pub inventory: Inventory,
 }
 
 impl Player {
@@ -19,6 +40,255 @@ impl Player {
             velocity: Vector {x: 0f32, y: 0f32},
             turn_speed: 5_f32,
             move_speed: 10_f32,
+            health: 100,
+            max_health: 100,
+            ammo: 8,
+            weapons: vec![WeaponKind::Knife, WeaponKind::Pistol],
+            weapon: WeaponKind::Pistol,
+            weapon_cooldown: 0_f32,
+            seconds_since_fired: f32::MAX,
+            trigger_held: false,
+            seconds_since_death: 0_f32,
+            seconds_since_damaged: f32::MAX,
+            seconds_since_pickup: f32::MAX,
+            inventory: Inventory::new(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+}
+
+pub type EntityId = u32;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EntityKind {
+    Barrel, Pillar, Lamp, Rat, Guard, PatrolGuard, Mutant, Rocket, Fireball,
+    HealthPack, AmmoClip, GoldKey, SilverKey, Treasure, MachineGun, RocketLauncher,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Sprite {
+    Barrel, Pillar, Lamp, Rat,
+    GuardStand, GuardWalk1, GuardWalk2, GuardAim, GuardFire, GuardPain, GuardDying, GuardDead,
+    MutantStand, MutantWalk1, MutantWalk2, MutantAim, MutantFire, MutantPain, MutantDying, MutantDead,
+    Rocket, Fireball, Explosion1, Explosion2,
+    HealthPack, AmmoClip, GoldKey, SilverKey, Treasure, MachineGun, RocketLauncher,
+}
+
+#[derive(Copy, Clone)]
+pub enum Behaviour {
+    /// Never moves on its own
+    Static,
+
+    /// Walks in a straight line, picking a new random heading every so often
+    Wander { seconds_until_turn: f32 },
+
+    /// Hostile to the player, see the `ai` module
+    Enemy(Enemy),
+
+    /// Flies in a straight line until it hits something, see the `projectiles` module
+    Projectile(Projectile),
+
+    /// Left behind when a projectile hits something, and removed once it has finished
+    Explosion { seconds_left: f32 },
+
+    /// Collected by the player when they walk over it, see the `items` module
+    Pickup(Item),
+}
+
+pub struct Entity {
+    pub id: EntityId,
+    pub kind: EntityKind,
+    pub position: Vector,
+    pub facing: Radians,
+    pub collision_size: u16,
+    pub move_speed: f32,
+    pub sprite: Sprite,
+    pub behaviour: Behaviour,
+    pub health: i32,
+
+    /// The route the entity is currently following, if any
+    pub path: Vec<CellPosition>,
+    pub path_movement: Movement,
+
+    /// If true other entities and the player cannot pass through this entity
+    pub solid: bool,
+}
+
+pub struct EntityStore {
+    entities: Vec<Entity>,
+    next_id: EntityId,
+}
+
+impl EntityKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntityKind::Barrel => "barrel",
+            EntityKind::Pillar => "pillar",
+            EntityKind::Lamp => "lamp",
+            EntityKind::Rat => "rat",
+            EntityKind::Guard => "guard",
+            EntityKind::PatrolGuard => "patrol_guard",
+            EntityKind::Mutant => "mutant",
+            EntityKind::Rocket => "rocket",
+            EntityKind::Fireball => "fireball",
+            EntityKind::HealthPack => "health_pack",
+            EntityKind::AmmoClip => "ammo_clip",
+            EntityKind::GoldKey => "gold_key",
+            EntityKind::SilverKey => "silver_key",
+            EntityKind::Treasure => "treasure",
+            EntityKind::MachineGun => "machine_gun",
+            EntityKind::RocketLauncher => "rocket_launcher",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "barrel" => Some(EntityKind::Barrel),
+            "pillar" => Some(EntityKind::Pillar),
+            "lamp" => Some(EntityKind::Lamp),
+            "rat" => Some(EntityKind::Rat),
+            "guard" => Some(EntityKind::Guard),
+            "patrol_guard" => Some(EntityKind::PatrolGuard),
+            "mutant" => Some(EntityKind::Mutant),
+            "rocket" => Some(EntityKind::Rocket),
+            "fireball" => Some(EntityKind::Fireball),
+            "health_pack" => Some(EntityKind::HealthPack),
+            "ammo_clip" => Some(EntityKind::AmmoClip),
+            "gold_key" => Some(EntityKind::GoldKey),
+            "silver_key" => Some(EntityKind::SilverKey),
+            "treasure" => Some(EntityKind::Treasure),
+            "machine_gun" => Some(EntityKind::MachineGun),
+            "rocket_launcher" => Some(EntityKind::RocketLauncher),
+            _ => None,
+        }
+    }
+}
+
+impl Entity {
+    fn new(id: EntityId, kind: EntityKind, position: Vector) -> Self {
+        const GUARD_HEALTH: i32 = 25;
+        const MUTANT_HEALTH: i32 = 45;
+
+        // Patrolling guards stick to the grid, like the patrol routes they walk
+        let path_movement = match kind {
+            EntityKind::PatrolGuard => Movement::FourWay,
+            _ => Movement::EightWay,
+        };
+
+        let (sprite, behaviour, collision_size, solid, health) = match kind {
+            EntityKind::Barrel => (Sprite::Barrel, Behaviour::Static, 2, true, 0),
+            EntityKind::Pillar => (Sprite::Pillar, Behaviour::Static, 2, true, 0),
+            EntityKind::Lamp => (Sprite::Lamp, Behaviour::Static, 1, false, 0),
+            EntityKind::Rat => (Sprite::Rat, Behaviour::Wander { seconds_until_turn: 0.0 }, 1, false, 0),
+            EntityKind::Guard => {
+                let enemy = Enemy::new(EnemyState::Idle, EnemyAttack::Hitscan, GUARD_HEALTH);
+                (Sprite::GuardStand, Behaviour::Enemy(enemy), 2, true, GUARD_HEALTH)
+            }
+
+            EntityKind::PatrolGuard => {
+                let enemy = Enemy::new(EnemyState::Patrol, EnemyAttack::Hitscan, GUARD_HEALTH);
+                (Sprite::GuardWalk1, Behaviour::Enemy(enemy), 2, true, GUARD_HEALTH)
+            }
+
+            EntityKind::Mutant => {
+                let enemy = Enemy::new(EnemyState::Idle, EnemyAttack::Fireball, MUTANT_HEALTH);
+                (Sprite::MutantStand, Behaviour::Enemy(enemy), 2, true, MUTANT_HEALTH)
+            }
+
+            EntityKind::Rocket => {
+                let projectile = Projectile::new(None, 40.0, 30, 10.0);
+                (Sprite::Rocket, Behaviour::Projectile(projectile), 1, false, 0)
+            }
+
+            EntityKind::Fireball => {
+                let projectile = Projectile::new(None, 20.0, 12, 0.0);
+                (Sprite::Fireball, Behaviour::Projectile(projectile), 1, false, 0)
+            }
+
+            EntityKind::HealthPack => (Sprite::HealthPack, Behaviour::Pickup(Item::Health(25)), 2, false, 0),
+            EntityKind::AmmoClip => (Sprite::AmmoClip, Behaviour::Pickup(Item::Ammo(8)), 2, false, 0),
+            EntityKind::GoldKey => (Sprite::GoldKey, Behaviour::Pickup(Item::Key(KeyColor::Gold)), 2, false, 0),
+            EntityKind::SilverKey => (Sprite::SilverKey, Behaviour::Pickup(Item::Key(KeyColor::Silver)), 2, false, 0),
+            EntityKind::Treasure => (Sprite::Treasure, Behaviour::Pickup(Item::Treasure(100)), 2, false, 0),
+            EntityKind::MachineGun => {
+                (Sprite::MachineGun, Behaviour::Pickup(Item::Weapon(WeaponKind::MachineGun)), 2, false, 0)
+            }
+
+            EntityKind::RocketLauncher => {
+                (Sprite::RocketLauncher, Behaviour::Pickup(Item::Weapon(WeaponKind::RocketLauncher)), 2, false, 0)
+            }
+        };
+
+        Entity {
+            id,
+            kind,
+            position,
+            facing: Radians(0_f32),
+            collision_size,
+            move_speed: 4_f32,
+            sprite,
+            behaviour,
+            health,
+            path: Vec::new(),
+            path_movement,
+            solid,
         }
     }
-}
\ No newline at end of file
+}
+
+impl EntityStore {
+    pub fn new() -> Self {
+        EntityStore {
+            entities: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn spawn(&mut self, kind: EntityKind, position: Vector) -> EntityId {
+        let id = self.next_id;
+        self.spawn_with_id(id, kind, position);
+
+        id
+    }
+
+    /// Spawns an entity with a specific id, such as when restoring a saved game.  Any existing
+    /// entity with the same id is replaced.
+    pub fn spawn_with_id(&mut self, id: EntityId, kind: EntityKind, position: Vector) {
+        self.entities.retain(|x| x.id != id);
+        self.entities.push(Entity::new(id, kind, position));
+        self.next_id = self.next_id.max(id + 1);
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.iter().find(|x| x.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.iter_mut().find(|x| x.id == id)
+    }
+
+    pub fn remove(&mut self, id: EntityId) {
+        self.entities.retain(|x| x.id != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.entities.iter_mut()
+    }
+}
+
+impl Default for EntityStore {
+    fn default() -> Self {
+        EntityStore::new()
+    }
+}
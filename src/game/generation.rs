@@ -0,0 +1,606 @@
+use std::collections::VecDeque;
+use std::io;
+use crate::core::random::Random;
+use crate::game::map::{Map, CellType, ParsedCell, SpawnType};
+use crate::game::entities::EntityKind;
+use crate::game::text_format::{parse_value, invalid_data};
+
+/// Generated map ids are this followed by the algorithm, size and seed, such as
+/// `generate:maze:31x31:42`.  Everything needed to generate the map again is in its id, so saves,
+/// replays and episodes work with generated maps like any other.
+const GENERATED_ID_PREFIX: &str = "generate";
+
+pub const DEFAULT_WIDTH: usize = 32;
+pub const DEFAULT_HEIGHT: usize = 32;
+const MIN_SIZE: usize = 9;
+const MAX_SIZE: usize = 256;
+
+/// How many cells of reachable floor there are for every enemy or item placed
+const CELLS_PER_ENTITY: usize = 30;
+
+/// Enemies and items aren't placed this close to the player's spawn
+const MIN_ENTITY_DISTANCE: usize = 4;
+
+/// What gets placed around generated maps.  Guards are the only solid ones, so they're kept out of
+/// corridors a single cell wide, where nothing could get past them.
+const POPULATION: [EntityKind; 6] = [
+    EntityKind::Guard,
+    EntityKind::Guard,
+    EntityKind::Rat,
+    EntityKind::HealthPack,
+    EntityKind::AmmoClip,
+    EntityKind::Treasure,
+];
+
+const MAX_ROOMS: usize = 12;
+const ROOM_ATTEMPTS: usize = 200;
+const MIN_ROOM_SIZE: usize = 3;
+const MAX_ROOM_SIZE: usize = 8;
+
+/// Caves start out as noise with this percentage of walls, which is smoothed into open caverns
+const CAVE_WALL_PERCENT: u32 = 45;
+const CAVE_SMOOTHING_STEPS: usize = 5;
+
+/// A cell becomes a wall while smoothing if at least this many of the eight cells around it are
+const CAVE_WALL_NEIGHBOURS: usize = 5;
+
+/// Caves whose largest cavern covers less of the map than this percentage are generated again,
+/// up to the maximum number of attempts
+const MIN_CAVE_FLOOR_PERCENT: usize = 35;
+const MAX_CAVE_ATTEMPTS: usize = 20;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Algorithm {
+    /// A perfect maze carved by a recursive backtracker, with exactly one path between any two
+    /// cells
+    Maze,
+
+    /// Rectangular rooms joined up by corridors
+    Rooms,
+
+    /// Open caverns grown by a cellular automaton
+    Caves,
+}
+
+/// Open and solid cells while a map is being generated
+struct Grid {
+    width: usize,
+    height: usize,
+    open: Vec<bool>,
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Maze => "maze",
+            Algorithm::Rooms => "rooms",
+            Algorithm::Caves => "caves",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "maze" => Some(Algorithm::Maze),
+            "rooms" => Some(Algorithm::Rooms),
+            "caves" => Some(Algorithm::Caves),
+            _ => None,
+        }
+    }
+
+    fn wall(&self) -> CellType {
+        match self {
+            Algorithm::Maze => CellType::BrickWall,
+            Algorithm::Rooms => CellType::BlueWall,
+            Algorithm::Caves => CellType::WoodWall,
+        }
+    }
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid { width, height, open: vec![false; width * height] }
+    }
+
+    fn is_open(&self, row: usize, col: usize) -> bool {
+        row < self.height && col < self.width && self.open[row * self.width + col]
+    }
+
+    /// Cells on the edge of the map always stay solid, so the map is enclosed
+    fn set_open(&mut self, row: usize, col: usize, open: bool) {
+        if row > 0 && col > 0 && row < self.height - 1 && col < self.width - 1 {
+            self.open[row * self.width + col] = open;
+        }
+    }
+
+    /// Returns true if the cell is walled in on both sides, like a corridor a single cell wide
+    fn is_corridor(&self, row: usize, col: usize) -> bool {
+        let walled_vertically = row == 0 || (!self.is_open(row - 1, col) && !self.is_open(row + 1, col));
+        let walled_horizontally = col == 0 || (!self.is_open(row, col - 1) && !self.is_open(row, col + 1));
+        walled_vertically || walled_horizontally
+    }
+
+    fn open_cells(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+            .filter(|(row, col)| self.is_open(*row, *col))
+            .collect()
+    }
+
+    /// The cells next to the cell, in a fixed order so generation is the same every time
+    fn neighbours(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(4);
+        if row > 0 {
+            neighbours.push((row - 1, col));
+        }
+
+        if col + 1 < self.width {
+            neighbours.push((row, col + 1));
+        }
+
+        if row + 1 < self.height {
+            neighbours.push((row + 1, col));
+        }
+
+        if col > 0 {
+            neighbours.push((row, col - 1));
+        }
+
+        neighbours
+    }
+
+    /// How many steps it takes to walk to each cell from the start, if it can be reached at all
+    fn distances_from(&self, start: (usize, usize)) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.width * self.height];
+        let mut queue = VecDeque::new();
+        distances[start.0 * self.width + start.1] = Some(0);
+        queue.push_back((start, 0));
+        while let Some(((row, col), distance)) = queue.pop_front() {
+            for (next_row, next_col) in self.neighbours(row, col) {
+                let index = next_row * self.width + next_col;
+                if self.open[index] && distances[index].is_none() {
+                    distances[index] = Some(distance + 1);
+                    queue.push_back(((next_row, next_col), distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+}
+
+/// Returns true if the map id asks for a generated map rather than naming a map file
+pub fn is_generated_id(id: &str) -> bool {
+    id.starts_with(&format!("{}:", GENERATED_ID_PREFIX))
+}
+
+pub fn generated_map_id(algorithm: Algorithm, width: usize, height: usize, seed: u64) -> String {
+    format!("{}:{}:{}x{}:{}", GENERATED_ID_PREFIX, algorithm.name(), width, height, seed)
+}
+
+/// Parses a map size such as `48x32`
+pub fn parse_size(value: &str) -> Option<(usize, usize)> {
+    let (width, height) = value.split_once('x')?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    let valid = MIN_SIZE..=MAX_SIZE;
+    if valid.contains(&width) && valid.contains(&height) {
+        Some((width, height))
+    } else {
+        None
+    }
+}
+
+/// Generates the map described by a generated map id
+pub fn load_generated_map(id: &str) -> io::Result<Map> {
+    let parts = id.split(':').collect::<Vec<_>>();
+    if parts.len() != 4 || parts[0] != GENERATED_ID_PREFIX {
+        return Err(invalid_data(format!("Invalid generated map '{}', expected {}:<algorithm>:<width>x<height>:<seed>",
+                                        id, GENERATED_ID_PREFIX)));
+    }
+
+    let algorithm = match Algorithm::from_name(parts[1]) {
+        Some(x) => x,
+        None => return Err(invalid_data(format!("Unknown map generator '{}', expected maze, rooms or caves", parts[1]))),
+    };
+
+    let (width, height) = match parse_size(parts[2]) {
+        Some(x) => x,
+        None => return Err(invalid_data(format!("Invalid map size '{}', expected {} to {} cells on each side",
+                                                parts[2], MIN_SIZE, MAX_SIZE))),
+    };
+
+    let seed = parse_value(Some(parts[3]), "map seed")?;
+    Ok(generate(algorithm, width, height, seed))
+}
+
+/// Generates an enclosed map with a player spawn and an exit the player can reach.  The same
+/// algorithm, size and seed always generate the same map.
+pub fn generate(algorithm: Algorithm, width: usize, height: usize, seed: u64) -> Map {
+    let mut random = Random::new(seed);
+    let (grid, start) = match algorithm {
+        Algorithm::Maze => generate_maze(width, height, &mut random),
+        Algorithm::Rooms => generate_rooms(width, height, &mut random),
+        Algorithm::Caves => generate_caves(width, height, &mut random),
+    };
+
+    let id = generated_map_id(algorithm, width, height, seed);
+    finish_map(&id, grid, start, algorithm.wall(), &mut random)
+}
+
+fn generate_maze(width: usize, height: usize, random: &mut Random) -> (Grid, (usize, usize)) {
+    let mut grid = Grid::new(width, height);
+    let start = (1, 1);
+    grid.set_open(start.0, start.1, true);
+
+    // Passages run between cells on odd rows and columns, leaving walls on the even ones
+    let mut stack = vec![start];
+    while let Some(&(row, col)) = stack.last() {
+        let mut unvisited = Vec::with_capacity(4);
+        if row >= 3 {
+            unvisited.push((row - 2, col));
+        }
+
+        if col + 3 < width {
+            unvisited.push((row, col + 2));
+        }
+
+        if row + 3 < height {
+            unvisited.push((row + 2, col));
+        }
+
+        if col >= 3 {
+            unvisited.push((row, col - 2));
+        }
+
+        unvisited.retain(|(next_row, next_col)| !grid.is_open(*next_row, *next_col));
+
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (next_row, next_col) = unvisited[random.next_below(unvisited.len() as u32) as usize];
+        grid.set_open((row + next_row) / 2, (col + next_col) / 2, true);
+        grid.set_open(next_row, next_col, true);
+        stack.push((next_row, next_col));
+    }
+
+    (grid, start)
+}
+
+fn generate_rooms(width: usize, height: usize, random: &mut Random) -> (Grid, (usize, usize)) {
+    let mut grid = Grid::new(width, height);
+    let mut rooms: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let max_width = MAX_ROOM_SIZE.min(width - 4);
+    let max_height = MAX_ROOM_SIZE.min(height - 4);
+    for _ in 0..ROOM_ATTEMPTS {
+        if rooms.len() == MAX_ROOMS {
+            break;
+        }
+
+        let room_width = MIN_ROOM_SIZE + random.next_below((max_width - MIN_ROOM_SIZE + 1) as u32) as usize;
+        let room_height = MIN_ROOM_SIZE + random.next_below((max_height - MIN_ROOM_SIZE + 1) as u32) as usize;
+        let row = 1 + random.next_below((height - room_height - 1) as u32) as usize;
+        let col = 1 + random.next_below((width - room_width - 1) as u32) as usize;
+
+        // Rooms keep at least one wall between them, so they don't merge into odd shapes
+        let overlaps = rooms.iter().any(|&(other_row, other_col, other_height, other_width)| {
+            row <= other_row + other_height && other_row <= row + room_height
+                && col <= other_col + other_width && other_col <= col + room_width
+        });
+
+        if !overlaps {
+            rooms.push((row, col, room_height, room_width));
+        }
+    }
+
+    for (row, col, room_height, room_width) in &rooms {
+        for room_row in *row..row + room_height {
+            for room_col in *col..col + room_width {
+                grid.set_open(room_row, room_col, true);
+            }
+        }
+    }
+
+    // Each room is joined to the one placed before it, so every room can be reached from the first
+    let centers = rooms.iter()
+        .map(|(row, col, room_height, room_width)| (row + room_height / 2, col + room_width / 2))
+        .collect::<Vec<_>>();
+
+    for pair in centers.windows(2) {
+        let ((from_row, from_col), (to_row, to_col)) = (pair[0], pair[1]);
+        let corner = if random.next_below(2) == 0 { (from_row, to_col) } else { (to_row, from_col) };
+        carve_line(&mut grid, (from_row, from_col), corner);
+        carve_line(&mut grid, corner, (to_row, to_col));
+    }
+
+    (grid, centers[0])
+}
+
+/// Opens every cell on a straight line between two cells in the same row or column
+fn carve_line(grid: &mut Grid, from: (usize, usize), to: (usize, usize)) {
+    for row in from.0.min(to.0)..=from.0.max(to.0) {
+        for col in from.1.min(to.1)..=from.1.max(to.1) {
+            grid.set_open(row, col, true);
+        }
+    }
+}
+
+fn generate_caves(width: usize, height: usize, random: &mut Random) -> (Grid, (usize, usize)) {
+    let interior = (width - 2) * (height - 2);
+    let mut best: Option<(Grid, Vec<(usize, usize)>)> = None;
+    for _ in 0..MAX_CAVE_ATTEMPTS {
+        let mut grid = Grid::new(width, height);
+        for row in 0..height {
+            for col in 0..width {
+                grid.set_open(row, col, random.next_below(100) >= CAVE_WALL_PERCENT);
+            }
+        }
+
+        for _ in 0..CAVE_SMOOTHING_STEPS {
+            grid = smooth_cave(&grid);
+        }
+
+        let cavern = largest_cavern(&grid);
+        match &best {
+            Some((_, best_cavern)) if best_cavern.len() >= cavern.len() => (),
+            _ => best = Some((grid, cavern)),
+        }
+
+        if best.as_ref().unwrap().1.len() * 100 >= interior * MIN_CAVE_FLOOR_PERCENT {
+            break;
+        }
+    }
+
+    let (mut grid, cavern) = best.unwrap();
+
+    // Every attempt filling up with rock is unlikely, but would leave nowhere to spawn
+    if cavern.len() < 2 {
+        open_interior(&mut grid);
+        return (grid, (1, 1));
+    }
+
+    // Only the largest cavern is kept, as the rest can't be reached
+    let mut cave = Grid::new(width, height);
+    for (row, col) in &cavern {
+        cave.set_open(*row, *col, true);
+    }
+
+    let start = cavern[random.next_below(cavern.len() as u32) as usize];
+    (cave, start)
+}
+
+/// Opens every cell inside the edge of the map, leaving one big room
+fn open_interior(grid: &mut Grid) {
+    for row in 0..grid.height {
+        for col in 0..grid.width {
+            grid.set_open(row, col, true);
+        }
+    }
+}
+
+/// Cells surrounded by walls become walls, and the rest open up, with the edge of the map
+/// counting as wall
+fn smooth_cave(grid: &Grid) -> Grid {
+    let mut smoothed = Grid::new(grid.width, grid.height);
+    for row in 1..grid.height - 1 {
+        for col in 1..grid.width - 1 {
+            let walls = (row - 1..=row + 1)
+                .flat_map(|x| (col - 1..=col + 1).map(move |y| (x, y)))
+                .filter(|(x, y)| (*x, *y) != (row, col) && !grid.is_open(*x, *y))
+                .count();
+
+            smoothed.set_open(row, col, walls < CAVE_WALL_NEIGHBOURS);
+        }
+    }
+
+    smoothed
+}
+
+/// The open cells of the largest area that can all be walked between
+fn largest_cavern(grid: &Grid) -> Vec<(usize, usize)> {
+    let mut seen = vec![false; grid.width * grid.height];
+    let mut largest = Vec::new();
+    for start in grid.open_cells() {
+        if seen[start.0 * grid.width + start.1] {
+            continue;
+        }
+
+        let cavern = grid.distances_from(start)
+            .iter()
+            .enumerate()
+            .filter(|(_, distance)| distance.is_some())
+            .map(|(index, _)| (index / grid.width, index % grid.width))
+            .collect::<Vec<_>>();
+
+        for (row, col) in &cavern {
+            seen[row * grid.width + col] = true;
+        }
+
+        if cavern.len() > largest.len() {
+            largest = cavern;
+        }
+    }
+
+    largest
+}
+
+/// Turns the grid into a map, with the exit on the wall beside the floor furthest from the
+/// player's spawn, and enemies and items scattered around
+fn finish_map(id: &str, grid: Grid, start: (usize, usize), wall: CellType, random: &mut Random) -> Map {
+    let distances = grid.distances_from(start);
+    let mut map = Map::with_size(id, grid.width, grid.height);
+    for row in 0..grid.height {
+        for col in 0..grid.width {
+            // Floor that can't be reached is filled in rather than left as a hidden pocket
+            let reachable = distances[row * grid.width + col].is_some();
+            map.set_cell(row, col, if reachable { CellType::Empty } else { wall });
+        }
+    }
+
+    // Only floor beside a wall can have the exit switch next to it
+    let exit = grid.open_cells()
+        .into_iter()
+        .filter_map(|(row, col)| {
+            let exit_wall = grid.neighbours(row, col)
+                .into_iter()
+                .find(|(x, y)| !grid.is_open(*x, *y))?;
+
+            Some((distances[row * grid.width + col]?, exit_wall))
+        })
+        .fold(None, |furthest: Option<(usize, (usize, usize))>, candidate| match furthest {
+            Some(x) if x.0 >= candidate.0 => Some(x),
+            _ => Some(candidate),
+        });
+
+    if let Some((_, (row, col))) = exit {
+        map.set_cell(row, col, CellType::Exit);
+    }
+
+    map.set_contents(start.0, start.1, ParsedCell::Spawn(SpawnType::Player));
+
+    let mut candidates = grid.open_cells()
+        .into_iter()
+        .filter(|(row, col)| matches!(distances[row * grid.width + col], Some(x) if x >= MIN_ENTITY_DISTANCE))
+        .collect::<Vec<_>>();
+
+    let count = grid.open_cells().len() / CELLS_PER_ENTITY;
+    for _ in 0..count.min(candidates.len()) {
+        let (row, col) = candidates.swap_remove(random.next_below(candidates.len() as u32) as usize);
+        let kind = match POPULATION[random.next_below(POPULATION.len() as u32) as usize] {
+            EntityKind::Guard if grid.is_corridor(row, col) => EntityKind::Rat,
+            x => x,
+        };
+
+        map.set_contents(row, col, ParsedCell::Spawn(SpawnType::Entity(kind)));
+    }
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGORITHMS: [Algorithm; 3] = [Algorithm::Maze, Algorithm::Rooms, Algorithm::Caves];
+    const SIZES: [(usize, usize); 3] = [(MIN_SIZE, MIN_SIZE), (32, 20), (17, 41)];
+    const SEEDS: [u64; 4] = [0, 1, 42, 987_654_321];
+
+    fn each_map(test: impl Fn(Algorithm, &Map)) {
+        for algorithm in ALGORITHMS {
+            for (width, height) in SIZES {
+                for seed in SEEDS {
+                    test(algorithm, &generate(algorithm, width, height, seed));
+                }
+            }
+        }
+    }
+
+    fn contents(map: &Map) -> Vec<Option<ParsedCell>> {
+        (0..map.height).flat_map(|row| (0..map.width).map(move |col| map.contents_at(row, col))).collect()
+    }
+
+    /// Which cells can be walked to from the player's spawn
+    fn reachable_from_spawn(map: &Map) -> Vec<bool> {
+        let spawn = map.spawns.iter().find(|x| x.entity == SpawnType::Player).unwrap();
+        let mut reached = vec![false; map.width * map.height];
+        let mut queue = VecDeque::from([(spawn.row, spawn.col)]);
+        reached[spawn.row * map.width + spawn.col] = true;
+        while let Some((row, col)) = queue.pop_front() {
+            for (next_row, next_col) in [(row - 1, col), (row, col + 1), (row + 1, col), (row, col - 1)] {
+                let index = next_row * map.width + next_col;
+                if map.is_passable(next_row, next_col) && !reached[index] {
+                    reached[index] = true;
+                    queue.push_back((next_row, next_col));
+                }
+            }
+        }
+
+        reached
+    }
+
+    #[test]
+    fn same_seed_and_size_generate_the_same_map() {
+        each_map(|algorithm, map| {
+            let again = load_generated_map(&map.id).unwrap();
+            assert_eq!(map.id, again.id);
+            assert_eq!((map.width, map.height), (again.width, again.height), "{}", algorithm.name());
+            assert!(contents(map) == contents(&again), "{} differs", map.id);
+        });
+
+        assert!(contents(&generate(Algorithm::Maze, 32, 32, 1)) != contents(&generate(Algorithm::Maze, 32, 32, 2)));
+    }
+
+    #[test]
+    fn boundary_is_closed() {
+        each_map(|_, map| {
+            for row in 0..map.height {
+                for col in 0..map.width {
+                    let edge = row == 0 || col == 0 || row == map.height - 1 || col == map.width - 1;
+                    assert!(!edge || !map.is_passable(row, col), "{} is open at {},{}", map.id, row, col);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn every_open_cell_and_the_exit_are_reachable_from_the_spawn() {
+        each_map(|_, map| {
+            let reached = reachable_from_spawn(map);
+            let mut exits = 0;
+            for row in 0..map.height {
+                for col in 0..map.width {
+                    if map.is_passable(row, col) {
+                        assert!(reached[row * map.width + col], "{} can't reach {},{}", map.id, row, col);
+                    }
+
+                    if map.cell_at(row, col) == Some(CellType::Exit) {
+                        exits += 1;
+                        let beside = [(row.wrapping_sub(1), col), (row, col + 1), (row + 1, col), (row, col.wrapping_sub(1))];
+                        assert!(beside.iter().any(|(x, y)| map.is_passable(*x, *y) && reached[x * map.width + y]),
+                                "{} can't reach the exit", map.id);
+                    }
+                }
+            }
+
+            assert_eq!(exits, 1, "{}", map.id);
+        });
+    }
+
+    #[test]
+    fn guards_are_not_placed_in_corridors() {
+        each_map(|_, map| {
+            for spawn in map.spawns.iter().filter(|x| x.entity == SpawnType::Entity(EntityKind::Guard)) {
+                let (row, col) = (spawn.row, spawn.col);
+                let walled_vertically = !map.is_passable(row - 1, col) && !map.is_passable(row + 1, col);
+                let walled_horizontally = !map.is_passable(row, col - 1) && !map.is_passable(row, col + 1);
+                assert!(!walled_vertically && !walled_horizontally, "{} has a guard in a corridor", map.id);
+            }
+        });
+    }
+
+    #[test]
+    fn sizes_and_ids_round_trip() {
+        for (width, height) in SIZES {
+            assert_eq!(parse_size(&format!("{}x{}", width, height)), Some((width, height)));
+        }
+
+        assert_eq!(parse_size(&format!("{}x{}", MIN_SIZE - 1, MIN_SIZE)), None);
+        assert_eq!(parse_size(&format!("{}x{}", MAX_SIZE, MAX_SIZE + 1)), None);
+        assert_eq!(parse_size("32"), None);
+
+        for algorithm in ALGORITHMS {
+            let id = generated_map_id(algorithm, 48, 32, 42);
+            assert!(is_generated_id(&id));
+
+            let parts = id.split(':').collect::<Vec<_>>();
+            assert_eq!(Algorithm::from_name(parts[1]), Some(algorithm));
+            assert_eq!(parse_size(parts[2]), Some((48, 32)));
+            assert_eq!(parts[3], "42");
+            assert_eq!(load_generated_map(&id).unwrap().id, id);
+        }
+
+        assert!(load_generated_map("generate:tunnels:32x32:1").is_err());
+        assert!(load_generated_map("generate:maze:4x4:1").is_err());
+        assert!(load_generated_map("generate:maze:32x32").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::game::entities::Player;
+use crate::game::weapons::WeaponKind;
+
+/// The most ammo the player can carry
+pub const MAX_AMMO: u32 = 99;
+
+/// Ammo that comes loaded in a weapon when it is picked up
+const WEAPON_PICKUP_AMMO: u32 = 6;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeyColor { Gold, Silver }
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Item {
+    Health(i32),
+    Ammo(u32),
+    Key(KeyColor),
+
+    /// Worth the specified number of points
+    Treasure(u32),
+    Weapon(WeaponKind),
+}
+
+/// Everything the player has collected that isn't a weapon or ammo
+pub struct Inventory {
+    pub keys: Vec<KeyColor>,
+    pub treasure: u32,
+}
+
+impl KeyColor {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyColor::Gold => "gold",
+            KeyColor::Silver => "silver",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gold" => Some(KeyColor::Gold),
+            "silver" => Some(KeyColor::Silver),
+            _ => None,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Inventory {
+            keys: Vec::new(),
+            treasure: 0,
+        }
+    }
+
+    pub fn has_key(&self, color: KeyColor) -> bool {
+        self.keys.contains(&color)
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::new()
+    }
+}
+
+/// Gives the item to the player.  Returns false if the player has no use for it, such as health
+/// when they are already at full health, in which case the item should be left where it is.
+pub fn pick_up(player: &mut Player, item: Item) -> bool {
+    match item {
+        Item::Health(amount) => {
+            if player.health >= player.max_health {
+                return false;
+            }
+
+            player.health = (player.health + amount).min(player.max_health);
+        }
+
+        Item::Ammo(amount) => {
+            if player.ammo >= MAX_AMMO {
+                return false;
+            }
+
+            player.ammo = (player.ammo + amount).min(MAX_AMMO);
+        }
+
+        Item::Key(color) => {
+            if !player.inventory.has_key(color) {
+                player.inventory.keys.push(color);
+            }
+        }
+
+        Item::Treasure(points) => player.inventory.treasure += points,
+        Item::Weapon(weapon) => {
+            if !player.weapons.contains(&weapon) {
+                player.weapons.push(weapon);
+                player.weapon = weapon;
+            }
+
+            player.ammo = (player.ammo + WEAPON_PICKUP_AMMO).min(MAX_AMMO);
+        }
+    }
+
+    true
+}
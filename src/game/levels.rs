@@ -0,0 +1,78 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::game::map::{Map, CellType, DEFAULT_MAP_ID};
+use crate::game::entities::{EntityStore, Behaviour};
+use crate::game::ai::EnemyState;
+use crate::game::text_format::invalid_data;
+use crate::game::generation::is_generated_id;
+
+/// How long the level complete screen shows before the player can continue
+pub const CONTINUE_DELAY_SECONDS: f32 = 1.0;
+
+/// How the player did on a level, shown once they reach its exit
+#[derive(Copy, Clone)]
+pub struct LevelStats {
+    pub seconds: f32,
+    pub kills: u32,
+    pub total_kills: u32,
+    pub secrets_found: u32,
+    pub total_secrets: u32,
+}
+
+impl LevelStats {
+    /// Every enemy counts towards the total, and the dead ones count as kills.  Every secret wall
+    /// the level started with counts towards the total secrets.
+    pub fn new(seconds: f32, entities: &EntityStore, initial_map: &Map, secrets_found: u32) -> Self {
+        let enemies = entities.iter()
+            .filter_map(|x| match x.behaviour {
+                Behaviour::Enemy(enemy) => Some(enemy),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        LevelStats {
+            seconds,
+            kills: enemies.iter().filter(|x| x.state == EnemyState::Death).count() as u32,
+            total_kills: enemies.len() as u32,
+            secrets_found,
+            total_secrets: count_secrets(initial_map),
+        }
+    }
+}
+
+/// Reads the ids of the maps in an episode, which are listed one per line in the order they are
+/// played.  Paths are relative to the episode file, while built in and generated maps are used as
+/// they are.
+pub fn load_episode(path: &str) -> io::Result<Vec<String>> {
+    let contents = fs::read_to_string(path)?;
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let map_ids = contents.lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| match x {
+            DEFAULT_MAP_ID => x.to_owned(),
+            x if is_generated_id(x) => x.to_owned(),
+            x => directory.join(x).to_string_lossy().into_owned(),
+        })
+        .collect::<Vec<_>>();
+
+    if map_ids.is_empty() {
+        return Err(invalid_data(format!("Episode '{}' has no maps", path)));
+    }
+
+    Ok(map_ids)
+}
+
+fn count_secrets(map: &Map) -> u32 {
+    let mut count = 0;
+    for row in 0..map.height {
+        for col in 0..map.width {
+            if map.cell_at(row, col) == Some(CellType::SecretWall) {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
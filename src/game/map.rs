@@ -1,46 +1,220 @@
-#[derive(Copy, Clone, Debug)]
-pub enum CellType { Empty, BrickWall, BlueWall, WoodWall }
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::game::entities::EntityKind;
+use crate::game::items::KeyColor;
+use crate::game::tiled::{is_tiled_path, load_tiled_map};
+use crate::game::wolf3d::{is_wolf3d_path, load_wolf3d_map};
+use crate::game::generation::{is_generated_id, load_generated_map};
+use crate::game::triggers::Trigger;
+use crate::game::text_format::invalid_data;
 
-#[derive(Eq, PartialEq)]
-pub enum SpawnType {Player}
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CellType {
+    Empty, BrickWall, BlueWall, WoodWall,
 
-enum ParsedCell {
+    /// Blocks the way like a wall until it is opened, which needs a key if the door is locked
+    Door { lock: Option<KeyColor> },
+
+    /// A switch on the wall that completes the level when used
+    Exit,
+
+    /// Looks like a brick wall, but opens like a door and counts as a secret found
+    SecretWall,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum SpawnType { Player, Entity(EntityKind) }
+
+/// What a single character of a map file holds.  Cells with spawns are always empty.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum ParsedCell {
     Environmental(CellType),
     Spawn(SpawnType)
 }
 
+#[derive(Clone)]
 pub struct SpawnLocation {
     pub entity: SpawnType,
     pub row: usize,
     pub col: usize,
 }
 
+#[derive(Clone)]
 pub struct Map {
+    pub id: String,
     pub width: usize,
     pub height: usize,
     pub units_per_cell: u32,
     pub spawns: Vec<SpawnLocation>,
+
+    /// The walls whose textures are drawn on the floor and ceiling, which are flat colors if unset
+    pub floor_texture: Option<CellType>,
+    pub ceiling_texture: Option<CellType>,
+    pub triggers: Vec<Trigger>,
+
+    /// The Rhai script running the level's logic, relative to the map file
+    pub script: Option<String>,
     cells: Vec<CellType>,
 }
 
+/// A map file that could be read, but describes a level that can't be played, such as one with
+/// no player spawn.  Loading fails with this rather than a plain error so map checks can tell a
+/// broken level apart from a broken file.
+#[derive(Debug)]
+pub struct MapProblem(pub String);
+
+pub const DEFAULT_MAP_ID: &str = "default";
+
+/// Every character a map file can contain and what it means
+pub const GLYPHS: [(char, ParsedCell); 24] = [
+    (' ', ParsedCell::Environmental(CellType::Empty)),
+    ('x', ParsedCell::Environmental(CellType::BrickWall)),
+    ('b', ParsedCell::Environmental(CellType::BlueWall)),
+    ('w', ParsedCell::Environmental(CellType::WoodWall)),
+    ('d', ParsedCell::Environmental(CellType::Door { lock: None })),
+    ('G', ParsedCell::Environmental(CellType::Door { lock: Some(KeyColor::Gold) })),
+    ('S', ParsedCell::Environmental(CellType::Door { lock: Some(KeyColor::Silver) })),
+    ('e', ParsedCell::Environmental(CellType::Exit)),
+    ('X', ParsedCell::Environmental(CellType::SecretWall)),
+    ('@', ParsedCell::Spawn(SpawnType::Player)),
+    ('o', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Barrel))),
+    ('i', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Pillar))),
+    ('*', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Lamp))),
+    ('r', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Rat))),
+    ('g', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Guard))),
+    ('p', ParsedCell::Spawn(SpawnType::Entity(EntityKind::PatrolGuard))),
+    ('m', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Mutant))),
+    ('+', ParsedCell::Spawn(SpawnType::Entity(EntityKind::HealthPack))),
+    ('a', ParsedCell::Spawn(SpawnType::Entity(EntityKind::AmmoClip))),
+    ('k', ParsedCell::Spawn(SpawnType::Entity(EntityKind::GoldKey))),
+    ('K', ParsedCell::Spawn(SpawnType::Entity(EntityKind::SilverKey))),
+    ('$', ParsedCell::Spawn(SpawnType::Entity(EntityKind::Treasure))),
+    ('3', ParsedCell::Spawn(SpawnType::Entity(EntityKind::MachineGun))),
+    ('4', ParsedCell::Spawn(SpawnType::Entity(EntityKind::RocketLauncher))),
+];
+
 static DEFAULT_MAP: &str = "
 xxxxxxxxxx
-x        x
+x$  *  g x
 x xxxxxx x
-x        x
-xxxxx bbbb
+xp 3   o x
+xxxxxGbbbb
 x @ x b  b
-x   x b  b
-x   x b  b
-x xxx    b
-x        b
-w w w w ww
+x   x b* b
+x i x b  b
+xdxxx  r b
+x   m o  b
+wkw+waw4ww
 wwwwwwwwww
 ";
 
+impl CellType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CellType::Empty => "empty",
+            CellType::BrickWall => "brick_wall",
+            CellType::BlueWall => "blue_wall",
+            CellType::WoodWall => "wood_wall",
+            CellType::Door { lock: None } => "door",
+            CellType::Door { lock: Some(KeyColor::Gold) } => "gold_door",
+            CellType::Door { lock: Some(KeyColor::Silver) } => "silver_door",
+            CellType::Exit => "exit",
+            CellType::SecretWall => "secret_wall",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "empty" => Some(CellType::Empty),
+            "brick_wall" => Some(CellType::BrickWall),
+            "blue_wall" => Some(CellType::BlueWall),
+            "wood_wall" => Some(CellType::WoodWall),
+            "door" => Some(CellType::Door { lock: None }),
+            "gold_door" => Some(CellType::Door { lock: Some(KeyColor::Gold) }),
+            "silver_door" => Some(CellType::Door { lock: Some(KeyColor::Silver) }),
+            "exit" => Some(CellType::Exit),
+            "secret_wall" => Some(CellType::SecretWall),
+            _ => None,
+        }
+    }
+}
+
+impl ParsedCell {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParsedCell::Environmental(cell_type) => cell_type.name(),
+            ParsedCell::Spawn(SpawnType::Player) => "player",
+            ParsedCell::Spawn(SpawnType::Entity(kind)) => kind.name(),
+        }
+    }
+
+    /// The character the cell is written as in map files
+    pub fn glyph(&self) -> char {
+        match GLYPHS.iter().find(|(_, parsed_cell)| parsed_cell == self) {
+            Some((glyph, _)) => *glyph,
+            None => unreachable!(),
+        }
+    }
+}
+
 impl Map {
     pub fn new() -> Self {
-        string_map_to_map(DEFAULT_MAP)
+        string_map_to_map(DEFAULT_MAP_ID, DEFAULT_MAP).unwrap()
+    }
+
+    /// Creates a map with every cell empty and nothing spawned
+    pub fn with_size(id: &str, width: usize, height: usize) -> Self {
+        Map {
+            id: id.to_owned(),
+            width,
+            height,
+            spawns: Vec::new(),
+            floor_texture: None,
+            ceiling_texture: None,
+            triggers: Vec::new(),
+            script: None,
+            cells: vec![CellType::Empty; width * height],
+            units_per_cell: 5,
+        }
+    }
+
+    /// Loads the map with the specified identifier.  The identifier is either the name of a
+    /// built in map, a description of a map to generate, or the path to a map file, which is
+    /// imported if it was saved by Tiled or Wolfenstein 3D.
+    pub fn load(id: &str) -> io::Result<Self> {
+        if id == DEFAULT_MAP_ID {
+            return Ok(Map::new());
+        }
+
+        if is_generated_id(id) {
+            return load_generated_map(id);
+        }
+
+        if is_tiled_path(id) {
+            return load_tiled_map(id);
+        }
+
+        if is_wolf3d_path(id) {
+            return load_wolf3d_map(id);
+        }
+
+        let contents = fs::read_to_string(id)?;
+        string_map_to_map(id, &contents)
+    }
+
+    /// Where the level's script is, found relative to the map file's directory
+    pub fn script_path(&self) -> Option<PathBuf> {
+        let script = self.script.as_ref()?;
+        let directory = Path::new(&self.id).parent().unwrap_or_else(|| Path::new(""));
+        Some(directory.join(script))
+    }
+
+    /// Returns true if entities can walk through the cell
+    pub fn is_passable(&self, row: usize, col: usize) -> bool {
+        matches!(self.cell_at(row, col), Some(CellType::Empty))
     }
 
     pub fn cell_at(&self, row: usize, col: usize) -> Option<CellType> {
@@ -54,13 +228,109 @@ impl Map {
             Some(x) => Some(*x),
         }
     }
+
+    /// Changes the type of a cell, such as when a door is opened.  Anything caching information
+    /// about the map's layout, like the pathfinder, needs to be told about the change.
+    pub fn set_cell(&mut self, row: usize, col: usize, cell_type: CellType) {
+        if row < self.height && col < self.width {
+            self.cells[row * self.width + col] = cell_type;
+        }
+    }
+
+    /// What the cell holds as it would be written to a map file
+    pub fn contents_at(&self, row: usize, col: usize) -> Option<ParsedCell> {
+        let cell_type = self.cell_at(row, col)?;
+        match self.spawns.iter().find(|x| x.row == row && x.col == col) {
+            Some(spawn) => Some(ParsedCell::Spawn(spawn.entity)),
+            None => Some(ParsedCell::Environmental(cell_type)),
+        }
+    }
+
+    /// Replaces whatever is in the cell, including any spawn.  Spawns always leave the cell empty.
+    pub fn set_contents(&mut self, row: usize, col: usize, contents: ParsedCell) {
+        if row >= self.height || col >= self.width {
+            return;
+        }
+
+        self.spawns.retain(|x| x.row != row || x.col != col);
+        match contents {
+            ParsedCell::Environmental(cell_type) => self.set_cell(row, col, cell_type),
+            ParsedCell::Spawn(entity) => {
+                self.set_cell(row, col, CellType::Empty);
+                self.spawns.push(SpawnLocation { entity, row, col });
+            }
+        }
+    }
+
+    /// Writes the map out in the same format map files are loaded from
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut contents = String::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                contents.push(self.contents_at(row, col).unwrap().glyph());
+            }
+
+            contents.push('\n');
+        }
+
+        if let Some(floor) = self.floor_texture {
+            contents.push_str(&format!("floor {}\n", floor.name()));
+        }
+
+        if let Some(ceiling) = self.ceiling_texture {
+            contents.push_str(&format!("ceiling {}\n", ceiling.name()));
+        }
+
+        for trigger in &self.triggers {
+            contents.push_str(&trigger.to_line());
+            contents.push('\n');
+        }
+
+        if let Some(script) = &self.script {
+            contents.push_str(&format!("script {}\n", script));
+        }
+
+        fs::write(path, contents)
+    }
+}
+
+impl Default for Map {
+    fn default() -> Self {
+        Map::new()
+    }
+}
+
+impl fmt::Display for MapProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
-fn string_map_to_map(map: &str) -> Map {
+impl Error for MapProblem {}
+
+pub fn map_problem(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, MapProblem(message))
+}
+
+pub fn is_map_problem(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|x| x.is::<MapProblem>())
+}
+
+/// Says which file an imported map's error came from, keeping whether it is a problem with the map
+pub fn import_error(id: &str, err: io::Error) -> io::Error {
+    let message = format!("Failed to import '{}': {}", id, err);
+    if is_map_problem(&err) { map_problem(message) } else { invalid_data(message) }
+}
+
+fn string_map_to_map(id: &str, map: &str) -> io::Result<Map> {
     let mut width = 0;
     let mut height = 0;
     let mut cells = Vec::new();
     let mut spawns = Vec::new();
+    let mut triggers = Vec::new();
+    let mut script = None;
+    let mut floor_texture = None;
+    let mut ceiling_texture = None;
 
     let mut current_row = 0;
     let mut current_col;
@@ -69,15 +339,40 @@ fn string_map_to_map(map: &str) -> Map {
             continue;
         }
 
+        // Triggers are listed after the cells, and no cell is written with a 't'
+        if line.starts_with("trigger ") {
+            triggers.push(Trigger::parse(line)?);
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("script ") {
+            script = Some(path.trim().to_owned());
+            continue;
+        }
+
+        if let Some(wall) = line.strip_prefix("floor ") {
+            floor_texture = Some(parse_texture(wall)?);
+            continue;
+        }
+
+        if let Some(wall) = line.strip_prefix("ceiling ") {
+            ceiling_texture = Some(parse_texture(wall)?);
+            continue;
+        }
+
         if width == 0 {
             width = line.len();
         } else if width != line.len() {
-            panic!("Different lines have different widths.  Line of '{}' doesn't match previous line width of {}", line, width);
+            return Err(invalid_data(format!("Different lines have different widths.  Line of '{}' doesn't match previous line width of {}", line, width)));
         }
 
         current_col = 0;
         for character in line.chars() {
-            let parsed_cell = char_to_cell_type(character);
+            let parsed_cell = match char_to_cell_type(character) {
+                Some(x) => x,
+                None => return Err(invalid_data(format!("No known type of cell type for '{}' in line '{}'", character, line))),
+            };
+
             match parsed_cell {
                 ParsedCell::Environmental(cell_type) => cells.push(cell_type),
                 ParsedCell::Spawn(spawn_type) => {
@@ -97,19 +392,25 @@ fn string_map_to_map(map: &str) -> Map {
         current_row += 1;
     }
 
-    Map {
-        width, height, spawns, cells,
-        units_per_cell: 5,
+    if !spawns.iter().any(|x| x.entity == SpawnType::Player) {
+        return Err(map_problem(format!("Map '{}' has no player spawn", id)));
     }
+
+    Ok(Map {
+        id: id.to_owned(),
+        width, height, spawns, cells, triggers, script, floor_texture, ceiling_texture,
+        units_per_cell: 5,
+    })
 }
 
-fn char_to_cell_type(character: char) -> ParsedCell {
-    match character {
-        ' ' => ParsedCell::Environmental(CellType::Empty),
-        'x' => ParsedCell::Environmental(CellType::BrickWall),
-        'b' => ParsedCell::Environmental(CellType::BlueWall),
-        'w' => ParsedCell::Environmental(CellType::WoodWall),
-        '@' => ParsedCell::Spawn(SpawnType::Player),
-        _ => panic!("No known type of cell type for '{}'", character),
+/// Floors and ceilings are drawn with the texture of a wall
+fn parse_texture(name: &str) -> io::Result<CellType> {
+    match CellType::from_name(name.trim()) {
+        Some(CellType::Empty) | None => Err(invalid_data(format!("Floors and ceilings have to be a type of wall, not '{}'", name.trim()))),
+        Some(x) => Ok(x),
     }
+}
+
+fn char_to_cell_type(character: char) -> Option<ParsedCell> {
+    GLYPHS.iter().find(|(glyph, _)| *glyph == character).map(|(_, parsed_cell)| *parsed_cell)
 }
\ No newline at end of file
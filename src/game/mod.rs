@@ -1,19 +1,120 @@
-use crate::game::map::{Map, SpawnType, CellType};
-use crate::game::entities::Player;
+use crate::game::map::{Map, SpawnType, SpawnLocation, CellType};
+use crate::game::entities::{Player, EntityStore, EntityKind, Behaviour, Sprite};
+use std::io;
 use std::time::Duration;
+use std::collections::BTreeMap;
+use std::mem;
+use std::f32::consts::PI;
 use crate::core::vector::Vector;
+use crate::core::radians::Radians;
+use crate::core::degrees::Degrees;
+use crate::core::random::Random;
+use crate::game::pathfinding::Pathfinder;
+use crate::game::weapons::{WeaponKind, hitscan};
+use crate::game::entities::EntityId;
+use crate::game::ai::{EnemyAction, EnemyState};
+use crate::game::projectiles::{Target, Impact, EXPLOSION_SECONDS, MAX_STEP_DISTANCE, explosion_sprite};
+use crate::game::pathfinding::CellPosition;
+use crate::game::automap::ExploredCells;
+use crate::game::levels::{LevelStats, CONTINUE_DELAY_SECONDS};
+use crate::game::triggers::{Activation, TriggerAction, MESSAGE_SECONDS, MAX_LIGHT_LEVEL};
+use crate::game::raycast::cast_ray;
+use crate::game::scripting::{LevelScript, ScriptEvent, ScriptWorld, ScriptCommand, ScriptTimer};
+use crate::game::text_format::invalid_data;
 
 pub mod entities;
+pub mod raycast;
+pub mod ai;
+pub mod pathfinding;
+pub mod weapons;
+pub mod projectiles;
+pub mod items;
 pub mod map;
+pub mod replay;
+pub mod save;
+pub mod automap;
+pub mod tiled;
+pub mod wolf3d;
+pub mod levels;
+pub mod triggers;
+pub mod scripting;
+pub mod generation;
+pub mod text_format;
+
+/// How long the player has to stay dead before they can restart the level
+const RESTART_DELAY_SECONDS: f32 = 1.0;
+
+/// How far in front of the player a door or switch can be to be used
+const USE_DISTANCE: f32 = 3.0;
+
+/// How wide the player's view is
+pub const FOV_DEGREES: Degrees = Degrees(90.0);
+
+/// How many map units per second the automap pans at the lowest zoom level
+const AUTOMAP_PAN_SPEED: f32 = 40.0;
 
 pub struct GameState {
     pub map: Map,
+
+    /// The map as it was when the level started, used to restart the level
+    pub initial_map: Map,
+
+    /// Every map of the episode in the order they are played, and which of them is being played
+    pub levels: Vec<Map>,
+    pub level: usize,
+    pub level_seconds: f32,
+
+    /// Set once the level's script has been told the level started, which happens on the first
+    /// tick the level is played
+    pub level_started: bool,
+    pub secrets_found: u32,
+
+    /// Set once the player uses the level's exit, after which the level is frozen while its stats
+    /// are shown
+    pub completed: Option<LevelStats>,
+    pub seconds_since_completed: f32,
+
+    /// Indices into the map's triggers of those that only go off once and already have
+    pub spent_triggers: Vec<usize>,
+
+    /// The cell the player was in last tick, so walk triggers only go off as the player enters
+    pub player_cell: Option<CellPosition>,
+
+    /// Percentage of full brightness the view is drawn at, which triggers can change
+    pub light_level: u32,
+
+    /// Text shown by a trigger, and how much longer it stays on screen
+    pub message: Option<String>,
+    pub message_seconds: f32,
+
+    /// The level's script, along with the variables and timers it keeps between events
+    pub script: Option<LevelScript>,
+    pub script_vars: BTreeMap<String, i64>,
+    pub script_timers: Vec<ScriptTimer>,
     pub player: Player,
+    pub entities: EntityStore,
     pub map_zoom_level: u16,
     pub display_map: bool,
+    pub map_view: MapView,
+
+    /// Whether the minimap turns with the player so they always face its top
+    pub rotate_minimap: bool,
+
+    /// Whether the overview map draws cells with their textures rather than flat colors
+    pub textured_map: bool,
+
+    /// How far the middle of the automap has been panned away from the player
+    pub automap_offset: Vector,
+    pub explored: ExploredCells,
+    pub display_paths: bool,
+    pub display_performance: bool,
     pub test_mode: bool,
+    pub seed: u64,
+    pub random: Random,
+    pub pathfinder: Pathfinder,
 }
 
+#[derive(Clone)]
 pub struct ActiveInputs {
     pub exit_game: bool,
     pub turn_left: bool,
@@ -23,46 +124,158 @@ pub struct ActiveInputs {
     pub zoom_in: bool,
     pub zoom_out: bool,
     pub toggle_map: bool,
+    pub cycle_map_view: bool,
+    pub toggle_minimap_rotation: bool,
+    pub toggle_map_textures: bool,
+    pub pan_map_up: bool,
+    pub pan_map_down: bool,
+    pub pan_map_left: bool,
+    pub pan_map_right: bool,
     pub toggle_test: bool,
+    pub toggle_paths: bool,
+    pub toggle_performance: bool,
+    pub fire: bool,
+    pub select_weapon_1: bool,
+    pub select_weapon_2: bool,
+    pub select_weapon_3: bool,
+    pub select_weapon_4: bool,
+    pub use_action: bool,
+    pub quick_save: bool,
+    pub quick_load: bool,
+}
+
+/// How the map is drawn while it is displayed
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MapView {
+    /// The whole map drawn from the top left of the screen
+    Overview,
+
+    /// A small map centered on the player in a corner of the game view
+    Minimap,
+
+    /// A full screen map that only shows cells the player has seen, and can be panned around
+    Automap,
 }
 
 #[derive(Debug)]
 enum Side { Right, Left, Top, Bottom }
 
 impl GameState {
-    pub fn new() -> Self {
-        let map = Map::new();
+    pub fn new(map: Map, seed: u64) -> io::Result<Self> {
+        GameState::with_levels(vec![map], 0, seed)
+    }
 
-        let first_spawn_cell = map.spawns
-            .iter()
-            .filter(|x| x.entity == SpawnType::Player)
-            .nth(0)
-            .unwrap();
-
-        let half_cell_length = map.units_per_cell / 2;
-        let initial_pos = Vector {
-            x: ((map.units_per_cell * first_spawn_cell.col as u32) + half_cell_length) as f32,
-            y: ((map.units_per_cell * first_spawn_cell.row as u32) + half_cell_length) as f32,
+    /// Starts the specified level of an episode.  Every level is checked for a player spawn up
+    /// front, so restarting the level or moving on to the next one can't fail.
+    pub fn with_levels(levels: Vec<Map>, level: usize, seed: u64) -> io::Result<Self> {
+        for map in &levels {
+            player_spawn(map)?;
+        }
+
+        let map = match levels.get(level) {
+            Some(x) => x.clone(),
+            None => return Err(invalid_data(format!("Level {} is not in the episode", level))),
         };
 
-        let player = Player::new(initial_pos);
+        let first_spawn_cell = player_spawn(&map)?;
+        let player = Player::new(cell_center(&map, first_spawn_cell.row, first_spawn_cell.col));
+
+        let mut entities = EntityStore::new();
+        for spawn in &map.spawns {
+            if let SpawnType::Entity(kind) = spawn.entity {
+                entities.spawn(kind, cell_center(&map, spawn.row, spawn.col));
+            }
+        }
 
-        GameState {
+        Ok(GameState {
+            player_cell: CellPosition::from_position(&map, player.position),
+            initial_map: map.clone(),
+            levels,
+            level,
+            level_seconds: 0.0,
+            level_started: false,
+            secrets_found: 0,
+            completed: None,
+            seconds_since_completed: 0.0,
+            spent_triggers: Vec::new(),
+            light_level: MAX_LIGHT_LEVEL,
+            message: None,
+            message_seconds: 0.0,
+            script: map.script_path().map(LevelScript::load),
+            script_vars: BTreeMap::new(),
+            script_timers: Vec::new(),
+            explored: ExploredCells::new(&map),
             map,
             player,
+            entities,
             map_zoom_level: 1,
             display_map: true,
+            map_view: MapView::Overview,
+            rotate_minimap: false,
+            textured_map: false,
+            automap_offset: Vector { x: 0.0, y: 0.0 },
+            display_paths: false,
+            display_performance: false,
             test_mode: false,
-        }
+            seed,
+            random: Random::new(seed),
+            pathfinder: Pathfinder::new(),
+        })
     }
 
     pub fn tick(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
+        self.player.seconds_since_damaged += time_since_last_frame.as_secs_f32();
+        self.player.seconds_since_pickup += time_since_last_frame.as_secs_f32();
+
+        if self.completed.is_some() {
+            self.tick_completed(time_since_last_frame, inputs);
+            return;
+        }
+
+        if !self.level_started {
+            self.level_started = true;
+            self.run_script(ScriptEvent::LevelStart);
+        }
+
+        self.level_seconds += time_since_last_frame.as_secs_f32();
         self.apply_inputs(time_since_last_frame, inputs);
+        self.apply_entity_collision();
+        apply_wall_collisions(&self.map, &mut self.player.position, self.player.collision_size);
+        self.explored.explore_visible(&self.map, self.player.position, self.player.facing);
+        self.tick_walk_triggers();
+        self.tick_message(time_since_last_frame);
+        self.tick_script_timers(time_since_last_frame);
+        self.tick_pickups();
+        if inputs.use_action {
+            self.use_cell();
+        }
+
+        self.tick_weapon(time_since_last_frame, inputs);
+        self.tick_entities(time_since_last_frame);
+        self.tick_projectiles(time_since_last_frame);
+        self.tick_death(time_since_last_frame, inputs);
+    }
+
+    pub fn damage_entity(&mut self, id: EntityId, amount: i32) {
+        if let Some(entity) = self.entities.get_mut(id) {
+            entity.health -= amount;
+        }
+    }
+
+    pub fn damage_player(&mut self, amount: i32) {
+        self.player.health = (self.player.health - amount).max(0);
+        self.player.seconds_since_damaged = 0.0;
+    }
 
-        self.apply_wall_collision(Side::Right);
-        self.apply_wall_collision(Side::Bottom);
-        self.apply_wall_collision(Side::Left);
-        self.apply_wall_collision(Side::Top);
+    /// Launches a projectile from the position along the facing.  The owner is the entity that
+    /// launched it, or `None` for the player.
+    pub fn launch_projectile(&mut self, kind: EntityKind, position: Vector, facing: Radians, owner: Option<EntityId>) {
+        let id = self.entities.spawn(kind, position);
+        let entity = self.entities.get_mut(id).unwrap();
+        entity.facing = facing;
+        if let Behaviour::Projectile(projectile) = &mut entity.behaviour {
+            projectile.owner = owner;
+        }
     }
 
     fn apply_inputs(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
@@ -82,6 +295,50 @@ impl GameState {
             self.display_map = !self.display_map;
         }
 
+        if inputs.cycle_map_view {
+            self.map_view = self.map_view.next();
+            self.automap_offset = Vector { x: 0.0, y: 0.0 };
+        }
+
+        if self.display_map && self.map_view == MapView::Automap {
+            let pan_amount = AUTOMAP_PAN_SPEED / self.map_zoom_level as f32 * time_since_last_frame.as_secs_f32();
+            if inputs.pan_map_up {
+                self.automap_offset.y -= pan_amount;
+            }
+
+            if inputs.pan_map_down {
+                self.automap_offset.y += pan_amount;
+            }
+
+            if inputs.pan_map_left {
+                self.automap_offset.x -= pan_amount;
+            }
+
+            if inputs.pan_map_right {
+                self.automap_offset.x += pan_amount;
+            }
+        }
+
+        if inputs.toggle_minimap_rotation {
+            self.rotate_minimap = !self.rotate_minimap;
+        }
+
+        if inputs.toggle_map_textures {
+            self.textured_map = !self.textured_map;
+        }
+
+        if inputs.toggle_paths {
+            self.display_paths = !self.display_paths;
+        }
+
+        if inputs.toggle_performance {
+            self.display_performance = !self.display_performance;
+        }
+
+        if !self.player.is_alive() {
+            return;
+        }
+
         let turn_amount = self.player.turn_speed * time_since_last_frame.as_secs_f32();
         if inputs.turn_left {
             self.player.facing = self.player.facing - turn_amount;
@@ -109,28 +366,580 @@ impl GameState {
         self.player.position = self.player.position + (velocity * time_since_last_frame.as_secs_f32());
     }
 
-    fn apply_wall_collision(&mut self, side: Side) {
-        let (test_x, test_y) = match side {
-            Side::Right => (self.player.position.x + self.player.collision_size as f32 / 2.0, self.player.position.y),
-            Side::Left => (self.player.position.x - self.player.collision_size as f32 / 2.0, self.player.position.y),
-            Side::Top => (self.player.position.x, self.player.position.y - self.player.collision_size as f32 / 2.0),
-            Side::Bottom => (self.player.position.x, self.player.position.y + self.player.collision_size as f32 / 2.0),
+    /// Pushes the player out of any solid entities they have walked into
+    fn apply_entity_collision(&mut self) {
+        for entity in self.entities.iter().filter(|x| x.solid) {
+            let min_distance = (self.player.collision_size + entity.collision_size) as f32 / 2.0;
+            let delta = self.player.position - entity.position;
+            let overlap_x = min_distance - delta.x.abs();
+            let overlap_y = min_distance - delta.y.abs();
+            if overlap_x <= 0.0 || overlap_y <= 0.0 {
+                continue;
+            }
+
+            if overlap_x < overlap_y {
+                self.player.position.x += if delta.x < 0.0 { -overlap_x } else { overlap_x };
+            } else {
+                self.player.position.y += if delta.y < 0.0 { -overlap_y } else { overlap_y };
+            }
+        }
+    }
+
+    /// Collects every item the player is standing on that they have a use for
+    fn tick_pickups(&mut self) {
+        if !self.player.is_alive() {
+            return;
+        }
+
+        let player_position = self.player.position;
+        let player_size = self.player.collision_size;
+        let touching = self.entities
+            .iter()
+            .filter_map(|entity| match entity.behaviour {
+                Behaviour::Pickup(item) => Some((entity, item)),
+                _ => None,
+            })
+            .filter(|(entity, _)| {
+                let min_distance = (player_size + entity.collision_size) as f32 / 2.0;
+                let delta = player_position - entity.position;
+                delta.x.abs() < min_distance && delta.y.abs() < min_distance
+            })
+            .map(|(entity, item)| (entity.id, item))
+            .collect::<Vec<_>>();
+
+        for (id, item) in touching {
+            if items::pick_up(&mut self.player, item) {
+                self.entities.remove(id);
+                self.player.seconds_since_pickup = 0.0;
+            }
+        }
+    }
+
+    /// Opens the door or secret wall directly in front of the player, as long as they have the key
+    /// needed for it, or completes the level if it is the exit
+    fn use_cell(&mut self) {
+        if !self.player.is_alive() {
+            return;
+        }
+
+        let direction = Vector { x: self.player.facing.0.cos(), y: self.player.facing.0.sin() };
+        let cell = match CellPosition::from_position(&self.map, self.player.position + direction * USE_DISTANCE) {
+            Some(x) => x,
+            None => return,
         };
 
-        let row = test_y as u32 / self.map.units_per_cell;
-        let col = test_x as u32 / self.map.units_per_cell;
+        self.fire_triggers(Activation::Use, cell);
+
+        let unlocked = match self.map.cell_at(cell.row, cell.col) {
+            Some(CellType::Door { lock: None }) => true,
+            Some(CellType::Door { lock: Some(color) }) => self.player.inventory.has_key(color),
+            Some(CellType::SecretWall) => {
+                self.secrets_found += 1;
+                true
+            }
+
+            Some(CellType::Exit) => {
+                self.completed = Some(LevelStats::new(self.level_seconds, &self.entities, &self.initial_map, self.secrets_found));
+                false
+            }
+
+            _ => false,
+        };
+
+        if unlocked {
+            self.map.set_cell(cell.row, cell.col, CellType::Empty);
+            self.pathfinder.clear_cache();
+        }
+    }
+
+    fn tick_weapon(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
+        let player = &mut self.player;
+        player.weapon_cooldown = (player.weapon_cooldown - time_since_last_frame.as_secs_f32()).max(0.0);
+        player.seconds_since_fired += time_since_last_frame.as_secs_f32();
+
+        let trigger_was_held = player.trigger_held;
+        player.trigger_held = inputs.fire;
+        if !player.is_alive() {
+            return;
+        }
+
+        let selections = [
+            (inputs.select_weapon_1, WeaponKind::Knife),
+            (inputs.select_weapon_2, WeaponKind::Pistol),
+            (inputs.select_weapon_3, WeaponKind::MachineGun),
+            (inputs.select_weapon_4, WeaponKind::RocketLauncher),
+        ];
+
+        for (selected, weapon) in &selections {
+            if *selected && player.weapons.contains(weapon) {
+                player.weapon = *weapon;
+            }
+        }
+
+        let stats = player.weapon.stats();
+        let trigger_pulled = inputs.fire && (stats.automatic || !trigger_was_held);
+        if !trigger_pulled || player.weapon_cooldown > 0.0 {
+            return;
+        }
+
+        if stats.uses_ammo {
+            if player.ammo == 0 {
+                // Out of ammo, so fall back to the knife
+                player.weapon = WeaponKind::Knife;
+                return;
+            }
+
+            player.ammo -= 1;
+        }
+
+        player.weapon_cooldown = stats.seconds_between_shots;
+        player.seconds_since_fired = 0.0;
+
+        if let Some(kind) = stats.projectile {
+            let (position, facing) = (player.position, player.facing);
+            self.launch_projectile(kind, position, facing, None);
+            return;
+        }
+
+        let (position, facing) = (player.position, player.facing);
+        let target = hitscan(&self.map, &self.entities, position, facing, stats.range);
+        if let Some(id) = target {
+            // Scenery absorbs the shot without taking any damage
+            if self.entities.get(id).is_some_and(|x| x.health > 0) {
+                let damage = stats.base_damage + self.random.next_below(stats.base_damage as u32 + 1) as i32;
+                self.damage_entity(id, damage);
+            }
+
+            return;
+        }
+
+        // A shot that misses every entity hits the wall behind them, if it is in range
+        let wall = cast_ray(&self.map, position, facing);
+        if wall.distance > 0.0 && wall.distance <= stats.range {
+            let direction = Vector { x: facing.0.cos(), y: facing.0.sin() };
+            if let Some(cell) = CellPosition::from_position(&self.map, position + direction * (wall.distance + 0.1)) {
+                self.fire_triggers(Activation::Shoot, cell);
+            }
+        }
+    }
+
+    /// Sets off the walk triggers of the cell the player has just entered
+    fn tick_walk_triggers(&mut self) {
+        let cell = CellPosition::from_position(&self.map, self.player.position);
+        if cell == self.player_cell || !self.player.is_alive() {
+            return;
+        }
+
+        self.player_cell = cell;
+        if let Some(cell) = cell {
+            self.fire_triggers(Activation::Walk, cell);
+            self.run_script(ScriptEvent::EnterCell(cell));
+        }
+    }
+
+    fn tick_message(&mut self, time_since_last_frame: &Duration) {
+        self.message_seconds -= time_since_last_frame.as_secs_f32();
+        if self.message_seconds <= 0.0 {
+            self.message = None;
+        }
+    }
+
+    /// Counts down the script's timers, letting the script know about each one that runs out in
+    /// the order they were started
+    fn tick_script_timers(&mut self, time_since_last_frame: &Duration) {
+        let mut expired = Vec::new();
+        for timer in &mut self.script_timers {
+            timer.seconds_left -= time_since_last_frame.as_secs_f32();
+            if timer.seconds_left <= 0.0 {
+                expired.push(timer.name.clone());
+            }
+        }
+
+        self.script_timers.retain(|x| x.seconds_left > 0.0);
+        for name in expired {
+            self.run_script(ScriptEvent::Timer(name));
+        }
+    }
 
-        let should_move = match self.map.cell_at(row as usize, col as usize) {
-            Some(CellType::Empty) => false,
-            _ => true,
+    /// Lets the level's script handle the event, then carries out whatever it asked for
+    fn run_script(&mut self, event: ScriptEvent) {
+        let script = match &mut self.script {
+            Some(x) if x.handles(&event) => x,
+            _ => return,
         };
 
-        match (should_move, side) {
-            (false, _) => (),
-            (true, Side::Right) => {self.player.position.x = (col * self.map.units_per_cell - self.player.collision_size as u32 / 2) as f32;},
-            (true, Side::Left) => {self.player.position.x = ((col + 1) * self.map.units_per_cell + self.player.collision_size as u32 / 2) as f32;},
-            (true, Side::Top) => {self.player.position.y = ((row + 1) * self.map.units_per_cell + self.player.collision_size as u32 / 2) as f32;},
-            (true, Side::Bottom) => {self.player.position.y = (row * self.map.units_per_cell - self.player.collision_size as u32 / 2) as f32;},
+        // The map is lent to the script rather than copied for every event
+        let world = script.handle(&event, ScriptWorld {
+            map: mem::replace(&mut self.map, Map::with_size("", 0, 0)),
+            player_cell: self.player_cell,
+            player_health: self.player.health,
+            level_seconds: self.level_seconds,
+            vars: mem::take(&mut self.script_vars),
+            commands: Vec::new(),
+        });
+
+        self.map = world.map;
+        self.script_vars = world.vars;
+        for command in world.commands {
+            match command {
+                ScriptCommand::Action(action) => self.apply_trigger_action(action),
+                ScriptCommand::SetTimer { name, seconds } => {
+                    self.script_timers.retain(|x| x.name != name);
+                    if seconds > 0.0 {
+                        self.script_timers.push(ScriptTimer { name, seconds_left: seconds });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Carries out the actions of every trigger on the cell with the activation, skipping those
+    /// that only go off once and already have
+    fn fire_triggers(&mut self, activation: Activation, cell: CellPosition) {
+        let fired = self.map.triggers
+            .iter()
+            .enumerate()
+            .filter(|(index, x)| x.activation == activation && x.row == cell.row && x.col == cell.col
+                && !self.spent_triggers.contains(index))
+            .map(|(index, x)| (index, x.once, x.action.clone()))
+            .collect::<Vec<_>>();
+
+        for (index, once, action) in fired {
+            if once {
+                self.spent_triggers.push(index);
+            }
+
+            self.apply_trigger_action(action);
+        }
+    }
+
+    fn apply_trigger_action(&mut self, action: TriggerAction) {
+        match action {
+            TriggerAction::OpenDoor { row, col } => {
+                if let Some(CellType::Door { .. }) = self.map.cell_at(row, col) {
+                    self.map.set_cell(row, col, CellType::Empty);
+                    self.pathfinder.clear_cache();
+                }
+            }
+
+            TriggerAction::ToggleWall { row, col, cell_type } => {
+                let toggled = match self.map.cell_at(row, col) {
+                    // A wall can't appear on top of the player, or they would be stuck inside it
+                    Some(CellType::Empty) if self.player_cell == Some(CellPosition { row, col }) => None,
+                    Some(CellType::Empty) => Some(cell_type),
+                    Some(_) => Some(CellType::Empty),
+                    None => None,
+                };
+
+                if let Some(toggled) = toggled {
+                    self.map.set_cell(row, col, toggled);
+                    self.pathfinder.clear_cache();
+                }
+            }
+
+            TriggerAction::Spawn { kind, row, col } => {
+                if self.map.is_passable(row, col) {
+                    let position = cell_center(&self.map, row, col);
+                    self.entities.spawn(kind, position);
+                }
+            }
+
+            TriggerAction::Light(level) => self.light_level = level,
+            TriggerAction::Message(text) => {
+                self.message = Some(text);
+                self.message_seconds = MESSAGE_SECONDS;
+            }
+        }
+    }
+
+    fn tick_death(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
+        if self.player.is_alive() {
+            return;
+        }
+
+        self.player.seconds_since_death += time_since_last_frame.as_secs_f32();
+        if inputs.fire && self.player.seconds_since_death >= RESTART_DELAY_SECONDS {
+            self.restart_level();
+        }
+    }
+
+    /// Waits for the player to press fire on the level complete screen, then starts the next
+    /// level.  The last level of the episode stays on the screen.
+    fn tick_completed(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
+        self.seconds_since_completed += time_since_last_frame.as_secs_f32();
+        if inputs.fire && self.seconds_since_completed >= CONTINUE_DELAY_SECONDS && self.has_next_level() {
+            self.start_next_level();
+        }
+    }
+
+    pub fn has_next_level(&self) -> bool {
+        self.level + 1 < self.levels.len()
+    }
+
+    /// The player keeps their health, ammo, weapons and treasure, but keys only open doors in the
+    /// level they were found in
+    fn start_next_level(&mut self) {
+        // Every level was checked for a player spawn when the episode started
+        let mut next = GameState::with_levels(self.levels.clone(), self.level + 1, self.seed).unwrap();
+        next.player.health = self.player.health;
+        next.player.ammo = self.player.ammo;
+        next.player.weapons = self.player.weapons.clone();
+        next.player.weapon = self.player.weapon;
+        next.player.inventory.treasure = self.player.inventory.treasure;
+
+        // Fire was just pressed to continue, so it shouldn't also fire a shot in the new level
+        next.player.trigger_held = true;
+        self.keep_settings(&mut next);
+
+        *self = next;
+    }
+
+    fn restart_level(&mut self) {
+        let mut restarted = GameState::with_levels(self.levels.clone(), self.level, self.seed).unwrap();
+        self.keep_settings(&mut restarted);
+
+        *self = restarted;
+    }
+
+    /// Copies everything that isn't part of the level itself to a game state starting a level
+    fn keep_settings(&self, next: &mut GameState) {
+        // Keep the random sequence going, so the level doesn't play out exactly the same again
+        next.random = self.random;
+        next.map_zoom_level = self.map_zoom_level;
+        next.display_map = self.display_map;
+        next.map_view = self.map_view;
+        next.rotate_minimap = self.rotate_minimap;
+        next.textured_map = self.textured_map;
+        next.display_paths = self.display_paths;
+        next.display_performance = self.display_performance;
+        next.test_mode = self.test_mode;
+    }
+
+    fn tick_entities(&mut self, time_since_last_frame: &Duration) {
+        let seconds = time_since_last_frame.as_secs_f32();
+        let mut player_damage = 0;
+        let mut launches = Vec::new();
+        let mut deaths = Vec::new();
+        for entity in self.entities.iter_mut() {
+            match entity.behaviour {
+                // Projectiles are moved after everything else has had a chance to launch one
+                Behaviour::Static | Behaviour::Projectile(_) | Behaviour::Explosion { .. } | Behaviour::Pickup(_) => (),
+                Behaviour::Wander { seconds_until_turn } => {
+                    let mut seconds_until_turn = seconds_until_turn - seconds;
+                    if seconds_until_turn <= 0.0 {
+                        entity.facing = Radians(self.random.next_f32() * 2.0 * PI);
+                        seconds_until_turn = 1.0 + self.random.next_f32() * 2.0;
+                    }
+
+                    let velocity = Vector {
+                        x: entity.facing.0.cos() * entity.move_speed,
+                        y: entity.facing.0.sin() * entity.move_speed,
+                    };
+
+                    entity.position = entity.position + (velocity * seconds);
+                    if apply_wall_collisions(&self.map, &mut entity.position, entity.collision_size) {
+                        // Bumped into a wall, so pick a new direction next tick
+                        seconds_until_turn = 0.0;
+                    }
+
+                    entity.behaviour = Behaviour::Wander { seconds_until_turn };
+                }
+
+                Behaviour::Enemy(enemy) => {
+                    let was_dead = enemy.state == EnemyState::Death;
+                    let action = ai::tick_enemy(entity, &self.map, &mut self.pathfinder, &mut self.random, &self.player, seconds);
+                    if let Behaviour::Enemy(enemy) = entity.behaviour {
+                        if !was_dead && enemy.state == EnemyState::Death {
+                            deaths.push((entity.id, entity.kind));
+                        }
+                    }
+
+                    match action {
+                        Some(EnemyAction::DamagePlayer(damage)) => player_damage += damage,
+                        Some(EnemyAction::LaunchProjectile { kind, position, facing }) => {
+                            launches.push((kind, position, facing, entity.id));
+                        }
+
+                        None => (),
+                    }
+                }
+            }
+        }
+
+        if player_damage > 0 {
+            self.damage_player(player_damage);
+        }
+
+        for (kind, position, facing, owner) in launches {
+            self.launch_projectile(kind, position, facing, Some(owner));
+        }
+
+        for (id, kind) in deaths {
+            self.run_script(ScriptEvent::EntityDied { id, kind });
+        }
+    }
+
+    /// Moves projectiles, turning them into explosions when they hit something, and removes
+    /// explosions once they have finished.
+    fn tick_projectiles(&mut self, time_since_last_frame: &Duration) {
+        let seconds = time_since_last_frame.as_secs_f32();
+        let ids = self.entities
+            .iter()
+            .filter(|x| matches!(x.behaviour, Behaviour::Projectile(_) | Behaviour::Explosion { .. }))
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+
+        for id in ids {
+            let entity = match self.entities.get(id) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let projectile = match entity.behaviour {
+                Behaviour::Projectile(x) => x,
+                Behaviour::Explosion { seconds_left } => {
+                    let seconds_left = seconds_left - seconds;
+                    if seconds_left <= 0.0 {
+                        self.entities.remove(id);
+                    } else if let Some(entity) = self.entities.get_mut(id) {
+                        entity.behaviour = Behaviour::Explosion { seconds_left };
+                        entity.sprite = explosion_sprite(seconds_left);
+                    }
+
+                    continue;
+                }
+
+                _ => continue,
+            };
+
+            let facing = entity.facing;
+            let (position, impact) = projectiles::move_projectile(&self.map, &self.entities, &self.player, entity, &projectile, seconds);
+            let damage = match &impact {
+                Some(impact) => projectiles::impact_damage(&self.map, &self.entities, &self.player, impact, &projectile),
+                None => Vec::new(),
+            };
+
+            if let Some(entity) = self.entities.get_mut(id) {
+                entity.position = position;
+                if impact.is_some() {
+                    entity.behaviour = Behaviour::Explosion { seconds_left: EXPLOSION_SECONDS };
+                    entity.sprite = Sprite::Explosion1;
+                }
+            }
+
+            for (target, amount) in damage {
+                match target {
+                    Target::Player => self.damage_player(amount),
+                    Target::Entity(target_id) => self.damage_entity(target_id, amount),
+                }
+            }
+
+            // The player's projectiles stop just short of the wall they hit
+            if let Some(Impact { position, direct_hit: None }) = impact {
+                let direction = Vector { x: facing.0.cos(), y: facing.0.sin() };
+                let wall_cell = CellPosition::from_position(&self.map, position + direction * MAX_STEP_DISTANCE);
+                if let (None, Some(cell)) = (projectile.owner, wall_cell) {
+                    self.fire_triggers(Activation::Shoot, cell);
+                }
+            }
+        }
+    }
+}
+
+/// Where the player starts the level, which every playable map has
+fn player_spawn(map: &Map) -> io::Result<&SpawnLocation> {
+    match map.spawns.iter().find(|x| x.entity == SpawnType::Player) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Map '{}' has no player spawn", map.id))),
+    }
+}
+
+/// Returns the position at the center of the specified cell
+pub fn cell_center(map: &Map, row: usize, col: usize) -> Vector {
+    let half_cell_length = map.units_per_cell / 2;
+    Vector {
+        x: ((map.units_per_cell * col as u32) + half_cell_length) as f32,
+        y: ((map.units_per_cell * row as u32) + half_cell_length) as f32,
+    }
+}
+
+/// Moves the position out of any walls it overlaps with.  Returns true if any wall was hit.
+pub fn apply_wall_collisions(map: &Map, position: &mut Vector, collision_size: u16) -> bool {
+    let hit_right = apply_wall_collision(map, position, collision_size, Side::Right);
+    let hit_bottom = apply_wall_collision(map, position, collision_size, Side::Bottom);
+    let hit_left = apply_wall_collision(map, position, collision_size, Side::Left);
+    let hit_top = apply_wall_collision(map, position, collision_size, Side::Top);
+
+    hit_right || hit_bottom || hit_left || hit_top
+}
+
+/// Returns true if any of the points wall collisions are tested at is outside of the map or in a
+/// cell that can't be walked through
+pub fn is_touching_wall(map: &Map, position: Vector, collision_size: u16) -> bool {
+    [Side::Right, Side::Bottom, Side::Left, Side::Top].iter().any(|side| {
+        let (test_x, test_y) = collision_test_point(position, collision_size, side);
+        if test_x < 0.0 || test_y < 0.0 {
+            return true;
+        }
+
+        let row = test_y as u32 / map.units_per_cell;
+        let col = test_x as u32 / map.units_per_cell;
+        !matches!(map.cell_at(row as usize, col as usize), Some(CellType::Empty))
+    })
+}
+
+/// The point on the edge of something's collision box at the middle of the side
+fn collision_test_point(position: Vector, collision_size: u16, side: &Side) -> (f32, f32) {
+    match side {
+        Side::Right => (position.x + collision_size as f32 / 2.0, position.y),
+        Side::Left => (position.x - collision_size as f32 / 2.0, position.y),
+        Side::Top => (position.x, position.y - collision_size as f32 / 2.0),
+        Side::Bottom => (position.x, position.y + collision_size as f32 / 2.0),
+    }
+}
+
+fn apply_wall_collision(map: &Map, position: &mut Vector, collision_size: u16, side: Side) -> bool {
+    let (test_x, test_y) = collision_test_point(*position, collision_size, &side);
+    let row = test_y as u32 / map.units_per_cell;
+    let col = test_x as u32 / map.units_per_cell;
+
+    let should_move = !matches!(map.cell_at(row as usize, col as usize), Some(CellType::Empty));
+
+    match (should_move, side) {
+        (false, _) => (),
+        (true, Side::Right) => {position.x = (col * map.units_per_cell - collision_size as u32 / 2) as f32;},
+        (true, Side::Left) => {position.x = ((col + 1) * map.units_per_cell + collision_size as u32 / 2) as f32;},
+        (true, Side::Top) => {position.y = ((row + 1) * map.units_per_cell + collision_size as u32 / 2) as f32;},
+        (true, Side::Bottom) => {position.y = (row * map.units_per_cell - collision_size as u32 / 2) as f32;},
+    }
+
+    should_move
+}
+
+impl MapView {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MapView::Overview => "overview",
+            MapView::Minimap => "minimap",
+            MapView::Automap => "automap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "overview" => Some(MapView::Overview),
+            "minimap" => Some(MapView::Minimap),
+            "automap" => Some(MapView::Automap),
+            _ => None,
+        }
+    }
+
+    /// The view after this one when cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            MapView::Overview => MapView::Minimap,
+            MapView::Minimap => MapView::Automap,
+            MapView::Automap => MapView::Overview,
         }
     }
 }
@@ -146,7 +955,30 @@ impl ActiveInputs {
             zoom_in: false,
             zoom_out: false,
             toggle_map: false,
+            cycle_map_view: false,
+            toggle_minimap_rotation: false,
+            toggle_map_textures: false,
+            pan_map_up: false,
+            pan_map_down: false,
+            pan_map_left: false,
+            pan_map_right: false,
             toggle_test: false,
+            toggle_paths: false,
+            toggle_performance: false,
+            fire: false,
+            select_weapon_1: false,
+            select_weapon_2: false,
+            select_weapon_3: false,
+            select_weapon_4: false,
+            use_action: false,
+            quick_save: false,
+            quick_load: false,
         }
     }
 }
+
+impl Default for ActiveInputs {
+    fn default() -> Self {
+        ActiveInputs::new()
+    }
+}
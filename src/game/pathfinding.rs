@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use crate::core::vector::Vector;
+use crate::game::map::Map;
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Once this many paths are cached the cache is cleared, so that it doesn't keep growing as the
+/// player wanders around the level.
+const MAX_CACHED_PATHS: usize = 512;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CellPosition {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Movement { FourWay, EightWay }
+
+/// Finds paths between cells, remembering previous results until the map changes
+pub struct Pathfinder {
+    cache: HashMap<(CellPosition, CellPosition, Movement), Option<Vec<CellPosition>>>,
+}
+
+#[derive(Eq, PartialEq)]
+struct OpenCell {
+    estimated_cost: u32,
+    cost_so_far: u32,
+    position: CellPosition,
+}
+
+impl CellPosition {
+    pub fn from_position(map: &Map, position: Vector) -> Option<Self> {
+        if position.x < 0.0 || position.y < 0.0 {
+            return None;
+        }
+
+        let row = position.y as usize / map.units_per_cell as usize;
+        let col = position.x as usize / map.units_per_cell as usize;
+        if row >= map.height || col >= map.width {
+            return None;
+        }
+
+        Some(CellPosition { row, col })
+    }
+}
+
+impl Pathfinder {
+    pub fn new() -> Self {
+        Pathfinder {
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns the cells to walk through to get from the start to the goal, including both the
+    /// start and goal cells.
+    pub fn find_path(&mut self, map: &Map, start: CellPosition, goal: CellPosition, movement: Movement)
+        -> Option<&[CellPosition]> {
+        let key = (start, goal, movement);
+        if self.cache.len() >= MAX_CACHED_PATHS && !self.cache.contains_key(&key) {
+            self.cache.clear();
+        }
+
+        self.cache
+            .entry(key)
+            .or_insert_with(|| find_path(map, start, goal, movement))
+            .as_deref()
+    }
+
+    /// Forgets every cached path.  Needs to be called whenever cells of the map change.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Pathfinder::new()
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the cheapest cell first
+        other.estimated_cost.cmp(&self.estimated_cost)
+            .then_with(|| self.cost_so_far.cmp(&other.cost_so_far))
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A* search over the map's cells.  Diagonal steps are only allowed when both cells next to the
+/// diagonal are open, so paths never cut across the corner of a wall.
+pub fn find_path(map: &Map, start: CellPosition, goal: CellPosition, movement: Movement) -> Option<Vec<CellPosition>> {
+    if !map.is_passable(start.row, start.col) || !map.is_passable(goal.row, goal.col) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<CellPosition, CellPosition> = HashMap::new();
+    let mut best_costs: HashMap<CellPosition, u32> = HashMap::new();
+
+    best_costs.insert(start, 0);
+    open.push(OpenCell {
+        estimated_cost: estimate_cost(start, goal, movement),
+        cost_so_far: 0,
+        position: start,
+    });
+
+    while let Some(current) = open.pop() {
+        if current.position == goal {
+            let mut path = vec![goal];
+            let mut position = goal;
+            while let Some(previous) = came_from.get(&position) {
+                path.push(*previous);
+                position = *previous;
+            }
+
+            path.reverse();
+            return Some(path);
+        }
+
+        if current.cost_so_far > *best_costs.get(&current.position).unwrap_or(&u32::MAX) {
+            // A cheaper route to this cell was already processed
+            continue;
+        }
+
+        for (neighbor, step_cost) in neighbors(map, current.position, movement) {
+            let cost = current.cost_so_far + step_cost;
+            if cost >= *best_costs.get(&neighbor).unwrap_or(&u32::MAX) {
+                continue;
+            }
+
+            best_costs.insert(neighbor, cost);
+            came_from.insert(neighbor, current.position);
+            open.push(OpenCell {
+                estimated_cost: cost + estimate_cost(neighbor, goal, movement),
+                cost_so_far: cost,
+                position: neighbor,
+            });
+        }
+    }
+
+    None
+}
+
+fn neighbors(map: &Map, position: CellPosition, movement: Movement) -> Vec<(CellPosition, u32)> {
+    let mut result = Vec::new();
+    let is_open = |row_offset: isize, col_offset: isize| -> Option<CellPosition> {
+        let row = position.row as isize + row_offset;
+        let col = position.col as isize + col_offset;
+        if row < 0 || col < 0 || !map.is_passable(row as usize, col as usize) {
+            return None;
+        }
+
+        Some(CellPosition { row: row as usize, col: col as usize })
+    };
+
+    for (row_offset, col_offset) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        if let Some(neighbor) = is_open(*row_offset, *col_offset) {
+            result.push((neighbor, STRAIGHT_COST));
+        }
+    }
+
+    if movement == Movement::EightWay {
+        for (row_offset, col_offset) in &[(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let corners_open = is_open(*row_offset, 0).is_some() && is_open(0, *col_offset).is_some();
+            if !corners_open {
+                continue;
+            }
+
+            if let Some(neighbor) = is_open(*row_offset, *col_offset) {
+                result.push((neighbor, DIAGONAL_COST));
+            }
+        }
+    }
+
+    result
+}
+
+fn estimate_cost(from: CellPosition, to: CellPosition, movement: Movement) -> u32 {
+    let rows = (from.row as isize - to.row as isize).unsigned_abs() as u32;
+    let cols = (from.col as isize - to.col as isize).unsigned_abs() as u32;
+
+    match movement {
+        Movement::FourWay => (rows + cols) * STRAIGHT_COST,
+        Movement::EightWay => {
+            let diagonal_steps = rows.min(cols);
+            let straight_steps = rows.max(cols) - diagonal_steps;
+            diagonal_steps * DIAGONAL_COST + straight_steps * STRAIGHT_COST
+        }
+    }
+}
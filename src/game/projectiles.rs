@@ -0,0 +1,148 @@
+use crate::core::vector::Vector;
+use crate::game::entities::{EntityStore, EntityId, Entity, Player, Sprite};
+use crate::game::map::Map;
+use crate::game::raycast::has_line_of_sight;
+use crate::game::is_touching_wall;
+
+/// How long the explosion is shown after a projectile hits something
+pub const EXPLOSION_SECONDS: f32 = 0.4;
+
+/// Projectiles move in small steps so fast ones can't pass through thin targets in a single tick
+pub const MAX_STEP_DISTANCE: f32 = 0.5;
+
+#[derive(Copy, Clone)]
+pub struct Projectile {
+    /// The entity that launched the projectile, or `None` if the player did.  A projectile never
+    /// hits whoever launched it.
+    pub owner: Option<EntityId>,
+    pub speed: f32,
+    pub damage: i32,
+
+    /// Anything within this distance of the impact takes damage, less the further away it is.
+    /// Projectiles without splash only damage what they hit directly.
+    pub splash_radius: f32,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Target { Player, Entity(EntityId) }
+
+pub struct Impact {
+    pub position: Vector,
+
+    /// What the projectile flew into, or `None` if it hit a wall
+    pub direct_hit: Option<Target>,
+}
+
+impl Projectile {
+    pub fn new(owner: Option<EntityId>, speed: f32, damage: i32, splash_radius: f32) -> Self {
+        Projectile { owner, speed, damage, splash_radius }
+    }
+}
+
+/// The explosion is drawn bigger for its second half
+pub fn explosion_sprite(seconds_left: f32) -> Sprite {
+    if seconds_left < EXPLOSION_SECONDS / 2.0 { Sprite::Explosion2 } else { Sprite::Explosion1 }
+}
+
+/// Works out where the projectile entity moves along its facing in a single tick.  Returns its
+/// new position, and where it hit something if it did.
+pub fn move_projectile(map: &Map,
+                       entities: &EntityStore,
+                       player: &Player,
+                       entity: &Entity,
+                       projectile: &Projectile,
+                       seconds: f32) -> (Vector, Option<Impact>) {
+    let distance = projectile.speed * seconds;
+    let steps = (distance / MAX_STEP_DISTANCE).ceil().max(1.0) as u32;
+    let step = Vector { x: entity.facing.0.cos(), y: entity.facing.0.sin() } * (distance / steps as f32);
+    let radius = entity.collision_size as f32 / 2.0;
+
+    let mut position = entity.position;
+    for _ in 0..steps {
+        let next_position = position + step;
+        if is_touching_wall(map, next_position, entity.collision_size) {
+            // Explode just in front of the wall, so the splash isn't blocked by the wall itself
+            return (position, Some(Impact { position, direct_hit: None }));
+        }
+
+        position = next_position;
+        if let Some(target) = find_target(entities, player, entity.id, position, radius, projectile) {
+            return (position, Some(Impact { position, direct_hit: Some(target) }));
+        }
+    }
+
+    (position, None)
+}
+
+/// Returns everything caught in the impact along with how much damage each takes.  Whatever was
+/// hit directly takes full damage, while splash damage falls off with distance and is blocked by
+/// walls.
+pub fn impact_damage(map: &Map,
+                     entities: &EntityStore,
+                     player: &Player,
+                     impact: &Impact,
+                     projectile: &Projectile) -> Vec<(Target, i32)> {
+    let mut damage = Vec::new();
+    match impact.direct_hit {
+        Some(Target::Player) => damage.push((Target::Player, projectile.damage)),
+        Some(Target::Entity(id)) if entities.get(id).is_some_and(|x| x.health > 0) => {
+            damage.push((Target::Entity(id), projectile.damage));
+        }
+
+        _ => (),
+    }
+
+    if projectile.splash_radius <= 0.0 {
+        return damage;
+    }
+
+    let splash_damage = |position: Vector| -> Option<i32> {
+        let distance = (position - impact.position).length();
+        if distance >= projectile.splash_radius || !has_line_of_sight(map, impact.position, position) {
+            return None;
+        }
+
+        let amount = (projectile.damage as f32 * (1.0 - distance / projectile.splash_radius)) as i32;
+        if amount > 0 { Some(amount) } else { None }
+    };
+
+    if player.is_alive() && impact.direct_hit != Some(Target::Player) {
+        if let Some(amount) = splash_damage(player.position) {
+            damage.push((Target::Player, amount));
+        }
+    }
+
+    for entity in entities.iter().filter(|x| x.health > 0) {
+        if impact.direct_hit == Some(Target::Entity(entity.id)) {
+            continue;
+        }
+
+        if let Some(amount) = splash_damage(entity.position) {
+            damage.push((Target::Entity(entity.id), amount));
+        }
+    }
+
+    damage
+}
+
+fn find_target(entities: &EntityStore,
+               player: &Player,
+               id: EntityId,
+               position: Vector,
+               radius: f32,
+               projectile: &Projectile) -> Option<Target> {
+    // Only projectiles launched by enemies can hit the player directly
+    if projectile.owner.is_some() && player.is_alive() {
+        let hit_distance = radius + player.collision_size as f32 / 2.0;
+        if (player.position - position).length() < hit_distance {
+            return Some(Target::Player);
+        }
+    }
+
+    // Solid scenery blocks projectiles even though it can't be hurt
+    entities
+        .iter()
+        .filter(|x| x.id != id && Some(x.id) != projectile.owner && (x.health > 0 || x.solid))
+        .find(|x| (x.position - position).length() < radius + x.collision_size as f32 / 2.0)
+        .map(|x| Target::Entity(x.id))
+}
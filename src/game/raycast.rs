@@ -0,0 +1,132 @@
+use crate::core::radians::Radians;
+use crate::core::vector::Vector;
+use crate::game::map::{Map, CellType};
+
+pub struct RayResult {
+    pub distance: f32,
+    pub units_from_cell_start: f32,
+    pub cell_type: CellType,
+}
+
+/// Casts a ray from the origin until it hits a wall.  A distance of zero means nothing was hit.
+pub fn cast_ray(map: &Map, origin: Vector, angle: Radians) -> RayResult {
+    // If the origin is off the map in the negative region, don't shoot the array
+    if origin.x < 0.0 || origin.y < 0.0 {
+        return RayResult {distance: 0.0, units_from_cell_start: 0.0, cell_type: CellType::Empty};
+    }
+
+    let rise = angle.0.sin();
+    let run = angle.0.cos();
+    let slope = rise / run;
+
+    // compute y intercept for full y=mx+b equation
+    let y_offset = (-slope * origin.x) + origin.y;
+
+    let y_adjustment = if rise < 0.0 { 0 } else { map.units_per_cell };
+    let x_adjustment = if run < 0.0 { 0 } else { map.units_per_cell };
+    let current_row = origin.y as u32 / map.units_per_cell;
+    let current_col = origin.x as u32 / map.units_per_cell;
+
+    let mut y_value = (current_row * map.units_per_cell + y_adjustment) as f32;
+    let mut x_value = (current_col * map.units_per_cell + x_adjustment) as f32;
+
+    loop {
+        let horizontally_out_of_map = x_value < 0.0 || x_value > map.width as f32 * map.units_per_cell as f32;
+        let vertically_out_of_map = y_value < 0.0 || y_value > map.height as f32 * map.units_per_cell as f32;
+
+        if horizontally_out_of_map && vertically_out_of_map {
+            return RayResult { distance: 0.0, units_from_cell_start: 0.0, cell_type: CellType::Empty };
+        }
+
+        let x_at_y_value_intercept = (y_value - y_offset) / slope;
+        let y_at_x_value_intercept = slope * x_value + y_offset;
+
+        let distance_to_y_value = calc_distance(&origin, x_at_y_value_intercept, y_value);
+        let distance_to_x_value = calc_distance(&origin, x_value, y_at_x_value_intercept);
+
+        if distance_to_y_value.is_finite() && distance_to_y_value < distance_to_x_value {
+            // since we are on the border of two cells we need to look up the next cell over, depending on if we are going up or down
+            let adjustment = if rise > 0.0 { 0.1 } else { -0.1 };
+            let adjusted_value = y_value + adjustment;
+
+            let row = adjusted_value as u32 / map.units_per_cell;
+            let col = x_at_y_value_intercept as u32 / map.units_per_cell;
+
+            match get_ray_result(map, row as usize, col as usize, distance_to_y_value, x_at_y_value_intercept) {
+                Some(x) => return x,
+                _ => (),
+            }
+
+            // hit empty space
+            y_value += map.units_per_cell as f32 * if rise < 0.0 { -1.0 } else { 1.0 };
+        } else {
+            // since we are on the border of two cells we need to look up the next cell over, depending on if we are going right or left
+            let adjustment = if run > 0.0 { 0.1 } else { -0.1 };
+            let adjusted_value = x_value + adjustment;
+
+            let row = y_at_x_value_intercept as u32 / map.units_per_cell;
+            let col = adjusted_value as u32 / map.units_per_cell;
+
+            match get_ray_result(map, row as usize, col as usize, distance_to_x_value, y_at_x_value_intercept) {
+                Some(x) => return x,
+                _ => (),
+            }
+
+            // hit empty space
+            x_value += map.units_per_cell as f32 * if run < 0.0 { -1.0 } else { 1.0 };
+        }
+    }
+}
+
+fn calc_distance(first: &Vector, second_x: f32, second_y: f32) -> f32 {
+    let a = (first.y - second_y).abs();
+    let b = (first.x - second_x).abs();
+
+    (a * a + b * b).sqrt()
+}
+
+fn get_ray_result(map: &Map, row: usize, col: usize, distance: f32, position: f32) -> Option<RayResult> {
+    match map.cell_at(row, col) {
+        None => None,
+        Some(CellType::Empty) => None,
+        Some(cell_type) => Some(RayResult {
+            distance,
+            units_from_cell_start: position % map.units_per_cell as f32,
+            cell_type,
+        }),
+    }
+}
+
+/// Returns true if no walls are between the two positions
+pub fn has_line_of_sight(map: &Map, from: Vector, to: Vector) -> bool {
+    let delta = to - from;
+    let distance = delta.length();
+    if distance == 0.0 {
+        return true;
+    }
+
+    let ray = cast_ray(map, from, Radians(delta.y.atan2(delta.x)).clamp());
+    ray.distance == 0.0 || ray.distance >= distance
+}
+
+/// Returns how far along the ray the circle is first touched, if at all.  The direction must be a
+/// unit vector.
+pub fn ray_hits_circle(origin: Vector, direction: Vector, center: Vector, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let b = offset.x * direction.x + offset.y * direction.y;
+    let c = offset.x * offset.x + offset.y * offset.y - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    if -b - root >= 0.0 {
+        Some(-b - root)
+    } else if -b + root >= 0.0 {
+        // The ray starts inside the circle
+        Some(0.0)
+    } else {
+        None
+    }
+}
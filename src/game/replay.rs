@@ -0,0 +1,286 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::time::Duration;
+use crate::core::vector::Vector;
+use crate::game::{GameState, ActiveInputs};
+use crate::game::map::Map;
+use crate::game::text_format::{parse_value, invalid_data};
+
+const REPLAY_HEADER: &str = "rustcaster-replay";
+const REPLAY_VERSION: u32 = 1;
+
+/// A recording of every tick of a game session, which can be fed back into a fresh `GameState`
+/// to reproduce the session exactly.
+pub struct Replay {
+    /// The maps of the episode that was played, which is a single map unless an episode was given
+    pub map_ids: Vec<String>,
+    pub seed: u64,
+    pub ticks: Vec<ReplayTick>,
+    pub final_position: Option<Vector>,
+}
+
+pub struct ReplayTick {
+    pub time_since_last_frame: Duration,
+    pub inputs: ActiveInputs,
+}
+
+pub enum ReplayOutcome {
+    Matched(Vector),
+    Mismatched { expected: Vector, actual: Vector },
+    NoExpectedPosition(Vector),
+}
+
+impl Replay {
+    pub fn new(map_ids: Vec<String>, seed: u64) -> Self {
+        Replay {
+            map_ids,
+            seed,
+            ticks: Vec::new(),
+            final_position: None,
+        }
+    }
+
+    pub fn record(&mut self, time_since_last_frame: &Duration, inputs: &ActiveInputs) {
+        self.ticks.push(ReplayTick {
+            time_since_last_frame: *time_since_last_frame,
+            inputs: inputs.clone(),
+        });
+    }
+
+    pub fn finish(&mut self, game_state: &GameState) {
+        self.final_position = Some(game_state.player.position);
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{} {}", REPLAY_HEADER, REPLAY_VERSION)?;
+        for map_id in &self.map_ids {
+            writeln!(writer, "map {}", map_id)?;
+        }
+
+        writeln!(writer, "seed {}", self.seed)?;
+
+        for tick in &self.ticks {
+            write!(writer, "tick {}", tick.time_since_last_frame.as_nanos())?;
+
+            let mut inputs = tick.inputs.clone();
+            for (name, value) in input_flags(&mut inputs) {
+                if *value {
+                    write!(writer, " {}", name)?;
+                }
+            }
+
+            writeln!(writer)?;
+        }
+
+        if let Some(position) = self.final_position {
+            writeln!(writer, "end {} {}", position.x, position.y)?;
+        }
+
+        writer.flush()
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut lines = reader.lines();
+
+        let header = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+        let mut header_parts = header.split_whitespace();
+        if header_parts.next() != Some(REPLAY_HEADER) {
+            return Err(invalid_data(format!("Expected replay header '{}' but found '{}'", REPLAY_HEADER, header)));
+        }
+
+        let version: u32 = parse_value(header_parts.next(), "replay version")?;
+        if version != REPLAY_VERSION {
+            return Err(invalid_data(format!("Unsupported replay version {} (expected {})", version, REPLAY_VERSION)));
+        }
+
+        let mut replay = Replay::new(Vec::new(), 0);
+        for line in lines {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                None => continue,
+                Some("map") => replay.map_ids.push(parts.collect::<Vec<_>>().join(" ")),
+                Some("seed") => replay.seed = parse_value(parts.next(), "replay seed")?,
+                Some("tick") => {
+                    let nanos: u64 = parse_value(parts.next(), "replay tick duration")?;
+                    let mut inputs = ActiveInputs::new();
+                    for name in parts {
+                        let mut found = false;
+                        for (flag_name, value) in input_flags(&mut inputs) {
+                            if flag_name == name {
+                                *value = true;
+                                found = true;
+                            }
+                        }
+
+                        if !found {
+                            return Err(invalid_data(format!("Unknown input '{}' in replay", name)));
+                        }
+                    }
+
+                    replay.ticks.push(ReplayTick {
+                        time_since_last_frame: Duration::from_nanos(nanos),
+                        inputs,
+                    });
+                }
+
+                Some("end") => {
+                    replay.final_position = Some(Vector {
+                        x: parse_value(parts.next(), "replay end x")?,
+                        y: parse_value(parts.next(), "replay end y")?,
+                    });
+                }
+
+                Some(x) => return Err(invalid_data(format!("Unknown replay entry '{}'", x))),
+            }
+        }
+
+        Ok(replay)
+    }
+
+    /// Runs every recorded tick against a fresh game state, without any rendering
+    pub fn play(&self) -> io::Result<GameState> {
+        self.play_with(|_| ())
+    }
+
+    /// Plays the replay like `play`, showing the callback the game state as it starts and after
+    /// every tick
+    pub fn play_with(&self, mut on_tick: impl FnMut(&GameState)) -> io::Result<GameState> {
+        if self.map_ids.is_empty() {
+            return Err(invalid_data("Replay does not specify a map".to_owned()));
+        }
+
+        let levels = self.map_ids.iter().map(|x| Map::load(x)).collect::<io::Result<Vec<_>>>()?;
+        let mut game_state = GameState::with_levels(levels, 0, self.seed)?;
+        on_tick(&game_state);
+        for tick in &self.ticks {
+            game_state.tick(&tick.time_since_last_frame, &tick.inputs);
+            on_tick(&game_state);
+        }
+
+        Ok(game_state)
+    }
+
+    pub fn verify(&self) -> io::Result<ReplayOutcome> {
+        let game_state = self.play()?;
+        let actual = game_state.player.position;
+
+        Ok(match self.final_position {
+            None => ReplayOutcome::NoExpectedPosition(actual),
+            Some(expected) if expected.x == actual.x && expected.y == actual.y => ReplayOutcome::Matched(actual),
+            Some(expected) => ReplayOutcome::Mismatched { expected, actual },
+        })
+    }
+}
+
+fn input_flags(inputs: &mut ActiveInputs) -> Vec<(&'static str, &mut bool)> {
+    vec![
+        ("exit_game", &mut inputs.exit_game),
+        ("turn_left", &mut inputs.turn_left),
+        ("turn_right", &mut inputs.turn_right),
+        ("move_forward", &mut inputs.move_forward),
+        ("move_back", &mut inputs.move_back),
+        ("zoom_in", &mut inputs.zoom_in),
+        ("zoom_out", &mut inputs.zoom_out),
+        ("toggle_map", &mut inputs.toggle_map),
+        ("cycle_map_view", &mut inputs.cycle_map_view),
+        ("toggle_minimap_rotation", &mut inputs.toggle_minimap_rotation),
+        ("toggle_map_textures", &mut inputs.toggle_map_textures),
+        ("pan_map_up", &mut inputs.pan_map_up),
+        ("pan_map_down", &mut inputs.pan_map_down),
+        ("pan_map_left", &mut inputs.pan_map_left),
+        ("pan_map_right", &mut inputs.pan_map_right),
+        ("toggle_test", &mut inputs.toggle_test),
+        ("toggle_paths", &mut inputs.toggle_paths),
+        ("toggle_performance", &mut inputs.toggle_performance),
+        ("fire", &mut inputs.fire),
+        ("select_weapon_1", &mut inputs.select_weapon_1),
+        ("select_weapon_2", &mut inputs.select_weapon_2),
+        ("select_weapon_3", &mut inputs.select_weapon_3),
+        ("select_weapon_4", &mut inputs.select_weapon_4),
+        ("use_action", &mut inputs.use_action),
+        ("quick_save", &mut inputs.quick_save),
+        ("quick_load", &mut inputs.quick_load),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use super::*;
+
+    const TEST_MAP: &str = "\
+xxxxxxxxx
+x@    r x
+x       x
+x    g  x
+x   o   x
+xxxxxxxxx
+";
+
+    fn scripted_inputs(tick: usize) -> ActiveInputs {
+        let mut inputs = ActiveInputs::new();
+        match tick {
+            0..=39 => inputs.move_forward = true,
+            40..=59 => inputs.turn_right = true,
+            60..=89 => {
+                inputs.move_forward = true;
+                inputs.fire = tick.is_multiple_of(10);
+            }
+
+            _ => {
+                inputs.turn_left = tick.is_multiple_of(3);
+                inputs.fire = tick.is_multiple_of(7);
+            }
+        }
+
+        inputs
+    }
+
+    #[test]
+    fn playback_matches_the_recorded_session() {
+        let directory = env::temp_dir().join(format!("rustcaster-replay-test-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let map_path = directory.join("replay.map").to_str().unwrap().to_owned();
+        let replay_path = directory.join("test.replay").to_str().unwrap().to_owned();
+        fs::write(&map_path, TEST_MAP).unwrap();
+
+        let mut game_state = GameState::new(Map::load(&map_path).unwrap(), 7).unwrap();
+        let start = game_state.player.position;
+        let mut replay = Replay::new(vec![map_path.clone()], game_state.seed);
+        for tick in 0..200 {
+            // Uneven frame times, including an empty frame, are recorded exactly
+            let time_since_last_frame = Duration::from_millis(if tick == 50 { 0 } else { 10 + tick as u64 % 7 });
+            let inputs = scripted_inputs(tick);
+            game_state.tick(&time_since_last_frame, &inputs);
+            replay.record(&time_since_last_frame, &inputs);
+        }
+
+        replay.finish(&game_state);
+        replay.save(&replay_path).unwrap();
+        let played = Replay::load(&replay_path).unwrap().play().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let (expected, actual) = (game_state.player.position, played.player.position);
+        assert!(expected.x != start.x || expected.y != start.y, "the scripted inputs never moved the player");
+        assert!(expected.x == actual.x && expected.y == actual.y, "player ended at ({}, {}) instead of ({}, {})",
+                actual.x, actual.y, expected.x, expected.y);
+        assert_eq!(played.player.health, game_state.player.health);
+        assert_eq!(played.player.ammo, game_state.player.ammo);
+
+        let entities = game_state.entities.iter().collect::<Vec<_>>();
+        let played_entities = played.entities.iter().collect::<Vec<_>>();
+        assert_eq!(played_entities.len(), entities.len());
+        for (expected, actual) in entities.iter().zip(played_entities.iter()) {
+            assert_eq!(actual.id, expected.id);
+            assert_eq!(actual.kind, expected.kind);
+            assert_eq!(actual.health, expected.health);
+            assert_eq!(actual.sprite, expected.sprite);
+            assert!(actual.position.x == expected.position.x && actual.position.y == expected.position.y);
+        }
+    }
+}
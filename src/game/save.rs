@@ -0,0 +1,452 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use crate::core::radians::Radians;
+use crate::core::random::Random;
+use crate::core::vector::Vector;
+use crate::game::{GameState, MapView};
+use crate::game::entities::{EntityKind, Behaviour};
+use crate::game::ai::{Enemy, EnemyState, EnemyAttack};
+use crate::game::projectiles::{Projectile, explosion_sprite};
+use crate::game::weapons::WeaponKind;
+use crate::game::map::{Map, CellType};
+use crate::game::items::KeyColor;
+use crate::game::levels::LevelStats;
+use crate::game::pathfinding::CellPosition;
+use crate::game::scripting::ScriptTimer;
+use crate::game::text_format::{parse_value, invalid_data};
+
+const SAVE_HEADER: &str = "rustcaster-save";
+const SAVE_VERSION: u32 = 1;
+
+pub fn save_game(game_state: &GameState, path: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{} {}", SAVE_HEADER, SAVE_VERSION)?;
+    writeln!(writer, "map {}", game_state.map.id)?;
+
+    // Saves from a single map run only need the map above
+    if game_state.levels.len() > 1 {
+        for level in &game_state.levels {
+            writeln!(writer, "level_map {}", level.id)?;
+        }
+
+        writeln!(writer, "level {}", game_state.level)?;
+    }
+
+    writeln!(writer, "level_seconds {}", game_state.level_seconds)?;
+    writeln!(writer, "level_started {}", game_state.level_started)?;
+    writeln!(writer, "secrets_found {}", game_state.secrets_found)?;
+    if game_state.completed.is_some() {
+        writeln!(writer, "completed {}", game_state.seconds_since_completed)?;
+    }
+
+    writeln!(writer, "light_level {}", game_state.light_level)?;
+    for index in &game_state.spent_triggers {
+        writeln!(writer, "spent_trigger {}", index)?;
+    }
+
+    if let Some(message) = &game_state.message {
+        writeln!(writer, "message {} {}", game_state.message_seconds, message)?;
+    }
+
+    for (name, value) in &game_state.script_vars {
+        writeln!(writer, "script_var {} {}", value, name)?;
+    }
+
+    for timer in &game_state.script_timers {
+        writeln!(writer, "script_timer {} {}", timer.seconds_left, timer.name)?;
+    }
+
+    writeln!(writer, "seed {}", game_state.seed)?;
+    writeln!(writer, "random {}", game_state.random.state())?;
+    writeln!(writer, "player {} {} {}",
+             game_state.player.position.x,
+             game_state.player.position.y,
+             game_state.player.facing.0)?;
+
+    writeln!(writer, "player_health {}", game_state.player.health)?;
+    writeln!(writer, "player_ammo {}", game_state.player.ammo)?;
+    writeln!(writer, "player_weapon {}", game_state.player.weapon.name())?;
+    write!(writer, "player_weapons")?;
+    for weapon in &game_state.player.weapons {
+        write!(writer, " {}", weapon.name())?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "player_seconds_since_death {}", game_state.player.seconds_since_death)?;
+    write!(writer, "player_keys")?;
+    for key in &game_state.player.inventory.keys {
+        write!(writer, " {}", key.name())?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "player_treasure {}", game_state.player.inventory.treasure)?;
+
+    writeln!(writer, "map_zoom_level {}", game_state.map_zoom_level)?;
+    writeln!(writer, "display_map {}", game_state.display_map)?;
+    writeln!(writer, "map_view {}", game_state.map_view.name())?;
+    writeln!(writer, "rotate_minimap {}", game_state.rotate_minimap)?;
+    writeln!(writer, "textured_map {}", game_state.textured_map)?;
+    writeln!(writer, "automap_offset {} {}", game_state.automap_offset.x, game_state.automap_offset.y)?;
+    writeln!(writer, "display_paths {}", game_state.display_paths)?;
+    writeln!(writer, "display_performance {}", game_state.display_performance)?;
+    writeln!(writer, "test_mode {}", game_state.test_mode)?;
+
+    // Only cells that changed since the level started, such as opened doors, need saving
+    for row in 0..game_state.map.height {
+        for col in 0..game_state.map.width {
+            let cell = game_state.map.cell_at(row, col);
+            if let Some(cell) = cell.filter(|x| Some(*x) != game_state.initial_map.cell_at(row, col)) {
+                writeln!(writer, "cell {} {} {}", row, col, cell.name())?;
+            }
+        }
+    }
+
+    // Each row of explored cells is saved as a string of 1s and 0s, skipping rows with none
+    for row in 0..game_state.map.height {
+        let cells = (0..game_state.map.width)
+            .map(|col| if game_state.explored.is_explored(row, col) { '1' } else { '0' })
+            .collect::<String>();
+
+        if cells.contains('1') {
+            writeln!(writer, "explored {} {}", row, cells)?;
+        }
+    }
+
+    for entity in game_state.entities.iter() {
+        write!(writer, "entity {} {} {} {} {} {}",
+               entity.id,
+               entity.kind.name(),
+               entity.position.x,
+               entity.position.y,
+               entity.facing.0,
+               entity.health)?;
+
+        match entity.behaviour {
+            Behaviour::Static => writeln!(writer, " static")?,
+            Behaviour::Wander { seconds_until_turn } => writeln!(writer, " wander {}", seconds_until_turn)?,
+            Behaviour::Enemy(enemy) => {
+                writeln!(writer, " enemy {} {} {} {} {} {} {} {} {}",
+                         enemy.state.name(),
+                         enemy.resting_state.name(),
+                         enemy.attack.name(),
+                         enemy.state_seconds,
+                         enemy.attack_cooldown,
+                         enemy.seconds_since_seen_player,
+                         enemy.last_known_player_position.x,
+                         enemy.last_known_player_position.y,
+                         enemy.health_last_tick)?;
+            }
+
+            Behaviour::Projectile(projectile) => {
+                let owner = projectile.owner.map_or("none".to_owned(), |x| x.to_string());
+                writeln!(writer, " projectile {} {} {} {}",
+                         owner,
+                         projectile.speed,
+                         projectile.damage,
+                         projectile.splash_radius)?;
+            }
+
+            Behaviour::Explosion { seconds_left } => writeln!(writer, " explosion {}", seconds_left)?,
+
+            // The item is always the same for each kind of pickup
+            Behaviour::Pickup(_) => writeln!(writer, " pickup")?,
+        }
+    }
+
+    writer.flush()
+}
+
+pub fn load_game(path: &str) -> io::Result<GameState> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines();
+
+    let header = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+    let mut header_parts = header.split_whitespace();
+    if header_parts.next() != Some(SAVE_HEADER) {
+        return Err(invalid_data(format!("'{}' is not a save file", path)));
+    }
+
+    let version: u32 = parse_value(header_parts.next(), "save version")?;
+    if version != SAVE_VERSION {
+        return Err(invalid_data(format!("Unsupported save version {} (expected {})", version, SAVE_VERSION)));
+    }
+
+    // The map has to be known before anything else can be applied to it
+    let lines = lines.collect::<io::Result<Vec<String>>>()?;
+    let map_id = match lines.iter().find(|x| x.starts_with("map ")) {
+        Some(line) => line["map ".len()..].to_owned(),
+        None => return Err(invalid_data("Save file does not specify a map".to_owned())),
+    };
+
+    let level_map_ids = lines.iter()
+        .filter(|x| x.starts_with("level_map "))
+        .map(|x| &x["level_map ".len()..])
+        .collect::<Vec<_>>();
+
+    let mut game_state = if level_map_ids.is_empty() {
+        GameState::new(Map::load(&map_id)?, 0)?
+    } else {
+        let level = match lines.iter().find(|x| x.starts_with("level ")) {
+            Some(line) => parse_value(line.split_whitespace().nth(1), "level")?,
+            None => return Err(invalid_data("Save file does not specify which level is being played".to_owned())),
+        };
+
+        if level >= level_map_ids.len() {
+            return Err(invalid_data(format!("Level {} is not in the episode", level)));
+        }
+
+        let levels = level_map_ids.iter().map(|x| Map::load(x)).collect::<io::Result<Vec<_>>>()?;
+        GameState::with_levels(levels, level, 0)?
+    };
+
+    // Entities are saved individually, so don't keep the ones the map spawned
+    let mut completed = false;
+    game_state.entities.clear();
+
+    for line in &lines {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            None => continue,
+            Some("map") | Some("level_map") | Some("level") => (),
+            Some("level_seconds") => game_state.level_seconds = parse_value(parts.next(), "level time")?,
+            Some("level_started") => game_state.level_started = parse_value(parts.next(), "level started")?,
+            Some("secrets_found") => game_state.secrets_found = parse_value(parts.next(), "secrets found")?,
+            Some("light_level") => game_state.light_level = parse_value(parts.next(), "light level")?,
+            Some("spent_trigger") => {
+                let index = parse_value(parts.next(), "spent trigger")?;
+                if index >= game_state.map.triggers.len() {
+                    return Err(invalid_data(format!("Trigger {} is not in the map", index)));
+                }
+
+                game_state.spent_triggers.push(index);
+            }
+
+            Some("message") => {
+                game_state.message_seconds = parse_value(parts.next(), "message time")?;
+                game_state.message = Some(parts.collect::<Vec<_>>().join(" "));
+            }
+
+            Some("script_var") => {
+                let value = parse_value(parts.next(), "script variable")?;
+                game_state.script_vars.insert(parts.collect::<Vec<_>>().join(" "), value);
+            }
+
+            Some("script_timer") => {
+                let seconds_left = parse_value(parts.next(), "script timer")?;
+                let name = parts.collect::<Vec<_>>().join(" ");
+                game_state.script_timers.push(ScriptTimer { name, seconds_left });
+            }
+
+            Some("completed") => {
+                game_state.seconds_since_completed = parse_value(parts.next(), "time since level completed")?;
+                completed = true;
+            }
+
+            Some("seed") => game_state.seed = parse_value(parts.next(), "seed")?,
+            Some("random") => game_state.random = Random::new(parse_value(parts.next(), "random state")?),
+            Some("player") => {
+                game_state.player.position.x = parse_value(parts.next(), "player x")?;
+                game_state.player.position.y = parse_value(parts.next(), "player y")?;
+                game_state.player.facing = Radians(parse_value(parts.next(), "player facing")?);
+            }
+
+            Some("player_health") => game_state.player.health = parse_value(parts.next(), "player health")?,
+            Some("player_ammo") => game_state.player.ammo = parse_value(parts.next(), "player ammo")?,
+            Some("player_weapon") => game_state.player.weapon = parse_weapon(parts.next())?,
+            Some("player_weapons") => {
+                game_state.player.weapons = parts.map(|x| parse_weapon(Some(x))).collect::<io::Result<Vec<_>>>()?;
+            }
+
+            Some("player_seconds_since_death") => {
+                game_state.player.seconds_since_death = parse_value(parts.next(), "player time since death")?;
+            }
+
+            Some("player_keys") => {
+                game_state.player.inventory.keys = parts.map(|x| parse_key(Some(x))).collect::<io::Result<Vec<_>>>()?;
+            }
+
+            Some("player_treasure") => {
+                game_state.player.inventory.treasure = parse_value(parts.next(), "player treasure")?;
+            }
+
+            Some("map_zoom_level") => game_state.map_zoom_level = parse_value(parts.next(), "map zoom level")?,
+            Some("display_map") => game_state.display_map = parse_value(parts.next(), "display map")?,
+            Some("map_view") => game_state.map_view = parse_map_view(parts.next())?,
+            Some("rotate_minimap") => game_state.rotate_minimap = parse_value(parts.next(), "rotate minimap")?,
+            Some("textured_map") => game_state.textured_map = parse_value(parts.next(), "textured map")?,
+            Some("automap_offset") => {
+                game_state.automap_offset.x = parse_value(parts.next(), "automap offset x")?;
+                game_state.automap_offset.y = parse_value(parts.next(), "automap offset y")?;
+            }
+
+            Some("display_paths") => game_state.display_paths = parse_value(parts.next(), "display paths")?,
+            Some("display_performance") => {
+                game_state.display_performance = parse_value(parts.next(), "display performance")?;
+            }
+
+            Some("test_mode") => game_state.test_mode = parse_value(parts.next(), "test mode")?,
+            Some("cell") => {
+                let row = parse_value(parts.next(), "cell row")?;
+                let col = parse_value(parts.next(), "cell column")?;
+                let cell_type = parse_cell_type(parts.next())?;
+                if game_state.map.cell_at(row, col).is_none() {
+                    return Err(invalid_data(format!("Cell {},{} is outside of the map", row, col)));
+                }
+
+                game_state.map.set_cell(row, col, cell_type);
+            }
+
+            Some("explored") => {
+                let row = parse_value(parts.next(), "explored row")?;
+                let cells: String = parse_value(parts.next(), "explored cells")?;
+                if row >= game_state.map.height || cells.len() != game_state.map.width {
+                    return Err(invalid_data(format!("Explored row {} does not fit the map", row)));
+                }
+
+                for (col, value) in cells.chars().enumerate() {
+                    match value {
+                        '1' => game_state.explored.explore(row, col),
+                        '0' => (),
+                        x => return Err(invalid_data(format!("Invalid explored cell '{}'", x))),
+                    }
+                }
+            }
+
+            Some("entity") => {
+                let id = parse_value(parts.next(), "entity id")?;
+                let kind_name: String = parse_value(parts.next(), "entity kind")?;
+                let kind = match EntityKind::from_name(&kind_name) {
+                    Some(x) => x,
+                    None => return Err(invalid_data(format!("Unknown entity kind '{}'", kind_name))),
+                };
+
+                let position = Vector {
+                    x: parse_value(parts.next(), "entity x")?,
+                    y: parse_value(parts.next(), "entity y")?,
+                };
+
+                let facing = Radians(parse_value(parts.next(), "entity facing")?);
+                let health = parse_value(parts.next(), "entity health")?;
+
+                // Pickups keep the item they were spawned with, so have no behaviour to restore
+                let behaviour = match parts.next() {
+                    Some("pickup") => None,
+                    Some("static") => Some(Behaviour::Static),
+                    Some("wander") => Some(Behaviour::Wander {
+                        seconds_until_turn: parse_value(parts.next(), "wander time")?,
+                    }),
+
+                    Some("enemy") => {
+                        let state = parse_enemy_state(parts.next())?;
+                        let resting_state = parse_enemy_state(parts.next())?;
+                        let attack = parse_enemy_attack(parts.next())?;
+                        let mut enemy = Enemy::new(resting_state, attack, 0);
+                        enemy.state = state;
+                        enemy.state_seconds = parse_value(parts.next(), "enemy state time")?;
+                        enemy.attack_cooldown = parse_value(parts.next(), "enemy attack cooldown")?;
+                        enemy.seconds_since_seen_player = parse_value(parts.next(), "enemy time since seen player")?;
+                        enemy.last_known_player_position = Vector {
+                            x: parse_value(parts.next(), "enemy last known player x")?,
+                            y: parse_value(parts.next(), "enemy last known player y")?,
+                        };
+
+                        enemy.health_last_tick = parse_value(parts.next(), "enemy health last tick")?;
+                        Some(Behaviour::Enemy(enemy))
+                    }
+
+                    Some("projectile") => {
+                        let owner = match parts.next() {
+                            Some("none") => None,
+                            x => Some(parse_value(x, "projectile owner")?),
+                        };
+
+                        Some(Behaviour::Projectile(Projectile::new(owner,
+                                                                   parse_value(parts.next(), "projectile speed")?,
+                                                                   parse_value(parts.next(), "projectile damage")?,
+                                                                   parse_value(parts.next(), "projectile splash radius")?)))
+                    }
+
+                    Some("explosion") => Some(Behaviour::Explosion {
+                        seconds_left: parse_value(parts.next(), "explosion time")?,
+                    }),
+
+                    x => return Err(invalid_data(format!("Unknown entity behaviour '{}'", x.unwrap_or("")))),
+                };
+
+                game_state.entities.spawn_with_id(id, kind, position);
+                let entity = game_state.entities.get_mut(id).unwrap();
+                entity.facing = facing;
+                match behaviour {
+                    // Explosions replace the sprite of the projectile that exploded
+                    Some(Behaviour::Explosion { seconds_left }) => {
+                        entity.behaviour = Behaviour::Explosion { seconds_left };
+                        entity.sprite = explosion_sprite(seconds_left);
+                    }
+
+                    Some(behaviour) => entity.behaviour = behaviour,
+                    None if matches!(entity.behaviour, Behaviour::Pickup(_)) => (),
+                    None => return Err(invalid_data(format!("Entity kind '{}' is not a pickup", kind_name))),
+                }
+
+                entity.health = health;
+            }
+
+            Some(x) => return Err(invalid_data(format!("Unknown save entry '{}'", x))),
+        }
+    }
+
+    // Otherwise the player would set off the walk triggers of the cell they were saved in
+    game_state.player_cell = CellPosition::from_position(&game_state.map, game_state.player.position);
+
+    // The stats only depend on what was saved, so are worked out again once everything is loaded
+    if completed {
+        game_state.completed = Some(LevelStats::new(game_state.level_seconds,
+                                                    &game_state.entities,
+                                                    &game_state.initial_map,
+                                                    game_state.secrets_found));
+    }
+
+    Ok(game_state)
+}
+
+fn parse_enemy_state(value: Option<&str>) -> io::Result<EnemyState> {
+    match value.and_then(EnemyState::from_name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unknown enemy state '{}'", value.unwrap_or("")))),
+    }
+}
+
+fn parse_enemy_attack(value: Option<&str>) -> io::Result<EnemyAttack> {
+    match value.and_then(EnemyAttack::from_name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unknown enemy attack '{}'", value.unwrap_or("")))),
+    }
+}
+
+fn parse_map_view(value: Option<&str>) -> io::Result<MapView> {
+    match value.and_then(MapView::from_name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unknown map view '{}'", value.unwrap_or("")))),
+    }
+}
+
+fn parse_key(value: Option<&str>) -> io::Result<KeyColor> {
+    match value.and_then(KeyColor::from_name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unknown key '{}'", value.unwrap_or("")))),
+    }
+}
+
+fn parse_cell_type(value: Option<&str>) -> io::Result<CellType> {
+    match value.and_then(CellType::from_name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unknown cell type '{}'", value.unwrap_or("")))),
+    }
+}
+
+fn parse_weapon(value: Option<&str>) -> io::Result<WeaponKind> {
+    match value.and_then(WeaponKind::from_name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unknown weapon '{}'", value.unwrap_or("")))),
+    }
+}
@@ -0,0 +1,431 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+use rhai::{Engine, AST, Scope, Dynamic, EvalAltResult, CallFnOptions};
+use crate::game::map::{Map, CellType};
+use crate::game::entities::{EntityId, EntityKind};
+use crate::game::pathfinding::CellPosition;
+use crate::game::triggers::{TriggerAction, MAX_LIGHT_LEVEL};
+
+/// How much work a script can do handling a single event before it is stopped, so a script stuck
+/// in a loop can't freeze the game
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPRESSION_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 1024;
+const MAX_COLLECTION_SIZE: usize = 1024;
+
+/// Something that happened in the level, which scripts handle by defining a function of the same
+/// name, such as `fn on_enter_cell(row, col)`
+pub enum ScriptEvent {
+    /// The first tick of the level
+    LevelStart,
+    EnterCell(CellPosition),
+    EntityDied { id: EntityId, kind: EntityKind },
+
+    /// A timer the script started has run out
+    Timer(String),
+}
+
+/// Something a script asked for, which is carried out once the script has finished handling the
+/// event
+pub enum ScriptCommand {
+    Action(TriggerAction),
+
+    /// Starts the named timer, replacing any timer of the same name.  No time at all stops it.
+    SetTimer { name: String, seconds: f32 },
+}
+
+/// Everything a script can see of the game, lent to it while it handles an event.  Scripts can
+/// only change the game by leaving commands, apart from their own variables.
+pub struct ScriptWorld {
+    pub map: Map,
+    pub player_cell: Option<CellPosition>,
+    pub player_health: i32,
+    pub level_seconds: f32,
+
+    /// Numbers the script keeps between events, since script functions can't keep anything
+    /// themselves
+    pub vars: BTreeMap<String, i64>,
+    pub commands: Vec<ScriptCommand>,
+}
+
+/// A countdown started by a script, which sends it an event when it runs out
+#[derive(Clone)]
+pub struct ScriptTimer {
+    pub name: String,
+    pub seconds_left: f32,
+}
+
+type SharedWorld = Rc<RefCell<Option<ScriptWorld>>>;
+
+/// The Rhai script that runs a level's logic.  Scripts can't touch files or anything outside of
+/// the functions registered here, and are stopped if they take too long.
+pub struct LevelScript {
+    pub path: PathBuf,
+    engine: Engine,
+
+    /// Missing if the script has never compiled, in which case events are ignored until it is
+    /// fixed and reloaded
+    ast: Option<AST>,
+    modified: Option<SystemTime>,
+    world: SharedWorld,
+}
+
+impl ScriptEvent {
+    fn function_name(&self) -> &'static str {
+        match self {
+            ScriptEvent::LevelStart => "on_level_start",
+            ScriptEvent::EnterCell(_) => "on_enter_cell",
+            ScriptEvent::EntityDied { .. } => "on_entity_died",
+            ScriptEvent::Timer(_) => "on_timer",
+        }
+    }
+
+    fn arguments(&self) -> Vec<Dynamic> {
+        match self {
+            ScriptEvent::LevelStart => Vec::new(),
+            ScriptEvent::EnterCell(cell) => vec![(cell.row as i64).into(), (cell.col as i64).into()],
+            ScriptEvent::EntityDied { id, kind } => vec![(*id as i64).into(), kind.name().into()],
+            ScriptEvent::Timer(name) => vec![name.clone().into()],
+        }
+    }
+}
+
+impl LevelScript {
+    /// Compiles the script at the path.  A script that doesn't compile is reported and keeps
+    /// being checked for changes, so it can be fixed while the game is running.
+    pub fn load(path: PathBuf) -> Self {
+        let world = Rc::new(RefCell::new(None));
+        let mut script = LevelScript {
+            engine: create_engine(&path, &world),
+            path,
+            ast: None,
+            modified: None,
+            world,
+        };
+
+        script.reload_if_changed();
+        script
+    }
+
+    /// Recompiles the script if its file has changed since it was last compiled.  Returns true if
+    /// a new version of the script is now running.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let modified = fs::metadata(&self.path).and_then(|x| x.modified()).ok();
+        if modified.is_some() && modified == self.modified {
+            return false;
+        }
+
+        self.modified = modified;
+        let compiled = fs::read_to_string(&self.path)
+            .map_err(|err| err.to_string())
+            .and_then(|source| self.engine.compile(&source).map_err(|err| err.to_string()));
+
+        match compiled {
+            Ok(ast) => {
+                self.ast = Some(ast);
+                true
+            }
+
+            Err(err) => {
+                eprintln!("Failed to load script '{}': {}", self.path.display(), err);
+                false
+            }
+        }
+    }
+
+    /// Returns true if the script has a function for the event, so the game doesn't have to lend
+    /// it the world for nothing
+    pub fn handles(&self, event: &ScriptEvent) -> bool {
+        let arguments = event.arguments().len();
+        match &self.ast {
+            Some(ast) => ast.iter_functions().any(|x| x.name == event.function_name() && x.params.len() == arguments),
+            None => false,
+        }
+    }
+
+    /// Calls the script's function for the event, and hands the world back along with whatever
+    /// the script asked for.  Errors are reported but otherwise ignored, so a broken script
+    /// doesn't stop the game.
+    pub fn handle(&mut self, event: &ScriptEvent, world: ScriptWorld) -> ScriptWorld {
+        let ast = match &self.ast {
+            Some(x) if self.handles(event) => x,
+            _ => return world,
+        };
+
+        *self.world.borrow_mut() = Some(world);
+
+        // The top level of the script isn't run, as it would run again for every event
+        let options = CallFnOptions::new().eval_ast(false);
+        let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, event.function_name(), event.arguments());
+        if let Err(err) = result {
+            eprintln!("Script '{}' failed in {}: {}", self.path.display(), event.function_name(), err);
+        }
+
+        self.world.borrow_mut().take().unwrap()
+    }
+}
+
+fn create_engine(path: &Path, world: &SharedWorld) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPRESSION_DEPTH, MAX_EXPRESSION_DEPTH)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE)
+        .set_max_modules(0)
+        .disable_symbol("eval");
+
+    let print_path = path.display().to_string();
+    engine.on_print(move |text| println!("[{}] {}", print_path, text));
+    let debug_path = path.display().to_string();
+    engine.on_debug(move |text, _, position| println!("[{} {}] {}", debug_path, position, text));
+
+    let shared = world.clone();
+    engine.register_fn("player_row", move || -> Result<i64, Box<EvalAltResult>> {
+        with_world(&shared, |world| world.player_cell.map_or(-1, |x| x.row as i64))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("player_col", move || -> Result<i64, Box<EvalAltResult>> {
+        with_world(&shared, |world| world.player_cell.map_or(-1, |x| x.col as i64))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("player_health", move || -> Result<i64, Box<EvalAltResult>> {
+        with_world(&shared, |world| world.player_health as i64)
+    });
+
+    let shared = world.clone();
+    engine.register_fn("level_seconds", move || -> Result<f64, Box<EvalAltResult>> {
+        with_world(&shared, |world| world.level_seconds as f64)
+    });
+
+    let shared = world.clone();
+    engine.register_fn("cell", move |row: i64, col: i64| -> Result<String, Box<EvalAltResult>> {
+        let (row, col) = (to_index(row)?, to_index(col)?);
+        with_world(&shared, |world| world.map.cell_at(row, col).map_or("", |x| x.name()).to_owned())
+    });
+
+    let shared = world.clone();
+    engine.register_fn("get_var", move |name: &str| -> Result<i64, Box<EvalAltResult>> {
+        with_world(&shared, |world| world.vars.get(name).copied().unwrap_or(0))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("set_var", move |name: &str, value: i64| -> Result<(), Box<EvalAltResult>> {
+        with_world(&shared, |world| { world.vars.insert(name.to_owned(), value); })
+    });
+
+    let shared = world.clone();
+    engine.register_fn("set_timer", move |name: &str, seconds: f64| -> Result<(), Box<EvalAltResult>> {
+        let command = ScriptCommand::SetTimer { name: name.to_owned(), seconds: seconds.max(0.0) as f32 };
+        with_world(&shared, |world| world.commands.push(command))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("stop_timer", move |name: &str| -> Result<(), Box<EvalAltResult>> {
+        let command = ScriptCommand::SetTimer { name: name.to_owned(), seconds: 0.0 };
+        with_world(&shared, |world| world.commands.push(command))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("message", move |text: &str| -> Result<(), Box<EvalAltResult>> {
+        push_action(&shared, TriggerAction::Message(text.to_owned()))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("set_light", move |level: i64| -> Result<(), Box<EvalAltResult>> {
+        let level = level.max(0).min(MAX_LIGHT_LEVEL as i64) as u32;
+        push_action(&shared, TriggerAction::Light(level))
+    });
+
+    let shared = world.clone();
+    engine.register_fn("open_door", move |row: i64, col: i64| -> Result<(), Box<EvalAltResult>> {
+        push_action(&shared, TriggerAction::OpenDoor { row: to_index(row)?, col: to_index(col)? })
+    });
+
+    let shared = world.clone();
+    engine.register_fn("toggle_wall", move |row: i64, col: i64, wall: &str| -> Result<(), Box<EvalAltResult>> {
+        let cell_type = match CellType::from_name(wall) {
+            Some(CellType::Empty) | None => return Err(format!("Unknown wall '{}'", wall).into()),
+            Some(x) => x,
+        };
+
+        push_action(&shared, TriggerAction::ToggleWall { row: to_index(row)?, col: to_index(col)?, cell_type })
+    });
+
+    let shared = world.clone();
+    engine.register_fn("spawn_entity", move |entity: &str, row: i64, col: i64| -> Result<(), Box<EvalAltResult>> {
+        let kind = match EntityKind::from_name(entity) {
+            Some(x) => x,
+            None => return Err(format!("Unknown entity '{}'", entity).into()),
+        };
+
+        push_action(&shared, TriggerAction::Spawn { kind, row: to_index(row)?, col: to_index(col)? })
+    });
+
+    engine
+}
+
+fn with_world<T>(world: &SharedWorld, f: impl FnOnce(&mut ScriptWorld) -> T) -> Result<T, Box<EvalAltResult>> {
+    match world.borrow_mut().as_mut() {
+        Some(world) => Ok(f(world)),
+        None => Err("The game can only be used while handling an event".into()),
+    }
+}
+
+fn push_action(world: &SharedWorld, action: TriggerAction) -> Result<(), Box<EvalAltResult>> {
+    with_world(world, |world| world.commands.push(ScriptCommand::Action(action)))
+}
+
+fn to_index(value: i64) -> Result<usize, Box<EvalAltResult>> {
+    usize::try_from(value).map_err(|_| format!("{} is not a row or column", value).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::process;
+    use std::time::{Duration, SystemTime};
+    use crate::game::{GameState, ActiveInputs, cell_center};
+    use crate::game::map::{Map, CellType};
+
+    const TEST_MAP: &str = "\
+xxxxxxx
+x@  d x
+x     x
+xxxxxxx
+script level.rhai
+";
+
+    const TEST_SCRIPT: &str = r#"
+fn on_enter_cell(row, col) {
+    if row == 1 && col == 2 {
+        open_door(1, 4);
+        set_var("entered", get_var("entered") + 1);
+    }
+
+    if row == 2 && col == 2 {
+        set_timer("alarm", 0.5);
+    }
+
+    if row == 2 && col == 3 {
+        loop { }
+    }
+}
+
+fn on_timer(name) {
+    message(name);
+}
+"#;
+
+    struct TestLevel {
+        directory: std::path::PathBuf,
+        map_path: String,
+        script_path: std::path::PathBuf,
+    }
+
+    impl TestLevel {
+        fn new(name: &str, script: &str) -> Self {
+            let directory = env::temp_dir().join(format!("rustcaster-scripting-{}-{}", name, process::id()));
+            fs::create_dir_all(&directory).unwrap();
+            let map_path = directory.join("level.map").to_str().unwrap().to_owned();
+            fs::write(&map_path, TEST_MAP).unwrap();
+            let level = TestLevel { script_path: directory.join("level.rhai"), directory, map_path };
+            level.write_script(script, SystemTime::UNIX_EPOCH);
+            level
+        }
+
+        /// Writes the script with the modified time it is checked for changes by
+        fn write_script(&self, script: &str, modified: SystemTime) {
+            fs::write(&self.script_path, script).unwrap();
+            File::options().write(true).open(&self.script_path).unwrap().set_modified(modified).unwrap();
+        }
+
+        fn start(&self) -> GameState {
+            GameState::new(Map::load(&self.map_path).unwrap(), 1).unwrap()
+        }
+    }
+
+    impl Drop for TestLevel {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.directory);
+        }
+    }
+
+    fn tick(game_state: &mut GameState, milliseconds: u64) {
+        game_state.tick(&Duration::from_millis(milliseconds), &ActiveInputs::new());
+    }
+
+    fn walk_to(game_state: &mut GameState, row: usize, col: usize) {
+        game_state.player.position = cell_center(&game_state.map, row, col);
+        tick(game_state, 16);
+    }
+
+    #[test]
+    fn entering_a_cell_runs_the_script() {
+        let level = TestLevel::new("enter", TEST_SCRIPT);
+        let mut game_state = level.start();
+        assert_eq!(game_state.map.cell_at(1, 4), Some(CellType::Door { lock: None }));
+
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.map.cell_at(1, 4), Some(CellType::Empty));
+        assert_eq!(game_state.script_vars.get("entered"), Some(&1));
+
+        walk_to(&mut game_state, 1, 1);
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.script_vars.get("entered"), Some(&2));
+    }
+
+    #[test]
+    fn timers_fire_once_they_run_out() {
+        let level = TestLevel::new("timer", TEST_SCRIPT);
+        let mut game_state = level.start();
+        walk_to(&mut game_state, 2, 2);
+        assert_eq!(game_state.script_timers.len(), 1);
+
+        tick(&mut game_state, 400);
+        assert_eq!(game_state.message, None);
+
+        tick(&mut game_state, 100);
+        assert_eq!(game_state.message.as_deref(), Some("alarm"));
+        assert!(game_state.script_timers.is_empty());
+    }
+
+    #[test]
+    fn scripts_stuck_in_a_loop_are_stopped() {
+        let level = TestLevel::new("loop", TEST_SCRIPT);
+        let mut game_state = level.start();
+        walk_to(&mut game_state, 2, 3);
+
+        // The game carries on, and the script still handles later events
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.map.cell_at(1, 4), Some(CellType::Empty));
+    }
+
+    #[test]
+    fn scripts_that_fail_to_compile_are_ignored_until_reloaded() {
+        let level = TestLevel::new("reload", "fn on_enter_cell(row, col) { open_door(1, 4)");
+        let mut game_state = level.start();
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.map.cell_at(1, 4), Some(CellType::Door { lock: None }));
+
+        // Nothing changes until the file does
+        let script = game_state.script.as_mut().unwrap();
+        assert!(!script.reload_if_changed());
+
+        level.write_script(TEST_SCRIPT, SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+        assert!(game_state.script.as_mut().unwrap().reload_if_changed());
+        walk_to(&mut game_state, 1, 1);
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.map.cell_at(1, 4), Some(CellType::Empty));
+    }
+}
@@ -0,0 +1,15 @@
+//! Helpers shared by the line based text formats the game reads and writes
+
+use std::io;
+use std::str::FromStr;
+
+pub fn parse_value<T: FromStr>(value: Option<&str>, name: &str) -> io::Result<T> {
+    match value.map(|x| x.parse::<T>()) {
+        Some(Ok(x)) => Ok(x),
+        _ => Err(invalid_data(format!("Missing or invalid {}", name))),
+    }
+}
+
+pub fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
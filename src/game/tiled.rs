@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde_json::Value;
+use crate::game::map::{Map, CellType, ParsedCell, SpawnType, map_problem, import_error};
+use crate::game::entities::EntityKind;
+use crate::game::text_format::invalid_data;
+
+/// Tile property naming the cell type a tile is imported as
+const CELL_PROPERTY: &str = "cell";
+
+/// Object property naming what an object spawns, when it doesn't have a class or type
+const SPAWN_PROPERTY: &str = "spawn";
+
+/// Map property naming the level's script, relative to the map file
+const SCRIPT_PROPERTY: &str = "script";
+
+/// Map properties naming the wall whose texture is drawn on the floor and ceiling
+const FLOOR_TEXTURE_PROPERTY: &str = "floor_texture";
+const CEILING_TEXTURE_PROPERTY: &str = "ceiling_texture";
+
+/// The layer used for walls when a map has more than one tile layer
+const WALL_LAYER_NAME: &str = "walls";
+
+/// Tiled stores whether a tile is flipped in the top bits of its global tile id
+const TILE_FLIP_FLAGS: u32 = 0xF000_0000;
+
+/// The parts of a Tiled map that are imported, whichever format it was read from
+struct TiledMap {
+    orientation: String,
+    infinite: bool,
+    width: usize,
+    height: usize,
+    tile_width: f32,
+    tile_height: f32,
+    properties: HashMap<String, String>,
+    tilesets: Vec<Tileset>,
+    tile_layers: Vec<TileLayer>,
+    objects: Vec<TiledObject>,
+}
+
+struct Tileset {
+    first_gid: u32,
+
+    /// The `cell` property of each tile that has one, by the tile's id within the tileset
+    cells: HashMap<u32, String>,
+}
+
+struct TileLayer {
+    name: String,
+    tiles: Vec<u32>,
+}
+
+struct TiledObject {
+    id: u32,
+
+    /// What the object spawns, from its class, type or `spawn` property
+    spawn: Option<String>,
+
+    /// Where the object is in pixels, already adjusted to be the top left of its bounds
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// Returns true if the path is a map that has to be imported from Tiled
+pub fn is_tiled_path(path: &str) -> bool {
+    path.ends_with(".tmx") || path.ends_with(".json")
+}
+
+/// Imports a Tiled map saved in either the TMX or JSON format.  Only orthogonal maps are
+/// supported, with tile layers saved as CSV.
+pub fn load_tiled_map(path: &str) -> io::Result<Map> {
+    let contents = fs::read_to_string(path)?;
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let tiled_map = if path.ends_with(".tmx") {
+        parse_tmx(&contents, directory)
+    } else {
+        parse_json(&contents, directory)
+    };
+
+    tiled_map.and_then(|x| import(path, x)).map_err(|err| import_error(path, err))
+}
+
+fn import(id: &str, tiled_map: TiledMap) -> io::Result<Map> {
+    if tiled_map.orientation != "orthogonal" {
+        return Err(invalid_data(format!("Only orthogonal maps are supported, but this map is {}", tiled_map.orientation)));
+    }
+
+    if tiled_map.infinite {
+        return Err(invalid_data("Infinite maps are not supported, the map needs a fixed size".to_owned()));
+    }
+
+    let layer = match tiled_map.tile_layers.len() {
+        0 => return Err(invalid_data("The map has no tile layer for its walls".to_owned())),
+        1 => &tiled_map.tile_layers[0],
+        _ => match tiled_map.tile_layers.iter().find(|x| x.name.eq_ignore_ascii_case(WALL_LAYER_NAME)) {
+            Some(x) => x,
+            None => return Err(invalid_data(format!("The map has several tile layers, so the walls need to be in one named '{}'", WALL_LAYER_NAME))),
+        },
+    };
+
+    if layer.tiles.len() != tiled_map.width * tiled_map.height {
+        return Err(invalid_data(format!("Layer '{}' has {} tiles but the map is {}x{}",
+                                        layer.name, layer.tiles.len(), tiled_map.width, tiled_map.height)));
+    }
+
+    let mut map = Map::with_size(id, tiled_map.width, tiled_map.height);
+    for (index, gid) in layer.tiles.iter().enumerate() {
+        let gid = gid & !TILE_FLIP_FLAGS;
+        if gid == 0 {
+            continue;
+        }
+
+        let cell_type = tile_cell_type(&tiled_map.tilesets, gid)?;
+        map.set_cell(index / tiled_map.width, index % tiled_map.width, cell_type);
+    }
+
+    for object in &tiled_map.objects {
+        let name = match &object.spawn {
+            Some(x) => x,
+            None => return Err(invalid_data(format!("Object {} has no class, type or '{}' property saying what it spawns", object.id, SPAWN_PROPERTY))),
+        };
+
+        let spawn = match name.as_str() {
+            "player" => SpawnType::Player,
+            x => match EntityKind::from_name(x) {
+                Some(kind) => SpawnType::Entity(kind),
+                None => return Err(invalid_data(format!("Object {} spawns unknown entity '{}'", object.id, x))),
+            },
+        };
+
+        // Objects are placed in the cell their center is in
+        let col = ((object.x + object.width / 2.0) / tiled_map.tile_width).floor();
+        let row = ((object.y + object.height / 2.0) / tiled_map.tile_height).floor();
+        if col < 0.0 || row < 0.0 || col as usize >= map.width || row as usize >= map.height {
+            return Err(invalid_data(format!("Object {} is outside of the map", object.id)));
+        }
+
+        if map.cell_at(row as usize, col as usize) != Some(CellType::Empty) {
+            return Err(map_problem(format!("Object {} is inside a wall at row {}, column {}", object.id, row, col)));
+        }
+
+        map.set_contents(row as usize, col as usize, ParsedCell::Spawn(spawn));
+    }
+
+    if !map.spawns.iter().any(|x| x.entity == SpawnType::Player) {
+        return Err(map_problem("The map has no object spawning the player".to_owned()));
+    }
+
+    map.floor_texture = texture_property(&tiled_map.properties, FLOOR_TEXTURE_PROPERTY)?;
+    map.ceiling_texture = texture_property(&tiled_map.properties, CEILING_TEXTURE_PROPERTY)?;
+    map.script = tiled_map.properties.get(SCRIPT_PROPERTY).cloned();
+    Ok(map)
+}
+
+fn tile_cell_type(tilesets: &[Tileset], gid: u32) -> io::Result<CellType> {
+    // Each tileset's ids start at its first gid and run up to the next tileset's
+    let tileset = tilesets.iter()
+        .filter(|x| x.first_gid <= gid)
+        .max_by_key(|x| x.first_gid);
+
+    let tileset = match tileset {
+        Some(x) => x,
+        None => return Err(invalid_data(format!("Tile {} is not in any tileset", gid))),
+    };
+
+    let name = match tileset.cells.get(&(gid - tileset.first_gid)) {
+        Some(x) => x,
+        None => return Err(invalid_data(format!("Tile {} has no '{}' property saying what type of cell it is", gid, CELL_PROPERTY))),
+    };
+
+    match CellType::from_name(name) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Tile {} has unknown cell type '{}'", gid, name))),
+    }
+}
+
+/// Floors and ceilings are drawn with the texture of the wall named by the property
+fn texture_property(properties: &HashMap<String, String>, name: &str) -> io::Result<Option<CellType>> {
+    let value = match properties.get(name) {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+
+    match CellType::from_name(value) {
+        Some(CellType::Empty) | None => Err(invalid_data(format!("Property '{}' has to name a type of wall, not '{}'", name, value))),
+        Some(x) => Ok(Some(x)),
+    }
+}
+
+fn parse_tmx(contents: &str, directory: &Path) -> io::Result<TiledMap> {
+    let document = roxmltree::Document::parse(contents).map_err(|err| invalid_data(format!("Invalid TMX: {}", err)))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "map" {
+        return Err(invalid_data("TMX files need a map element at the root".to_owned()));
+    }
+
+    let mut tiled_map = TiledMap {
+        orientation: xml_attribute::<String>(root, "orientation")?,
+        infinite: root.attribute("infinite") == Some("1"),
+        width: xml_attribute(root, "width")?,
+        height: xml_attribute(root, "height")?,
+        tile_width: xml_attribute(root, "tilewidth")?,
+        tile_height: xml_attribute(root, "tileheight")?,
+        properties: xml_properties(root),
+        tilesets: Vec::new(),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    // Layers can be nested inside groups, so every descendant is checked
+    for node in root.descendants().filter(|x| x.is_element()) {
+        match node.tag_name().name() {
+            "tileset" if node.parent() == Some(root) => {
+                let first_gid = xml_attribute(node, "firstgid")?;
+                let cells = match node.attribute("source") {
+                    Some(source) => {
+                        let path = directory.join(source);
+                        let tileset_contents = fs::read_to_string(&path)?;
+                        if source.ends_with(".tsx") {
+                            let tileset = roxmltree::Document::parse(&tileset_contents)
+                                .map_err(|err| invalid_data(format!("Invalid tileset '{}': {}", path.display(), err)))?;
+
+                            xml_tileset_cells(tileset.root_element())?
+                        } else {
+                            json_tileset_cells(&parse_json_value(&tileset_contents)?)?
+                        }
+                    }
+
+                    None => xml_tileset_cells(node)?,
+                };
+
+                tiled_map.tilesets.push(Tileset { first_gid, cells });
+            }
+
+            "layer" => {
+                let data = match node.children().find(|x| x.has_tag_name("data")) {
+                    Some(x) => x,
+                    None => return Err(invalid_data("Tile layer has no data".to_owned())),
+                };
+
+                if data.attribute("encoding") != Some("csv") {
+                    return Err(invalid_data("Tile layers have to be saved as CSV, which can be set in the map's properties".to_owned()));
+                }
+
+                let tiles = data.text().unwrap_or("")
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.parse().map_err(|_| invalid_data(format!("Invalid tile '{}'", x))))
+                    .collect::<io::Result<Vec<u32>>>()?;
+
+                tiled_map.tile_layers.push(TileLayer {
+                    name: node.attribute("name").unwrap_or("").to_owned(),
+                    tiles,
+                });
+            }
+
+            // Tiles can have objects of their own for collision shapes, which aren't spawns
+            "object" if is_layer_object(node) => {
+                let properties = xml_properties(node);
+                let spawn = node.attribute("class")
+                    .or_else(|| node.attribute("type"))
+                    .filter(|x| !x.is_empty())
+                    .map(str::to_owned)
+                    .or_else(|| properties.get(SPAWN_PROPERTY).cloned());
+
+                let width = node.attribute("width").and_then(|x| x.parse().ok()).unwrap_or(0.0);
+                let height = node.attribute("height").and_then(|x| x.parse().ok()).unwrap_or(0.0);
+                let y: f32 = xml_attribute(node, "y")?;
+
+                tiled_map.objects.push(TiledObject {
+                    id: xml_attribute(node, "id")?,
+                    spawn,
+                    x: xml_attribute(node, "x")?,
+
+                    // Tile objects are positioned by their bottom left corner
+                    y: if node.has_attribute("gid") { y - height } else { y },
+                    width,
+                    height,
+                });
+            }
+
+            "imagelayer" => return Err(invalid_data("Image layers are not supported".to_owned())),
+            _ => (),
+        }
+    }
+
+    Ok(tiled_map)
+}
+
+fn is_layer_object(object: roxmltree::Node) -> bool {
+    let layer_parent = object.parent().and_then(|x| x.parent());
+    matches!(layer_parent.map(|x| x.tag_name().name()), Some("map") | Some("group"))
+}
+
+fn xml_tileset_cells(tileset: roxmltree::Node) -> io::Result<HashMap<u32, String>> {
+    let mut cells = HashMap::new();
+    for tile in tileset.children().filter(|x| x.has_tag_name("tile")) {
+        if let Some(cell) = xml_properties(tile).remove(CELL_PROPERTY) {
+            cells.insert(xml_attribute(tile, "id")?, cell);
+        }
+    }
+
+    Ok(cells)
+}
+
+fn xml_properties(node: roxmltree::Node) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    let property_nodes = node.children()
+        .filter(|x| x.has_tag_name("properties"))
+        .flat_map(|x| x.children())
+        .filter(|x| x.has_tag_name("property"));
+
+    for property in property_nodes {
+        if let Some(name) = property.attribute("name") {
+            // Multi-line string values are stored as text rather than in the value attribute
+            let value = property.attribute("value").or_else(|| property.text()).unwrap_or("");
+            properties.insert(name.to_owned(), value.to_owned());
+        }
+    }
+
+    properties
+}
+
+fn xml_attribute<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> io::Result<T> {
+    node.attribute(name)
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| invalid_data(format!("Missing or invalid '{}' attribute on {}", name, node.tag_name().name())))
+}
+
+fn parse_json(contents: &str, directory: &Path) -> io::Result<TiledMap> {
+    let root = parse_json_value(contents)?;
+    let mut tiled_map = TiledMap {
+        orientation: json_string(&root, "orientation")?,
+        infinite: root["infinite"].as_bool().unwrap_or(false),
+        width: json_number(&root, "width")? as usize,
+        height: json_number(&root, "height")? as usize,
+        tile_width: json_number(&root, "tilewidth")? as f32,
+        tile_height: json_number(&root, "tileheight")? as f32,
+        properties: json_properties(&root),
+        tilesets: Vec::new(),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    for tileset in root["tilesets"].as_array().into_iter().flatten() {
+        let first_gid = json_number(tileset, "firstgid")? as u32;
+        let cells = match tileset["source"].as_str() {
+            Some(source) => {
+                let path = directory.join(source);
+                if source.ends_with(".tsx") {
+                    let contents = fs::read_to_string(&path)?;
+                    let document = roxmltree::Document::parse(&contents)
+                        .map_err(|err| invalid_data(format!("Invalid tileset '{}': {}", path.display(), err)))?;
+
+                    xml_tileset_cells(document.root_element())?
+                } else {
+                    json_tileset_cells(&parse_json_value(&fs::read_to_string(&path)?)?)?
+                }
+            }
+
+            None => json_tileset_cells(tileset)?,
+        };
+
+        tiled_map.tilesets.push(Tileset { first_gid, cells });
+    }
+
+    add_json_layers(&mut tiled_map, &root["layers"])?;
+    Ok(tiled_map)
+}
+
+fn add_json_layers(tiled_map: &mut TiledMap, layers: &Value) -> io::Result<()> {
+    for layer in layers.as_array().into_iter().flatten() {
+        match layer["type"].as_str() {
+            Some("tilelayer") => {
+                let tiles = match layer["data"].as_array() {
+                    Some(data) => data.iter()
+                        .map(|x| x.as_u64().map(|x| x as u32).ok_or_else(|| invalid_data(format!("Invalid tile '{}'", x))))
+                        .collect::<io::Result<Vec<_>>>()?,
+
+                    None => return Err(invalid_data("Tile layers have to be saved as CSV, which can be set in the map's properties".to_owned())),
+                };
+
+                tiled_map.tile_layers.push(TileLayer {
+                    name: layer["name"].as_str().unwrap_or("").to_owned(),
+                    tiles,
+                });
+            }
+
+            Some("objectgroup") => {
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    let spawn = object["class"].as_str()
+                        .or_else(|| object["type"].as_str())
+                        .filter(|x| !x.is_empty())
+                        .map(str::to_owned)
+                        .or_else(|| json_properties(object).remove(SPAWN_PROPERTY));
+
+                    let width = object["width"].as_f64().unwrap_or(0.0) as f32;
+                    let height = object["height"].as_f64().unwrap_or(0.0) as f32;
+                    let y = json_number(object, "y")? as f32;
+
+                    tiled_map.objects.push(TiledObject {
+                        id: json_number(object, "id")? as u32,
+                        spawn,
+                        x: json_number(object, "x")? as f32,
+
+                        // Tile objects are positioned by their bottom left corner
+                        y: if object.get("gid").is_some() { y - height } else { y },
+                        width,
+                        height,
+                    });
+                }
+            }
+
+            Some("group") => add_json_layers(tiled_map, &layer["layers"])?,
+            Some("imagelayer") => return Err(invalid_data("Image layers are not supported".to_owned())),
+            _ => return Err(invalid_data(format!("Unknown layer type '{}'", layer["type"]))),
+        }
+    }
+
+    Ok(())
+}
+
+fn json_tileset_cells(tileset: &Value) -> io::Result<HashMap<u32, String>> {
+    let mut cells = HashMap::new();
+    for tile in tileset["tiles"].as_array().into_iter().flatten() {
+        if let Some(cell) = json_properties(tile).remove(CELL_PROPERTY) {
+            cells.insert(json_number(tile, "id")? as u32, cell);
+        }
+    }
+
+    Ok(cells)
+}
+
+fn json_properties(value: &Value) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    for property in value["properties"].as_array().into_iter().flatten() {
+        if let Some(name) = property["name"].as_str() {
+            let value = match &property["value"] {
+                Value::String(x) => x.clone(),
+                x => x.to_string(),
+            };
+
+            properties.insert(name.to_owned(), value);
+        }
+    }
+
+    properties
+}
+
+fn parse_json_value(contents: &str) -> io::Result<Value> {
+    serde_json::from_str(contents).map_err(|err| invalid_data(format!("Invalid JSON: {}", err)))
+}
+
+fn json_string(value: &Value, name: &str) -> io::Result<String> {
+    match value[name].as_str() {
+        Some(x) => Ok(x.to_owned()),
+        None => Err(invalid_data(format!("Missing or invalid '{}'", name))),
+    }
+}
+
+fn json_number(value: &Value, name: &str) -> io::Result<f64> {
+    match value[name].as_f64() {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Missing or invalid '{}'", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+    use crate::game::map::is_map_problem;
+
+    /// A room with a door and a blue wall inside, where the decoration layer uses a tile with no
+    /// `cell` property that is never imported
+    fn tmx(walls: &str) -> String {
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="5" height="4" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+  <property name="floor_texture" value="wood_wall"/>
+  <property name="ceiling_texture" value="blue_wall"/>
+ </properties>
+ <tileset firstgid="1" name="cells" tilewidth="16" tileheight="16" tilecount="4" columns="4">
+  <tile id="0"><properties><property name="cell" value="brick_wall"/></properties></tile>
+  <tile id="1"><properties><property name="cell" value="door"/></properties></tile>
+  <tile id="2"><properties><property name="cell" value="blue_wall"/></properties></tile>
+ </tileset>
+ <layer id="1" name="walls" width="5" height="4">
+  <data encoding="csv">{}</data>
+ </layer>
+ <layer id="2" name="decoration" width="5" height="4">
+  <data encoding="csv">0,0,0,0,0,0,4,0,0,0,0,0,0,0,0,0,0,0,0,0</data>
+ </layer>
+ <objectgroup id="3" name="spawns">
+  <object id="1" class="player" x="16" y="16" width="16" height="16"/>
+  <object id="2" type="guard" x="52" y="20"/>
+  <object id="3" x="20" y="36"><properties><property name="spawn" value="health_pack"/></properties></object>
+  <object id="4" class="barrel" gid="1" x="48" y="48" width="16" height="16"/>
+ </objectgroup>
+</map>"#, walls)
+    }
+
+    const WALLS: &str = "
+1,1,1,1,1,
+1,0,2,0,1,
+1,0,0,0,3,
+1,1,1,1,1";
+
+    const JSON: &str = r#"{
+        "orientation": "orthogonal", "infinite": false, "width": 5, "height": 4, "tilewidth": 16, "tileheight": 16,
+        "properties": [
+            {"name": "floor_texture", "type": "string", "value": "wood_wall"},
+            {"name": "ceiling_texture", "type": "string", "value": "blue_wall"}
+        ],
+        "tilesets": [{"firstgid": 1, "tiles": [
+            {"id": 0, "properties": [{"name": "cell", "type": "string", "value": "brick_wall"}]},
+            {"id": 1, "properties": [{"name": "cell", "type": "string", "value": "door"}]},
+            {"id": 2, "properties": [{"name": "cell", "type": "string", "value": "blue_wall"}]}
+        ]}],
+        "layers": [
+            {"type": "tilelayer", "name": "walls", "data": [1,1,1,1,1, 1,0,2,0,1, 1,0,0,0,3, 1,1,1,1,1]},
+            {"type": "group", "layers": [{"type": "objectgroup", "objects": [
+                {"id": 1, "class": "player", "x": 16, "y": 16, "width": 16, "height": 16},
+                {"id": 2, "type": "guard", "x": 52, "y": 20},
+                {"id": 3, "x": 20, "y": 36, "properties": [{"name": "spawn", "type": "string", "value": "health_pack"}]},
+                {"id": 4, "class": "barrel", "gid": 1, "x": 48, "y": 48, "width": 16, "height": 16}
+            ]}]}
+        ]
+    }"#;
+
+    fn import_tmx(contents: &str) -> io::Result<Map> {
+        parse_tmx(contents, Path::new("")).and_then(|x| import("test.tmx", x))
+    }
+
+    fn import_json(contents: &str) -> io::Result<Map> {
+        parse_json(contents, Path::new("")).and_then(|x| import("test.json", x))
+    }
+
+    fn assert_error(result: io::Result<Map>, expected: &str) {
+        match result {
+            Ok(_) => panic!("Expected an error containing '{}'", expected),
+            Err(err) => assert!(err.to_string().contains(expected), "'{}' doesn't contain '{}'", err, expected),
+        }
+    }
+
+    fn assert_imported(map: &Map) {
+        assert_eq!((map.width, map.height), (5, 4));
+        assert_eq!(map.cell_at(0, 0), Some(CellType::BrickWall));
+        assert_eq!(map.cell_at(1, 1), Some(CellType::Empty));
+        assert_eq!(map.cell_at(1, 2), Some(CellType::Door { lock: None }));
+        assert_eq!(map.cell_at(2, 4), Some(CellType::BlueWall));
+        assert_eq!(map.cell_at(3, 4), Some(CellType::BrickWall));
+
+        let spawn_at = |row, col| map.spawns.iter().find(|x| x.row == row && x.col == col).map(|x| x.entity);
+        assert!(spawn_at(1, 1) == Some(SpawnType::Player));
+        assert!(spawn_at(1, 3) == Some(SpawnType::Entity(EntityKind::Guard)));
+        assert!(spawn_at(2, 1) == Some(SpawnType::Entity(EntityKind::HealthPack)));
+        assert!(spawn_at(2, 3) == Some(SpawnType::Entity(EntityKind::Barrel)));
+        assert_eq!(map.spawns.len(), 4);
+
+        assert_eq!(map.floor_texture, Some(CellType::WoodWall));
+        assert_eq!(map.ceiling_texture, Some(CellType::BlueWall));
+    }
+
+    #[test]
+    fn imports_tmx() {
+        assert_imported(&import_tmx(&tmx(WALLS)).unwrap());
+    }
+
+    #[test]
+    fn imports_json() {
+        assert_imported(&import_json(JSON).unwrap());
+    }
+
+    #[test]
+    fn flipped_tiles_keep_their_cell_type() {
+        let flipped = WALLS.replacen('3', &(3 | 0x8000_0000u32).to_string(), 1);
+        assert_eq!(import_tmx(&tmx(&flipped)).unwrap().cell_at(2, 4), Some(CellType::BlueWall));
+    }
+
+    #[test]
+    fn rejects_isometric_maps() {
+        assert_error(import_tmx(&tmx(WALLS).replace("orthogonal", "isometric")), "Only orthogonal maps");
+        assert_error(import_json(&JSON.replace("orthogonal", "isometric")), "Only orthogonal maps");
+    }
+
+    #[test]
+    fn rejects_layers_not_saved_as_csv() {
+        assert_error(import_tmx(&tmx(WALLS).replace("encoding=\"csv\"", "encoding=\"base64\"")), "saved as CSV");
+        let base64 = JSON.replace("\"data\": [1,1,1,1,1, 1,0,2,0,1, 1,0,0,0,3, 1,1,1,1,1]",
+                                  "\"encoding\": \"base64\", \"data\": \"AQAAAA==\"");
+        assert_error(import_json(&base64), "saved as CSV");
+    }
+
+    #[test]
+    fn rejects_several_tile_layers_without_walls() {
+        assert_error(import_tmx(&tmx(WALLS).replace("name=\"walls\"", "name=\"Tile Layer 1\"")), "several tile layers");
+    }
+
+    #[test]
+    fn rejects_tiles_without_a_cell_property() {
+        assert_error(import_tmx(&tmx(&WALLS.replacen('0', "4", 1))), "Tile 4 has no 'cell' property");
+    }
+
+    #[test]
+    fn rejects_objects_inside_walls() {
+        let result = import_tmx(&tmx(WALLS).replace("x=\"52\" y=\"20\"", "x=\"36\" y=\"20\""));
+        assert!(result.as_ref().is_err_and(is_map_problem));
+        assert_error(result, "Object 2 is inside a wall at row 1, column 2");
+    }
+
+    #[test]
+    fn rejects_maps_without_a_player() {
+        assert_error(import_tmx(&tmx(WALLS).replace("class=\"player\"", "class=\"rat\"")), "no object spawning the player");
+        assert_error(import_json(&JSON.replace("\"class\": \"player\"", "\"class\": \"rat\"")), "no object spawning the player");
+    }
+
+    #[test]
+    fn problems_keep_their_marker_when_loaded_from_a_file() {
+        let path = env::temp_dir().join(format!("rustcaster-tiled-{}.tmx", process::id()));
+        fs::write(&path, tmx(WALLS).replace("class=\"player\"", "class=\"rat\"")).unwrap();
+        let result = load_tiled_map(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.as_ref().is_err_and(is_map_problem));
+        assert_error(result, "no object spawning the player");
+    }
+}
@@ -0,0 +1,362 @@
+use std::io;
+use crate::game::map::CellType;
+use crate::game::entities::EntityKind;
+use crate::game::text_format::{parse_value, invalid_data};
+
+/// How long a message shown by a trigger stays on screen
+pub const MESSAGE_SECONDS: f32 = 4.0;
+
+/// The brightest light level, which leaves the view untouched
+pub const MAX_LIGHT_LEVEL: u32 = 100;
+
+/// What the player has to do to a trigger's cell to set it off
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Activation {
+    /// Entering the cell
+    Walk,
+
+    /// Pressing use while facing the cell, like opening a door
+    Use,
+
+    /// Hitting the cell's wall with a shot or a projectile
+    Shoot,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TriggerAction {
+    /// Opens the door in the cell, even if it is locked
+    OpenDoor { row: usize, col: usize },
+
+    /// Puts the wall in the cell if it is empty, or clears the cell if it isn't
+    ToggleWall { row: usize, col: usize, cell_type: CellType },
+    Spawn { kind: EntityKind, row: usize, col: usize },
+
+    /// Darkens the view to the percentage of full brightness
+    Light(u32),
+    Message(String),
+}
+
+/// An action that happens when the player does something to a cell.  Triggers are declared in
+/// map files after the cells, one per line, such as `trigger use 3 4 once open_door 3 6`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trigger {
+    pub activation: Activation,
+    pub row: usize,
+    pub col: usize,
+
+    /// Whether the trigger only goes off the first time, rather than every time it is activated
+    pub once: bool,
+    pub action: TriggerAction,
+}
+
+impl Activation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activation::Walk => "walk",
+            Activation::Use => "use",
+            Activation::Shoot => "shoot",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "walk" => Some(Activation::Walk),
+            "use" => Some(Activation::Use),
+            "shoot" => Some(Activation::Shoot),
+            _ => None,
+        }
+    }
+}
+
+impl Trigger {
+    /// Parses a `trigger` line from a map file
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        if parts.first() != Some(&"trigger") {
+            return Err(invalid_data(format!("'{}' is not a trigger", line)));
+        }
+
+        let activation = match parts.get(1).and_then(|x| Activation::from_name(x)) {
+            Some(x) => x,
+            None => return Err(invalid_data(format!("Unknown trigger activation '{}', expected walk, use or shoot",
+                                                    parts.get(1).unwrap_or(&"")))),
+        };
+
+        let row = parse_value(parts.get(2).copied(), "trigger row")?;
+        let col = parse_value(parts.get(3).copied(), "trigger column")?;
+        let once = parts.get(4) == Some(&"once");
+        let action_parts = if once { &parts[5..] } else { &parts[4..] };
+        let action = parse_action(action_parts)?;
+
+        Ok(Trigger { activation, row, col, once, action })
+    }
+
+    /// Writes the trigger back out in the same form it is parsed from
+    pub fn to_line(&self) -> String {
+        let once = if self.once { " once" } else { "" };
+        let action = match &self.action {
+            TriggerAction::OpenDoor { row, col } => format!("open_door {} {}", row, col),
+            TriggerAction::ToggleWall { row, col, cell_type } => format!("toggle_wall {} {} {}", row, col, cell_type.name()),
+            TriggerAction::Spawn { kind, row, col } => format!("spawn {} {} {}", kind.name(), row, col),
+            TriggerAction::Light(level) => format!("light {}", level),
+            TriggerAction::Message(text) => format!("message {}", text),
+        };
+
+        format!("trigger {} {} {}{} {}", self.activation.name(), self.row, self.col, once, action)
+    }
+}
+
+fn parse_action(parts: &[&str]) -> io::Result<TriggerAction> {
+    let value = |index: usize| parts.get(index).copied();
+    match value(0) {
+        Some("open_door") => Ok(TriggerAction::OpenDoor {
+            row: parse_value(value(1), "door row")?,
+            col: parse_value(value(2), "door column")?,
+        }),
+
+        Some("toggle_wall") => {
+            let row = parse_value(value(1), "wall row")?;
+            let col = parse_value(value(2), "wall column")?;
+            let cell_type = match value(3).and_then(CellType::from_name) {
+                Some(CellType::Empty) | None => {
+                    return Err(invalid_data(format!("Unknown wall '{}'", value(3).unwrap_or(""))));
+                }
+
+                Some(x) => x,
+            };
+
+            Ok(TriggerAction::ToggleWall { row, col, cell_type })
+        }
+
+        Some("spawn") => {
+            let kind = match value(1).and_then(EntityKind::from_name) {
+                Some(x) => x,
+                None => return Err(invalid_data(format!("Unknown entity '{}'", value(1).unwrap_or("")))),
+            };
+
+            Ok(TriggerAction::Spawn {
+                kind,
+                row: parse_value(value(2), "spawn row")?,
+                col: parse_value(value(3), "spawn column")?,
+            })
+        }
+
+        Some("light") => match parse_value(value(1), "light level")? {
+            x if x <= MAX_LIGHT_LEVEL => Ok(TriggerAction::Light(x)),
+            x => Err(invalid_data(format!("Light level {} is above the maximum of {}", x, MAX_LIGHT_LEVEL))),
+        },
+
+        Some("message") if parts.len() > 1 => Ok(TriggerAction::Message(parts[1..].join(" "))),
+        Some("message") => Err(invalid_data("Message triggers need some text to show".to_owned())),
+        x => Err(invalid_data(format!("Unknown trigger action '{}'", x.unwrap_or("")))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::time::Duration;
+    use crate::core::radians::Radians;
+    use crate::game::{GameState, ActiveInputs, cell_center};
+    use crate::game::map::Map;
+    use crate::game::save::{save_game, load_game};
+    use super::*;
+
+    const TEST_MAP: &str = "\
+xxxxxxxx
+x@    bx
+x      x
+xxxxxxxx
+trigger walk 1 2 message Walked in
+trigger walk 1 3 once light 40
+trigger use 1 6 toggle_wall 2 4 wood_wall
+trigger use 1 6 toggle_wall 1 5 wood_wall
+trigger shoot 1 6 spawn barrel 2 2
+";
+
+    struct TestLevel {
+        directory: std::path::PathBuf,
+        map_path: String,
+    }
+
+    impl TestLevel {
+        fn new(name: &str) -> Self {
+            let directory = env::temp_dir().join(format!("rustcaster-triggers-{}-{}", name, process::id()));
+            fs::create_dir_all(&directory).unwrap();
+            let map_path = directory.join("triggers.map").to_str().unwrap().to_owned();
+            fs::write(&map_path, TEST_MAP).unwrap();
+            TestLevel { directory, map_path }
+        }
+
+        fn start(&self) -> GameState {
+            let mut game_state = GameState::new(Map::load(&self.map_path).unwrap(), 1).unwrap();
+            game_state.player.facing = Radians(0.0);
+            game_state
+        }
+    }
+
+    impl Drop for TestLevel {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.directory);
+        }
+    }
+
+    fn tick(game_state: &mut GameState, inputs: &ActiveInputs) {
+        game_state.tick(&Duration::from_millis(16), inputs);
+    }
+
+    /// Puts the player in the middle of the cell, facing right, and lets a tick pass
+    fn walk_to(game_state: &mut GameState, row: usize, col: usize) {
+        game_state.player.position = cell_center(&game_state.map, row, col);
+        tick(game_state, &ActiveInputs::new());
+    }
+
+    #[test]
+    fn walk_triggers_fire_on_entering_the_cell() {
+        let level = TestLevel::new("walk");
+        let mut game_state = level.start();
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.message.as_deref(), Some("Walked in"));
+
+        // Standing still in the cell doesn't set it off again
+        game_state.message = None;
+        tick(&mut game_state, &ActiveInputs::new());
+        assert_eq!(game_state.message, None);
+
+        // Triggers without `once` go off every time the cell is entered
+        walk_to(&mut game_state, 1, 1);
+        walk_to(&mut game_state, 1, 2);
+        assert_eq!(game_state.message.as_deref(), Some("Walked in"));
+    }
+
+    #[test]
+    fn once_triggers_only_fire_the_first_time() {
+        let level = TestLevel::new("once");
+        let mut game_state = level.start();
+        walk_to(&mut game_state, 1, 3);
+        assert_eq!(game_state.light_level, 40);
+        assert_eq!(game_state.spent_triggers, vec![1]);
+
+        game_state.light_level = MAX_LIGHT_LEVEL;
+        walk_to(&mut game_state, 1, 2);
+        walk_to(&mut game_state, 1, 3);
+        assert_eq!(game_state.light_level, MAX_LIGHT_LEVEL);
+    }
+
+    #[test]
+    fn spent_triggers_stay_spent_after_loading_a_save() {
+        let level = TestLevel::new("save");
+        let mut game_state = level.start();
+        walk_to(&mut game_state, 1, 3);
+        walk_to(&mut game_state, 1, 2);
+
+        let save_path = level.directory.join("triggers.sav").to_str().unwrap().to_owned();
+        save_game(&game_state, &save_path).unwrap();
+        let mut loaded = load_game(&save_path).unwrap();
+        assert_eq!(loaded.spent_triggers, vec![1]);
+
+        loaded.light_level = MAX_LIGHT_LEVEL;
+        walk_to(&mut loaded, 1, 3);
+        assert_eq!(loaded.light_level, MAX_LIGHT_LEVEL);
+    }
+
+    #[test]
+    fn use_triggers_fire_when_facing_the_cell() {
+        let level = TestLevel::new("use");
+        let mut game_state = level.start();
+        let mut inputs = ActiveInputs::new();
+        inputs.use_action = true;
+
+        // Too far away to reach the wall
+        walk_to(&mut game_state, 1, 4);
+        tick(&mut game_state, &inputs);
+        assert_eq!(game_state.map.cell_at(2, 4), Some(CellType::Empty));
+
+        walk_to(&mut game_state, 1, 5);
+        tick(&mut game_state, &inputs);
+        assert_eq!(game_state.map.cell_at(2, 4), Some(CellType::WoodWall));
+
+        // Toggling again takes the wall away
+        tick(&mut game_state, &inputs);
+        assert_eq!(game_state.map.cell_at(2, 4), Some(CellType::Empty));
+    }
+
+    #[test]
+    fn toggled_walls_never_close_on_the_player() {
+        let level = TestLevel::new("toggle");
+        let mut game_state = level.start();
+        let mut inputs = ActiveInputs::new();
+        inputs.use_action = true;
+
+        walk_to(&mut game_state, 1, 5);
+        tick(&mut game_state, &inputs);
+        assert_eq!(game_state.map.cell_at(1, 5), Some(CellType::Empty));
+        assert_eq!(game_state.map.cell_at(2, 4), Some(CellType::WoodWall));
+    }
+
+    #[test]
+    fn shoot_triggers_fire_when_a_shot_hits_the_wall() {
+        let level = TestLevel::new("shoot");
+        let mut game_state = level.start();
+        let entities = game_state.entities.iter().count();
+        let mut inputs = ActiveInputs::new();
+        inputs.fire = true;
+
+        tick(&mut game_state, &inputs);
+        let barrels = game_state.entities.iter().filter(|x| x.kind == EntityKind::Barrel).collect::<Vec<_>>();
+        assert_eq!(game_state.entities.iter().count(), entities + 1);
+        assert_eq!(barrels.len(), 1);
+        let expected = cell_center(&game_state.map, 2, 2);
+        assert!(barrels[0].position.x == expected.x && barrels[0].position.y == expected.y);
+    }
+
+    #[test]
+    fn solid_scenery_stops_shots_before_the_wall() {
+        let level = TestLevel::new("scenery");
+        let mut game_state = level.start();
+        let position = cell_center(&game_state.map, 1, 4);
+        let barrel = game_state.entities.spawn(EntityKind::Barrel, position);
+        let mut inputs = ActiveInputs::new();
+        inputs.fire = true;
+
+        tick(&mut game_state, &inputs);
+        assert_eq!(game_state.entities.iter().filter(|x| x.kind == EntityKind::Barrel).count(), 1);
+        assert_eq!(game_state.entities.get(barrel).unwrap().health, 0);
+    }
+
+    #[test]
+    fn parsed_triggers_write_back_the_same_line() {
+        let lines = [
+            "trigger walk 1 2 message Something moved in the dark",
+            "trigger use 3 4 once open_door 3 6",
+            "trigger shoot 0 7 toggle_wall 2 9 blue_wall",
+            "trigger walk 5 5 once spawn patrol_guard 6 6",
+            "trigger use 2 2 light 40",
+        ];
+
+        for line in &lines {
+            let trigger = Trigger::parse(line).unwrap();
+            assert_eq!(&trigger.to_line(), line);
+            assert_eq!(Trigger::parse(&trigger.to_line()).unwrap(), trigger);
+        }
+    }
+
+    #[test]
+    fn invalid_triggers_are_errors() {
+        let lines = [
+            "trigger stand 1 2 light 40",
+            "trigger walk x 2 light 40",
+            "trigger walk 1 2 light 101",
+            "trigger walk 1 2 toggle_wall 1 2 empty",
+            "trigger walk 1 2 spawn dragon 1 2",
+            "trigger walk 1 2 message",
+            "trigger walk 1 2 dance",
+        ];
+
+        for line in &lines {
+            assert!(Trigger::parse(line).is_err(), "'{}' should not parse", line);
+        }
+    }
+}
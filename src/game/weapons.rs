@@ -0,0 +1,106 @@
+use crate::core::radians::Radians;
+use crate::core::vector::Vector;
+use crate::game::entities::{EntityStore, EntityId, EntityKind};
+use crate::game::map::Map;
+use crate::game::raycast::{cast_ray, ray_hits_circle};
+
+/// How long the firing frames of the weapon are shown after a shot
+pub const FIRE_ANIMATION_SECONDS: f32 = 0.2;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WeaponKind { Knife, Pistol, MachineGun, RocketLauncher }
+
+pub struct WeaponStats {
+    /// Damage dealt is somewhere between this and double this amount
+    pub base_damage: i32,
+    pub range: f32,
+    pub seconds_between_shots: f32,
+    pub uses_ammo: bool,
+
+    /// Automatic weapons keep firing while the trigger is held down
+    pub automatic: bool,
+
+    /// Weapons that launch a projectile instead of firing an instant shot
+    pub projectile: Option<EntityKind>,
+}
+
+impl WeaponKind {
+    pub fn stats(&self) -> WeaponStats {
+        match self {
+            WeaponKind::Knife => WeaponStats {
+                base_damage: 8,
+                range: 4.0,
+                seconds_between_shots: 0.4,
+                uses_ammo: false,
+                automatic: false,
+                projectile: None,
+            },
+
+            WeaponKind::Pistol => WeaponStats {
+                base_damage: 6,
+                range: 200.0,
+                seconds_between_shots: 0.3,
+                uses_ammo: true,
+                automatic: false,
+                projectile: None,
+            },
+
+            WeaponKind::MachineGun => WeaponStats {
+                base_damage: 6,
+                range: 200.0,
+                seconds_between_shots: 0.12,
+                uses_ammo: true,
+                automatic: true,
+                projectile: None,
+            },
+
+            WeaponKind::RocketLauncher => WeaponStats {
+                base_damage: 0,
+                range: 0.0,
+                seconds_between_shots: 0.8,
+                uses_ammo: true,
+                automatic: false,
+                projectile: Some(EntityKind::Rocket),
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponKind::Knife => "knife",
+            WeaponKind::Pistol => "pistol",
+            WeaponKind::MachineGun => "machine_gun",
+            WeaponKind::RocketLauncher => "rocket_launcher",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "knife" => Some(WeaponKind::Knife),
+            "pistol" => Some(WeaponKind::Pistol),
+            "machine_gun" => Some(WeaponKind::MachineGun),
+            "rocket_launcher" => Some(WeaponKind::RocketLauncher),
+            _ => None,
+        }
+    }
+}
+
+/// Fires an instant shot, returning the closest living or solid entity that was hit before the
+/// shot reached a wall or the end of its range.  Solid scenery stops the shot like projectiles,
+/// even though it can't be hurt.
+pub fn hitscan(map: &Map, entities: &EntityStore, origin: Vector, angle: Radians, range: f32) -> Option<EntityId> {
+    let wall = cast_ray(map, origin, angle);
+    let max_distance = if wall.distance > 0.0 { wall.distance.min(range) } else { range };
+    let direction = Vector { x: angle.0.cos(), y: angle.0.sin() };
+
+    entities
+        .iter()
+        .filter(|x| x.health > 0 || x.solid)
+        .filter_map(|entity| {
+            let radius = entity.collision_size as f32 / 2.0;
+            ray_hits_circle(origin, direction, entity.position, radius).map(|distance| (entity.id, distance))
+        })
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(id, _)| id)
+}
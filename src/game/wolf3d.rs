@@ -0,0 +1,453 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::game::map::{Map, CellType, ParsedCell, SpawnType, map_problem, import_error};
+use crate::game::entities::EntityKind;
+use crate::game::items::KeyColor;
+use crate::game::text_format::invalid_data;
+
+/// Separates the path of a `GAMEMAPS` file from the index of the map to load out of it
+const MAP_INDEX_SEPARATOR: char = '#';
+
+/// `MAPHEAD` has room for this many maps, although unused entries are zero
+const MAX_MAPS: usize = 100;
+
+/// Size of the header at the start of each map in `GAMEMAPS`: three plane offsets, three plane
+/// lengths, the width, the height and a 16 byte name
+const MAP_HEADER_SIZE: usize = 38;
+
+/// Plane 0 holds walls, doors and floor codes while plane 1 holds objects
+const WALL_PLANE: usize = 0;
+const OBJECT_PLANE: usize = 1;
+
+/// The wall with the elevator switch that ends the level
+const ELEVATOR_SWITCH_TILE: u16 = 21;
+
+/// The object placed on walls that can be pushed to find a secret
+const PUSHWALL_TILE: u16 = 98;
+
+/// Markers in Carmack compressed data for copying words from earlier in the output, either a
+/// byte sized distance back or a word sized offset from the start
+const CARMACK_NEAR_TAG: u8 = 0xA7;
+const CARMACK_FAR_TAG: u8 = 0xA8;
+
+/// Returns true if the map id points at a Wolfenstein 3D `GAMEMAPS` file, with or without the
+/// index of the map to load
+pub fn is_wolf3d_path(id: &str) -> bool {
+    let (path, _) = split_map_index(id);
+    match Path::new(path).file_name() {
+        Some(name) => name.to_string_lossy().to_ascii_uppercase().starts_with("GAMEMAPS."),
+        None => false,
+    }
+}
+
+/// Imports a map from the original Wolfenstein 3D data files.  The id is the path to `GAMEMAPS`,
+/// optionally followed by `#` and the index of the map, which defaults to the first.  `MAPHEAD` is
+/// read from the same directory with the same extension.
+pub fn load_wolf3d_map(id: &str) -> io::Result<Map> {
+    import(id).map_err(|err| import_error(id, err))
+}
+
+fn import(id: &str) -> io::Result<Map> {
+    let (gamemaps_path, index) = split_map_index(id);
+    let index = match index {
+        None => 0,
+        Some(x) => match x.parse::<usize>() {
+            Ok(x) if x < MAX_MAPS => x,
+            _ => return Err(invalid_data(format!("Invalid map index '{}', expected 0 to {}", x, MAX_MAPS - 1))),
+        },
+    };
+
+    let gamemaps = fs::read(gamemaps_path)?;
+    let maphead = fs::read(maphead_path(gamemaps_path))?;
+    let rlew_tag = read_u16(&maphead, 0)?;
+    let header_offset = read_u32(&maphead, 2 + index * 4)?;
+
+    // Both zero and -1 are used for maps that don't exist
+    if header_offset == 0 || header_offset == u32::MAX {
+        return Err(invalid_data(format!("There is no map {} in MAPHEAD", index)));
+    }
+
+    let header_offset = header_offset as usize;
+    let header = read_bytes(&gamemaps, header_offset, MAP_HEADER_SIZE)?;
+    let width = read_u16(header, 18)? as usize;
+    let height = read_u16(header, 20)? as usize;
+    let read_plane = |plane: usize| -> io::Result<Vec<u16>> {
+        let offset = read_u32(header, plane * 4)? as usize;
+        let length = read_u16(header, 12 + plane * 2)? as usize;
+        let words = rlew_expand(&carmack_expand(read_bytes(&gamemaps, offset, length)?)?, rlew_tag)?;
+        if words.len() != width * height {
+            return Err(invalid_data(format!("Plane {} has {} tiles but the map is {}x{}", plane, words.len(), width, height)));
+        }
+
+        Ok(words)
+    };
+
+    let walls = read_plane(WALL_PLANE)?;
+    let objects = read_plane(OBJECT_PLANE)?;
+    let mut map = Map::with_size(id, width, height);
+    for (index, tile) in walls.iter().enumerate() {
+        map.set_cell(index / width, index % width, wall_cell_type(*tile));
+    }
+
+    for (index, tile) in objects.iter().enumerate() {
+        let (row, col) = (index / width, index % width);
+        if *tile == PUSHWALL_TILE && map.cell_at(row, col) != Some(CellType::Empty) {
+            map.set_cell(row, col, CellType::SecretWall);
+        } else if let Some(spawn) = object_spawn(*tile) {
+            // Objects were never placed in walls, but modified maps might not be so careful
+            if map.cell_at(row, col) == Some(CellType::Empty) {
+                map.set_contents(row, col, ParsedCell::Spawn(spawn));
+            }
+        }
+    }
+
+    if !map.spawns.iter().any(|x| x.entity == SpawnType::Player) {
+        return Err(map_problem("The map has no player start".to_owned()));
+    }
+
+    Ok(map)
+}
+
+fn split_map_index(id: &str) -> (&str, Option<&str>) {
+    match id.rsplit_once(MAP_INDEX_SEPARATOR) {
+        Some((path, index)) => (path, Some(index)),
+        None => (id, None),
+    }
+}
+
+/// `MAPHEAD` sits next to `GAMEMAPS`, named in the same case and with the same extension
+fn maphead_path(gamemaps_path: &str) -> String {
+    let path = Path::new(gamemaps_path);
+    let name = path.file_name().unwrap().to_string_lossy();
+    let maphead = if name.starts_with("gamemaps") { "maphead" } else { "MAPHEAD" };
+    let maphead_name = format!("{}{}", maphead, &name["gamemaps".len()..]);
+    path.with_file_name(maphead_name).to_string_lossy().into_owned()
+}
+
+/// Only three wall textures exist, so each Wolfenstein 3D wall is imported as whichever looks
+/// closest.  Floor codes and anything else that isn't a wall or door are empty.
+fn wall_cell_type(tile: u16) -> CellType {
+    match tile {
+        ELEVATOR_SWITCH_TILE => CellType::Exit,
+        5 | 7 | 8 | 9 => CellType::BlueWall,
+        10 | 11 | 12 | 23 => CellType::WoodWall,
+        1..=89 => CellType::BrickWall,
+
+        // Door tiles come in pairs, one for each direction the door can face
+        92 | 93 => CellType::Door { lock: Some(KeyColor::Gold) },
+        94 | 95 => CellType::Door { lock: Some(KeyColor::Silver) },
+        90..=101 => CellType::Door { lock: None },
+        _ => CellType::Empty,
+    }
+}
+
+/// Every enemy is spawned regardless of the difficulty it was placed for, as if playing on the
+/// hardest difficulty.  Decorations and enemies without an equivalent are left out.
+fn object_spawn(tile: u16) -> Option<SpawnType> {
+    let kind = match tile {
+        19..=22 => return Some(SpawnType::Player),
+        24 | 58 => EntityKind::Barrel,
+        30 | 39 => EntityKind::Pillar,
+        26 | 27 | 37 => EntityKind::Lamp,
+        29 | 47 | 48 => EntityKind::HealthPack,
+        43 => EntityKind::GoldKey,
+        44 => EntityKind::SilverKey,
+        49 => EntityKind::AmmoClip,
+        50 | 51 => EntityKind::MachineGun,
+        52..=55 => EntityKind::Treasure,
+
+        // Guards, officers, SS and dogs repeat in blocks of 36 for each difficulty, with four
+        // tiles for each direction standing and then four for each direction patrolling
+        108..=215 => match (tile - 108) % 36 {
+            0..=3 | 8..=11 | 18..=21 => EntityKind::Guard,
+            4..=7 | 12..=15 | 22..=25 => EntityKind::PatrolGuard,
+            26..=33 => EntityKind::Rat,
+            _ => return None,
+        },
+
+        // Mutants repeat in blocks of 18 after the other enemies
+        216..=259 if (tile - 216) % 18 < 8 => EntityKind::Mutant,
+        _ => return None,
+    };
+
+    Some(SpawnType::Entity(kind))
+}
+
+/// Expands Carmack compressed data, which starts with the length of the expanded data in bytes
+fn carmack_expand(data: &[u8]) -> io::Result<Vec<u8>> {
+    let expanded_length = read_u16(data, 0)? as usize / 2;
+    let mut output: Vec<u16> = Vec::with_capacity(expanded_length);
+    let mut position = 2;
+    while output.len() < expanded_length {
+        let word = read_u16(data, position)?;
+        position += 2;
+
+        let tag = (word >> 8) as u8;
+        let count = (word & 0xFF) as usize;
+        if tag != CARMACK_NEAR_TAG && tag != CARMACK_FAR_TAG {
+            output.push(word);
+            continue;
+        }
+
+        // A count of zero escapes a literal word whose high byte happens to be a tag
+        if count == 0 {
+            output.push(word | read_bytes(data, position, 1)?[0] as u16);
+            position += 1;
+            continue;
+        }
+
+        let start = if tag == CARMACK_NEAR_TAG {
+            let distance = read_bytes(data, position, 1)?[0] as usize;
+            position += 1;
+            output.len().checked_sub(distance)
+        } else {
+            let offset = read_u16(data, position)? as usize;
+            position += 2;
+            Some(offset)
+        };
+
+        // Copies can overlap what they are writing, so they have to be done a word at a time
+        match start {
+            Some(start) if start < output.len() && count <= expanded_length - output.len() => {
+                for index in start..start + count {
+                    output.push(output[index]);
+                }
+            }
+
+            _ => return Err(invalid_data(format!("Carmack compressed data copies from outside of the data at byte {}", position))),
+        }
+    }
+
+    Ok(output.iter().flat_map(|x| x.to_le_bytes()).collect())
+}
+
+/// Expands run length encoded words, which start with the length of the expanded data in bytes.
+/// Runs are the tag followed by how many times to repeat the next word.
+fn rlew_expand(data: &[u8], tag: u16) -> io::Result<Vec<u16>> {
+    let expanded_length = read_u16(data, 0)? as usize / 2;
+    let mut output = Vec::with_capacity(expanded_length);
+    let mut position = 2;
+    while output.len() < expanded_length {
+        let word = read_u16(data, position)?;
+        position += 2;
+        if word != tag {
+            output.push(word);
+            continue;
+        }
+
+        let count = read_u16(data, position)? as usize;
+        let value = read_u16(data, position + 2)?;
+        position += 4;
+        if count > expanded_length - output.len() {
+            return Err(invalid_data(format!("Run length encoded data overflows the plane at byte {}", position)));
+        }
+
+        output.resize(output.len() + count, value);
+    }
+
+    Ok(output)
+}
+
+fn read_bytes(data: &[u8], offset: usize, length: usize) -> io::Result<&[u8]> {
+    match data.get(offset..offset + length) {
+        Some(x) => Ok(x),
+        None => Err(invalid_data(format!("Unexpected end of data reading {} bytes at {}", length, offset))),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> io::Result<u16> {
+    let bytes = read_bytes(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = read_bytes(data, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use super::*;
+
+    const RLEW_TAG: u16 = 0xABCD;
+
+    /// A 6x4 map with a door, an exit, a blue wall, a pushwall, the player and two objects
+    const FIXTURE_WIDTH: usize = 6;
+    const FIXTURE_WALLS: [u16; 24] = [
+        1, 1,   1,  1,   1,   1,
+        1, 106, 90, 106, 106, 8,
+        1, 106, 1,  106, 106, 21,
+        1, 1,   1,  1,   1,   1,
+    ];
+
+    const FIXTURE_OBJECTS: [u16; 24] = [
+        0, 0,  0,  0,  0,   0,
+        0, 19, 0,  24, 0,   0,
+        0, 0,  98, 0,  108, 0,
+        0, 0,  0,  0,  0,   0,
+    ];
+
+    fn words(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    /// Run length encodes the plane and wraps it in Carmack compression that only holds literal
+    /// words, the same layers the original files use
+    fn encode_plane(tiles: &[u16]) -> Vec<u8> {
+        let mut rlew = vec![(tiles.len() * 2) as u16];
+        let mut index = 0;
+        while index < tiles.len() {
+            let run = tiles[index..].iter().take_while(|x| **x == tiles[index]).count();
+            if run > 3 {
+                rlew.extend_from_slice(&[RLEW_TAG, run as u16, tiles[index]]);
+            } else {
+                rlew.extend(std::iter::repeat_n(tiles[index], run));
+            }
+
+            index += run;
+        }
+
+        let rlew = words(&rlew);
+        let mut carmack = words(&[rlew.len() as u16]);
+        carmack.extend(rlew);
+        carmack
+    }
+
+    /// Writes `GAMEMAPS.TST` and `MAPHEAD.TST` holding the fixture as map 0, no map 1 and a map 2
+    /// marked as missing with -1, returning the path to `GAMEMAPS.TST`
+    fn write_fixture(name: &str) -> String {
+        let directory = env::temp_dir().join(format!("rustcaster-wolf3d-{}-{}", name, process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut gamemaps = b"TED5v1.0".to_vec();
+        let walls = encode_plane(&FIXTURE_WALLS);
+        let objects = encode_plane(&FIXTURE_OBJECTS);
+        let walls_offset = gamemaps.len() as u32;
+        gamemaps.extend(&walls);
+        let objects_offset = gamemaps.len() as u32;
+        gamemaps.extend(&objects);
+
+        let header_offset = gamemaps.len() as u32;
+        for offset in &[walls_offset, objects_offset, 0] {
+            gamemaps.extend(&offset.to_le_bytes());
+        }
+
+        let height = FIXTURE_WALLS.len() / FIXTURE_WIDTH;
+        gamemaps.extend(words(&[walls.len() as u16, objects.len() as u16, 0, FIXTURE_WIDTH as u16, height as u16]));
+        gamemaps.extend(b"Fixture\0\0\0\0\0\0\0\0\0");
+
+        let mut maphead = words(&[RLEW_TAG]);
+        for index in 0..MAX_MAPS {
+            let offset = match index {
+                0 => header_offset,
+                2 => u32::MAX,
+                _ => 0,
+            };
+
+            maphead.extend(&offset.to_le_bytes());
+        }
+
+        fs::write(directory.join("GAMEMAPS.TST"), gamemaps).unwrap();
+        fs::write(directory.join("MAPHEAD.TST"), maphead).unwrap();
+        directory.join("GAMEMAPS.TST").to_str().unwrap().to_owned()
+    }
+
+    fn remove_fixture(path: &str) {
+        fs::remove_dir_all(Path::new(path).parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn carmack_near_copy_repeats_recent_words() {
+        let data = [8, 0, 0x11, 0x11, 0x22, 0x22, 2, CARMACK_NEAR_TAG, 2];
+        assert_eq!(carmack_expand(&data).unwrap(), vec![0x11, 0x11, 0x22, 0x22, 0x11, 0x11, 0x22, 0x22]);
+    }
+
+    #[test]
+    fn carmack_near_copy_can_overlap_its_output() {
+        let data = [8, 0, 0x34, 0x12, 3, CARMACK_NEAR_TAG, 1];
+        assert_eq!(carmack_expand(&data).unwrap(), words(&[0x1234; 4]));
+    }
+
+    #[test]
+    fn carmack_far_copy_repeats_words_from_the_start() {
+        let data = [10, 0, 0x34, 0x12, 0x78, 0x56, 0xBC, 0x9A, 2, CARMACK_FAR_TAG, 1, 0];
+        assert_eq!(carmack_expand(&data).unwrap(), words(&[0x1234, 0x5678, 0x9ABC, 0x5678, 0x9ABC]));
+    }
+
+    #[test]
+    fn carmack_count_of_zero_escapes_a_literal_tag() {
+        let data = [4, 0, 0, CARMACK_NEAR_TAG, 0x42, 0, CARMACK_FAR_TAG, 0x99];
+        assert_eq!(carmack_expand(&data).unwrap(), words(&[0xA742, 0xA899]));
+    }
+
+    #[test]
+    fn carmack_copies_from_outside_the_data_are_errors() {
+        // Further back than has been written
+        assert!(carmack_expand(&[6, 0, 0x11, 0x11, 2, CARMACK_NEAR_TAG, 2]).is_err());
+
+        // From an offset that hasn't been written yet
+        assert!(carmack_expand(&[6, 0, 0x11, 0x11, 1, CARMACK_FAR_TAG, 1, 0]).is_err());
+
+        // More words than the expanded length has room for
+        assert!(carmack_expand(&[4, 0, 0x11, 0x11, 2, CARMACK_NEAR_TAG, 1]).is_err());
+
+        // Missing the distance byte
+        assert!(carmack_expand(&[4, 0, 0x11, 0x11, 1, CARMACK_NEAR_TAG]).is_err());
+    }
+
+    #[test]
+    fn rlew_expands_runs_between_literal_words() {
+        let data = words(&[10, 1, RLEW_TAG, 3, 7, 2]);
+        assert_eq!(rlew_expand(&data, RLEW_TAG).unwrap(), vec![1, 7, 7, 7, 2]);
+    }
+
+    #[test]
+    fn rlew_runs_past_the_expanded_length_are_errors() {
+        assert!(rlew_expand(&words(&[4, 1, RLEW_TAG, 10, 7]), RLEW_TAG).is_err());
+        assert!(rlew_expand(&words(&[4, 1, RLEW_TAG, 1]), RLEW_TAG).is_err());
+    }
+
+    #[test]
+    fn import_reads_walls_doors_and_spawns() {
+        let path = write_fixture("import");
+        let map = load_wolf3d_map(&path);
+        let map_with_index = load_wolf3d_map(&format!("{}#0", path));
+        remove_fixture(&path);
+
+        let map = map.unwrap();
+        assert_eq!((map.width, map.height), (6, 4));
+        assert_eq!(map.cell_at(0, 0), Some(CellType::BrickWall));
+        assert_eq!(map.cell_at(1, 1), Some(CellType::Empty));
+        assert_eq!(map.cell_at(1, 2), Some(CellType::Door { lock: None }));
+        assert_eq!(map.cell_at(1, 5), Some(CellType::BlueWall));
+        assert_eq!(map.cell_at(2, 2), Some(CellType::SecretWall));
+        assert_eq!(map.cell_at(2, 5), Some(CellType::Exit));
+
+        let spawn_at = |row, col| map.spawns.iter().find(|x| x.row == row && x.col == col).map(|x| x.entity);
+        assert!(spawn_at(1, 1) == Some(SpawnType::Player));
+        assert!(spawn_at(1, 3) == Some(SpawnType::Entity(EntityKind::Barrel)));
+        assert!(spawn_at(2, 4) == Some(SpawnType::Entity(EntityKind::Guard)));
+        assert_eq!(map.spawns.len(), 3);
+
+        assert_eq!(map_with_index.unwrap().cell_at(2, 2), Some(CellType::SecretWall));
+    }
+
+    #[test]
+    fn import_reports_missing_maps_and_bad_indexes() {
+        let path = write_fixture("errors");
+        let missing = load_wolf3d_map(&format!("{}#1", path)).err().unwrap().to_string();
+        let marked_missing = load_wolf3d_map(&format!("{}#2", path)).err().unwrap().to_string();
+        let out_of_range = load_wolf3d_map(&format!("{}#100", path)).err().unwrap().to_string();
+        let not_a_number = load_wolf3d_map(&format!("{}#first", path)).err().unwrap().to_string();
+        remove_fixture(&path);
+
+        assert!(missing.contains("There is no map 1"), "{}", missing);
+        assert!(marked_missing.contains("There is no map 2"), "{}", marked_missing);
+        assert!(out_of_range.contains("Invalid map index '100'"), "{}", out_of_range);
+        assert!(not_a_number.contains("Invalid map index 'first'"), "{}", not_a_number);
+    }
+}
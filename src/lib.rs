@@ -0,0 +1,6 @@
+//! The game's logic and map formats, shared by the game and the tools that work with its maps
+
+pub mod core;
+pub mod game;
+pub mod textures;
+pub mod map_image;
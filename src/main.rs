@@ -1,20 +1,114 @@
-mod core;
-mod game;
 mod rendering;
+mod options;
+mod frame_limiter;
+mod editor;
 
-use std::time::{Instant};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use rustcaster::{core, game, textures, map_image};
 use sdl2::event::Event;
 use sdl2::keyboard::{Keycode, KeyboardState, Scancode};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use crate::core::vector::Vector;
+use crate::core::radians::Radians;
 use crate::game::{GameState, ActiveInputs};
+use crate::game::map::Map;
+use crate::game::pathfinding::CellPosition;
+use crate::game::levels::load_episode;
+use crate::game::replay::{Replay, ReplayOutcome};
+use crate::game::save::{save_game, load_game};
+use crate::rendering::{Assets, save_screenshot};
 use crate::rendering::atlas::Atlas;
+use crate::rendering::font::Font;
+use crate::rendering::performance::PerformanceStats;
+use crate::options::{Options, ScreenshotOptions};
+use crate::frame_limiter::{FrameLimit, FrameLimiter};
+use crate::editor::Editor;
+use crate::textures::{WALL_ATLAS_PATH, WALL_SECTIONS};
 
 const SCREEN_WIDTH: u32 = 800;
 const SCREEN_HEIGHT: u32 = 600;
-const BRICK_WALL_SECTION: &str = "BRICK";
-const WOOD_WALL_SECTION: &str = "WOOD";
-const BLUE_WALL_SECTION: &str = "BLUE";
+const BARREL_SPRITE_SECTION: &str = "BARREL";
+const PILLAR_SPRITE_SECTION: &str = "PILLAR";
+const LAMP_SPRITE_SECTION: &str = "LAMP";
+const RAT_SPRITE_SECTION: &str = "RAT";
+const GUARD_STAND_SPRITE_SECTION: &str = "GUARD_STAND";
+const GUARD_WALK1_SPRITE_SECTION: &str = "GUARD_WALK1";
+const GUARD_WALK2_SPRITE_SECTION: &str = "GUARD_WALK2";
+const GUARD_AIM_SPRITE_SECTION: &str = "GUARD_AIM";
+const GUARD_FIRE_SPRITE_SECTION: &str = "GUARD_FIRE";
+const GUARD_PAIN_SPRITE_SECTION: &str = "GUARD_PAIN";
+const GUARD_DYING_SPRITE_SECTION: &str = "GUARD_DYING";
+const GUARD_DEAD_SPRITE_SECTION: &str = "GUARD_DEAD";
+const MUTANT_STAND_SPRITE_SECTION: &str = "MUTANT_STAND";
+const MUTANT_WALK1_SPRITE_SECTION: &str = "MUTANT_WALK1";
+const MUTANT_WALK2_SPRITE_SECTION: &str = "MUTANT_WALK2";
+const MUTANT_AIM_SPRITE_SECTION: &str = "MUTANT_AIM";
+const MUTANT_FIRE_SPRITE_SECTION: &str = "MUTANT_FIRE";
+const MUTANT_PAIN_SPRITE_SECTION: &str = "MUTANT_PAIN";
+const MUTANT_DYING_SPRITE_SECTION: &str = "MUTANT_DYING";
+const MUTANT_DEAD_SPRITE_SECTION: &str = "MUTANT_DEAD";
+const ROCKET_SPRITE_SECTION: &str = "ROCKET";
+const FIREBALL_SPRITE_SECTION: &str = "FIREBALL";
+const EXPLOSION1_SPRITE_SECTION: &str = "EXPLOSION1";
+const EXPLOSION2_SPRITE_SECTION: &str = "EXPLOSION2";
+const HEALTH_PACK_SPRITE_SECTION: &str = "HEALTH_PACK";
+const AMMO_CLIP_SPRITE_SECTION: &str = "AMMO_CLIP";
+const GOLD_KEY_SPRITE_SECTION: &str = "GOLD_KEY";
+const SILVER_KEY_SPRITE_SECTION: &str = "SILVER_KEY";
+const TREASURE_SPRITE_SECTION: &str = "TREASURE";
+const MACHINE_GUN_PICKUP_SPRITE_SECTION: &str = "MACHINE_GUN_PICKUP";
+const ROCKET_LAUNCHER_PICKUP_SPRITE_SECTION: &str = "ROCKET_LAUNCHER_PICKUP";
+const KNIFE_SPRITE_SECTIONS: [&str; 3] = ["KNIFE", "KNIFE_FIRE1", "KNIFE_FIRE2"];
+const PISTOL_SPRITE_SECTIONS: [&str; 3] = ["PISTOL", "PISTOL_FIRE1", "PISTOL_FIRE2"];
+const MACHINE_GUN_SPRITE_SECTIONS: [&str; 3] = ["MACHINE_GUN", "MACHINE_GUN_FIRE1", "MACHINE_GUN_FIRE2"];
+const ROCKET_LAUNCHER_SPRITE_SECTIONS: [&str; 3] = ["ROCKET_LAUNCHER", "ROCKET_LAUNCHER_FIRE1", "ROCKET_LAUNCHER_FIRE2"];
+const HUD_STATUS_BAR_SECTION: &str = "STATUS_BAR";
+const HUD_DIGIT_SECTIONS: [&str; 10] = [
+    "DIGIT_0", "DIGIT_1", "DIGIT_2", "DIGIT_3", "DIGIT_4", "DIGIT_5", "DIGIT_6", "DIGIT_7", "DIGIT_8", "DIGIT_9",
+];
+const HUD_HEALTH_SECTION: &str = "HEALTH";
+const HUD_AMMO_SECTION: &str = "AMMO";
+const HUD_GOLD_KEY_SECTION: &str = "GOLD_KEY";
+const HUD_SILVER_KEY_SECTION: &str = "SILVER_KEY";
+const HUD_CROSSHAIR_SECTION: &str = "CROSSHAIR";
+const HUD_TREASURE_SECTION: &str = "TREASURE";
+const QUICK_SAVE_PATH: &str = "quicksave.sav";
 
 pub fn main() {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(x) => x,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(replay_path) = &options.replay_path {
+        std::process::exit(run_replay(replay_path));
+    }
+
+    let map_ids = match &options.episode_path {
+        Some(path) => match load_episode(path) {
+            Ok(x) => x,
+            Err(err) => panic!("Error loading episode '{}': {:?}", path, err),
+        },
+
+        None => vec![options.map_id.clone()],
+    };
+
+    let levels = map_ids.iter()
+        .map(|id| match Map::load(id) {
+            Ok(x) => x,
+            Err(err) => panic!("Error loading map '{}': {:?}", id, err),
+        })
+        .collect::<Vec<_>>();
+
+    let assets = load_assets();
+    if let Some(screenshot) = &options.screenshot {
+        std::process::exit(run_screenshot(levels, &options, screenshot, &assets));
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
@@ -23,18 +117,24 @@ pub fn main() {
         .build()
         .unwrap();
 
-    let mut wall_atlas = match Atlas::new("assets/wolftextures.png".to_owned()) {
+    let mut canvas = match options.frame_limit {
+        FrameLimit::VSync => window.into_canvas().present_vsync().build().unwrap(),
+        _ => window.into_canvas().build().unwrap(),
+    };
+
+    let mut game_state = match GameState::with_levels(levels, 0, options.seed) {
         Ok(x) => x,
-        Err(err) => panic!("Error loading atlas texture: {:?}", err),
+        Err(err) => panic!("Error starting the game: {:?}", err),
     };
 
-    wall_atlas.create_section(BRICK_WALL_SECTION.to_owned(), 64, 0, 64, 64);
-    wall_atlas.create_section(BLUE_WALL_SECTION.to_owned(), 256, 0, 64, 64);
-    wall_atlas.create_section(WOOD_WALL_SECTION.to_owned(), 384, 0, 64, 64);
+    let mut recording = match options.record_path {
+        Some(_) => Some(Replay::new(map_ids, game_state.seed)),
+        None => None,
+    };
 
-    let mut canvas = window.into_canvas().build().unwrap();
-    let mut game_state = GameState::new();
-    let mut frame_count = 0_u32;
+    let mut editor: Option<Editor> = None;
+    let mut performance = PerformanceStats::new();
+    let mut frame_limiter = FrameLimiter::new(options.frame_limit);
     let mut last_frame_at = Instant::now();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
@@ -42,11 +142,17 @@ pub fn main() {
         let frame_start = Instant::now();
         let time_since_last_frame = frame_start - last_frame_at;
         let mut inputs = ActiveInputs::new();
+        let mut toggle_editor = false;
+        let mut take_screenshot = false;
+        let (screen_width, screen_height) = canvas.output_size().unwrap();
 
         for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit {..} => break 'main_loop,
-                Event::KeyUp { keycode: Some(key), .. } => {
+            match (&mut editor, event) {
+                (_, Event::Quit {..}) => break 'main_loop,
+                (_, Event::KeyUp { keycode: Some(Keycode::F4), .. }) => toggle_editor = true,
+                (Some(editor), event) => editor.handle_event(&event, screen_width, screen_height),
+                (None, Event::KeyUp { keycode: Some(Keycode::F12), .. }) => take_screenshot = true,
+                (None, Event::KeyUp { keycode: Some(key), .. }) => {
                     apply_key_up_to_inputs(&mut inputs, key);
                 }
                 _ => {}
@@ -59,12 +165,277 @@ pub fn main() {
             break;
         }
 
+        if toggle_editor {
+            match editor.take() {
+                // The level starts over with the edited map, since entities may have been moved.  The
+                // editor stays open if the map can't be played, such as when it has no player spawn.
+                Some(mut closed) => {
+                    if closed.changed {
+                        let mut levels = game_state.levels.clone();
+                        levels[game_state.level] = closed.map.clone();
+                        match GameState::with_levels(levels, game_state.level, game_state.seed) {
+                            Ok(x) => game_state = x,
+                            Err(err) => {
+                                closed.message = Some(err.to_string());
+                                editor = Some(closed);
+                            }
+                        }
+                    }
+                }
+
+                // A replay can only reproduce a session that plays a single unedited map
+                None if recording.is_some() => eprintln!("The editor is disabled while recording a replay"),
+                None => editor = Some(Editor::new(game_state.initial_map.clone())),
+            }
+        }
+
+        if let Some(editor) = &mut editor {
+            editor.tick(&time_since_last_frame, &inputs);
+            rendering::editor::render_editor(&mut canvas, editor, &assets);
+
+            last_frame_at = frame_start;
+            frame_limiter.wait();
+            continue;
+        }
+
+        if inputs.quick_save {
+            match save_game(&game_state, QUICK_SAVE_PATH) {
+                Ok(()) => println!("Game saved to '{}'", QUICK_SAVE_PATH),
+                Err(err) => eprintln!("Failed to save game to '{}': {:?}", QUICK_SAVE_PATH, err),
+            }
+        }
+
+        if inputs.quick_load {
+            if recording.is_some() {
+                // A replay can only reproduce a session that starts from its map and seed
+                eprintln!("Quick load is disabled while recording a replay");
+            } else {
+                match load_game(QUICK_SAVE_PATH) {
+                    Ok(x) => game_state = x,
+                    Err(err) => eprintln!("Failed to load game from '{}': {:?}", QUICK_SAVE_PATH, err),
+                }
+            }
+        }
+
+        // A replay only plays back the same if the script doesn't change part way through
+        if let (Some(script), None) = (&mut game_state.script, &recording) {
+            if script.reload_if_changed() {
+                println!("Reloaded script '{}'", script.path.display());
+            }
+        }
+
         game_state.tick(&time_since_last_frame, &inputs);
+        if let Some(replay) = &mut recording {
+            replay.record(&time_since_last_frame, &inputs);
+        }
+
+        let mut timings = rendering::render(&mut canvas, &game_state, &assets, &options.minimap, &performance);
+        if take_screenshot {
+            let path = screenshot_path();
+            match save_screenshot(&canvas, &path) {
+                Ok(()) => println!("Screenshot saved to '{}'", path),
+                Err(err) => eprintln!("Failed to save screenshot to '{}': {}", path, err),
+            }
+        }
 
-        rendering::render(&mut canvas, &game_state, &wall_atlas);
+        rendering::present(&mut canvas, &mut timings);
 
-        frame_count = frame_count.wrapping_add(1_u32);
+        performance.record(time_since_last_frame, timings);
         last_frame_at = frame_start;
+        frame_limiter.wait();
+    }
+
+    if let (Some(replay), Some(path)) = (&mut recording, &options.record_path) {
+        replay.finish(&game_state);
+        if let Err(err) = replay.save(path) {
+            eprintln!("Failed to save replay to '{}': {:?}", path, err);
+        }
+    }
+}
+
+/// Loads the atlases and font the game is drawn with
+fn load_assets() -> Assets {
+    let mut wall_atlas = match Atlas::new(WALL_ATLAS_PATH.to_owned()) {
+        Ok(x) => x,
+        Err(err) => panic!("Error loading atlas texture: {:?}", err),
+    };
+
+    for (section, x, y, width, height) in WALL_SECTIONS.iter() {
+        wall_atlas.create_section((*section).to_owned(), *x, *y, *width, *height);
+    }
+
+    let mut sprite_atlas = match Atlas::new("assets/sprites.png".to_owned()) {
+        Ok(x) => x,
+        Err(err) => panic!("Error loading sprite texture: {:?}", err),
+    };
+
+    sprite_atlas.create_section(BARREL_SPRITE_SECTION.to_owned(), 0, 0, 64, 64);
+    sprite_atlas.create_section(PILLAR_SPRITE_SECTION.to_owned(), 64, 0, 64, 64);
+    sprite_atlas.create_section(LAMP_SPRITE_SECTION.to_owned(), 128, 0, 64, 64);
+    sprite_atlas.create_section(RAT_SPRITE_SECTION.to_owned(), 192, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_STAND_SPRITE_SECTION.to_owned(), 256, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_WALK1_SPRITE_SECTION.to_owned(), 320, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_WALK2_SPRITE_SECTION.to_owned(), 384, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_AIM_SPRITE_SECTION.to_owned(), 448, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_FIRE_SPRITE_SECTION.to_owned(), 512, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_PAIN_SPRITE_SECTION.to_owned(), 576, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_DYING_SPRITE_SECTION.to_owned(), 640, 0, 64, 64);
+    sprite_atlas.create_section(GUARD_DEAD_SPRITE_SECTION.to_owned(), 704, 0, 64, 64);
+
+    let weapon_sections = KNIFE_SPRITE_SECTIONS.iter()
+        .chain(PISTOL_SPRITE_SECTIONS.iter())
+        .chain(MACHINE_GUN_SPRITE_SECTIONS.iter());
+
+    for (index, section) in weapon_sections.enumerate() {
+        sprite_atlas.create_section((*section).to_owned(), 768 + index as u32 * 64, 0, 64, 64);
+    }
+
+    sprite_atlas.create_section(MUTANT_STAND_SPRITE_SECTION.to_owned(), 1344, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_WALK1_SPRITE_SECTION.to_owned(), 1408, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_WALK2_SPRITE_SECTION.to_owned(), 1472, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_AIM_SPRITE_SECTION.to_owned(), 1536, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_FIRE_SPRITE_SECTION.to_owned(), 1600, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_PAIN_SPRITE_SECTION.to_owned(), 1664, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_DYING_SPRITE_SECTION.to_owned(), 1728, 0, 64, 64);
+    sprite_atlas.create_section(MUTANT_DEAD_SPRITE_SECTION.to_owned(), 1792, 0, 64, 64);
+    sprite_atlas.create_section(ROCKET_SPRITE_SECTION.to_owned(), 1856, 0, 64, 64);
+    sprite_atlas.create_section(FIREBALL_SPRITE_SECTION.to_owned(), 1920, 0, 64, 64);
+    sprite_atlas.create_section(EXPLOSION1_SPRITE_SECTION.to_owned(), 1984, 0, 64, 64);
+    sprite_atlas.create_section(EXPLOSION2_SPRITE_SECTION.to_owned(), 2048, 0, 64, 64);
+
+    for (index, section) in ROCKET_LAUNCHER_SPRITE_SECTIONS.iter().enumerate() {
+        sprite_atlas.create_section((*section).to_owned(), 2112 + index as u32 * 64, 0, 64, 64);
+    }
+
+    sprite_atlas.create_section(HEALTH_PACK_SPRITE_SECTION.to_owned(), 2304, 0, 64, 64);
+    sprite_atlas.create_section(AMMO_CLIP_SPRITE_SECTION.to_owned(), 2368, 0, 64, 64);
+    sprite_atlas.create_section(GOLD_KEY_SPRITE_SECTION.to_owned(), 2432, 0, 64, 64);
+    sprite_atlas.create_section(SILVER_KEY_SPRITE_SECTION.to_owned(), 2496, 0, 64, 64);
+    sprite_atlas.create_section(TREASURE_SPRITE_SECTION.to_owned(), 2560, 0, 64, 64);
+    sprite_atlas.create_section(MACHINE_GUN_PICKUP_SPRITE_SECTION.to_owned(), 2624, 0, 64, 64);
+    sprite_atlas.create_section(ROCKET_LAUNCHER_PICKUP_SPRITE_SECTION.to_owned(), 2688, 0, 64, 64);
+
+    let mut hud_atlas = match Atlas::new("assets/hud.png".to_owned()) {
+        Ok(x) => x,
+        Err(err) => panic!("Error loading HUD texture: {:?}", err),
+    };
+
+    hud_atlas.create_section(HUD_STATUS_BAR_SECTION.to_owned(), 0, 0, 16, 64);
+    for (index, section) in HUD_DIGIT_SECTIONS.iter().enumerate() {
+        hud_atlas.create_section((*section).to_owned(), 64 + index as u32 * 8, 0, 16, 8);
+    }
+
+    hud_atlas.create_section(HUD_HEALTH_SECTION.to_owned(), 144, 0, 16, 16);
+    hud_atlas.create_section(HUD_AMMO_SECTION.to_owned(), 160, 0, 16, 16);
+    hud_atlas.create_section(HUD_GOLD_KEY_SECTION.to_owned(), 176, 0, 16, 16);
+    hud_atlas.create_section(HUD_SILVER_KEY_SECTION.to_owned(), 192, 0, 16, 16);
+    hud_atlas.create_section(HUD_CROSSHAIR_SECTION.to_owned(), 208, 0, 16, 16);
+    hud_atlas.create_section(HUD_TREASURE_SECTION.to_owned(), 224, 0, 16, 16);
+
+    let font = match Font::load_bmfont("assets/font.fnt") {
+        Ok(x) => x,
+        Err(err) => panic!("Error loading font: {:?}", err),
+    };
+
+    Assets { wall_atlas, sprite_atlas, hud_atlas, font }
+}
+
+/// Renders a single frame from the screenshot's view without opening a window, returning the
+/// process exit code
+fn run_screenshot(levels: Vec<Map>, options: &Options, screenshot: &ScreenshotOptions, assets: &Assets) -> i32 {
+    let mut game_state = match GameState::with_levels(levels, 0, options.seed) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("Failed to start the game: {}", err);
+            return 2;
+        }
+    };
+
+    let position = Vector { x: screenshot.x, y: screenshot.y };
+    let cell = CellPosition::from_position(&game_state.map, position);
+    if !matches!(cell, Some(x) if game_state.map.is_passable(x.row, x.col)) {
+        eprintln!("The screenshot view ({}, {}) is outside of the map or inside a wall", position.x, position.y);
+        return 2;
+    }
+
+    game_state.player.position = position;
+    game_state.player.facing = Radians(screenshot.angle.to_radians()).clamp();
+    game_state.player_cell = cell;
+
+    let canvas = Surface::new(SCREEN_WIDTH, SCREEN_HEIGHT, PixelFormatEnum::RGB24).and_then(|x| x.into_canvas());
+    let mut canvas = match canvas {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("Failed to create a canvas for the screenshot: {}", err);
+            return 2;
+        }
+    };
+
+    rendering::render(&mut canvas, &game_state, assets, &options.minimap, &PerformanceStats::new());
+    match save_screenshot(&canvas, &screenshot.path) {
+        Ok(()) => {
+            println!("Screenshot saved to '{}'", screenshot.path);
+            0
+        }
+
+        Err(err) => {
+            eprintln!("Failed to save screenshot to '{}': {}", screenshot.path, err);
+            2
+        }
+    }
+}
+
+/// A file name for a screenshot taken now, such as `screenshot-20240131-154502-123.png`, in UTC
+fn screenshot_path() -> String {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_date(seconds / 86_400);
+    let time_of_day = seconds % 86_400;
+    format!("screenshot-{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.png",
+            year, month, day, time_of_day / 3600, time_of_day / 60 % 60, time_of_day % 60, since_epoch.subsec_millis())
+}
+
+/// Converts days since 1970-01-01 to a year, month and day in the Gregorian calendar
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Counts from 0000-03-01 so leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Plays back a recorded replay without opening a window, returning the process exit code
+fn run_replay(path: &str) -> i32 {
+    let outcome = match Replay::load(path).and_then(|replay| replay.verify()) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("Failed to play replay '{}': {:?}", path, err);
+            return 2;
+        }
+    };
+
+    match outcome {
+        ReplayOutcome::Matched(position) => {
+            println!("Replay matched: player ended at ({}, {})", position.x, position.y);
+            0
+        }
+
+        ReplayOutcome::Mismatched { expected, actual } => {
+            println!("Replay mismatch: expected player at ({}, {}) but ended at ({}, {})",
+                     expected.x, expected.y, actual.x, actual.y);
+            1
+        }
+
+        ReplayOutcome::NoExpectedPosition(position) => {
+            println!("Replay has no recorded final position: player ended at ({}, {})", position.x, position.y);
+            1
+        }
     }
 }
 
@@ -74,7 +445,13 @@ fn process_inputs(inputs: &mut ActiveInputs, keyboard_state: &KeyboardState) {
     inputs.move_back = keyboard_state.is_scancode_pressed(Scancode::S);
     inputs.turn_right = keyboard_state.is_scancode_pressed(Scancode::D);
     inputs.turn_left = keyboard_state.is_scancode_pressed(Scancode::A);
+    inputs.fire = keyboard_state.is_scancode_pressed(Scancode::LCtrl)
+        || keyboard_state.is_scancode_pressed(Scancode::Space);
 
+    inputs.pan_map_up = keyboard_state.is_scancode_pressed(Scancode::Up);
+    inputs.pan_map_down = keyboard_state.is_scancode_pressed(Scancode::Down);
+    inputs.pan_map_left = keyboard_state.is_scancode_pressed(Scancode::Left);
+    inputs.pan_map_right = keyboard_state.is_scancode_pressed(Scancode::Right);
 }
 
 fn apply_key_up_to_inputs(inputs: &mut ActiveInputs, key: Keycode) {
@@ -82,7 +459,19 @@ fn apply_key_up_to_inputs(inputs: &mut ActiveInputs, key: Keycode) {
         Keycode::KpPlus => inputs.zoom_in = true,
         Keycode::KpMinus => inputs.zoom_out = true,
         Keycode::M => inputs.toggle_map = true,
+        Keycode::N => inputs.cycle_map_view = true,
+        Keycode::R => inputs.toggle_minimap_rotation = true,
+        Keycode::T => inputs.toggle_map_textures = true,
         Keycode::Slash => inputs.toggle_test = true,
+        Keycode::P => inputs.toggle_paths = true,
+        Keycode::F3 => inputs.toggle_performance = true,
+        Keycode::Num1 => inputs.select_weapon_1 = true,
+        Keycode::Num2 => inputs.select_weapon_2 = true,
+        Keycode::Num3 => inputs.select_weapon_3 = true,
+        Keycode::Num4 => inputs.select_weapon_4 = true,
+        Keycode::E => inputs.use_action = true,
+        Keycode::F5 => inputs.quick_save = true,
+        Keycode::F9 => inputs.quick_load = true,
         _ => (),
     }
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+//! Draws maps from above into images that can be saved without opening a window
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use crate::core::vector::Vector;
+use crate::game::map::{Map, CellType, SpawnType};
+use crate::game::entities::{EntityStore, EntityKind};
+use crate::game::items::KeyColor;
+
+pub type Rgb = [u8; 3];
+
+pub const WHITE: Rgb = [255, 255, 255];
+pub const BLACK: Rgb = [0, 0, 0];
+pub const RED: Rgb = [255, 0, 0];
+pub const GREEN: Rgb = [0, 255, 0];
+pub const BLUE: Rgb = [0, 0, 255];
+pub const YELLOW: Rgb = [255, 255, 0];
+pub const MAGENTA: Rgb = [255, 0, 255];
+pub const CYAN: Rgb = [0, 255, 255];
+pub const GREY: Rgb = [128, 128, 128];
+pub const GOLD: Rgb = [230, 180, 40];
+pub const SILVER: Rgb = [170, 180, 200];
+
+/// The player's path is darker than their spawn so the two stand apart where they cross
+const PATH_COLOR: Rgb = [0, 140, 0];
+
+/// An RGB image of a map, drawn with the same flat colors as the in game overview map
+pub struct MapImage {
+    pub width: u32,
+    pub height: u32,
+    pixels_per_cell: u32,
+
+    /// How many pixels make up a single map unit
+    scale: f32,
+    pixels: Vec<u8>,
+}
+
+impl MapImage {
+    /// Draws every cell of the map as a square of the specified size
+    pub fn new(map: &Map, pixels_per_cell: u32) -> Self {
+        let width = map.width as u32 * pixels_per_cell;
+        let height = map.height as u32 * pixels_per_cell;
+        let mut image = MapImage {
+            width,
+            height,
+            pixels_per_cell,
+            scale: pixels_per_cell as f32 / map.units_per_cell as f32,
+            pixels: vec![0; (width * height * 3) as usize],
+        };
+
+        for row in 0..map.height {
+            for col in 0..map.width {
+                let color = cell_color(map.cell_at(row, col).unwrap());
+                let (x, y) = (col as u32 * pixels_per_cell, row as u32 * pixels_per_cell);
+                image.fill_rect(x as i32, y as i32, pixels_per_cell, pixels_per_cell, color);
+            }
+        }
+
+        image
+    }
+
+    /// Marks every spawn with a square half a cell wide in the middle of its cell
+    pub fn draw_spawns(&mut self, map: &Map) {
+        let size = (self.pixels_per_cell / 2).max(1);
+        let offset = ((self.pixels_per_cell - size) / 2) as i32;
+        for spawn in &map.spawns {
+            let color = match spawn.entity {
+                SpawnType::Player => GREEN,
+                SpawnType::Entity(kind) => entity_color(kind),
+            };
+
+            let x = (spawn.col as u32 * self.pixels_per_cell) as i32 + offset;
+            let y = (spawn.row as u32 * self.pixels_per_cell) as i32 + offset;
+            self.fill_rect(x, y, size, size, color);
+        }
+    }
+
+    /// Draws every entity where it is, as large as it is solid
+    pub fn draw_entities(&mut self, entities: &EntityStore) {
+        for entity in entities.iter() {
+            self.draw_marker(entity.position, entity.collision_size, entity_color(entity.kind));
+        }
+    }
+
+    /// Draws the player as large as they are solid
+    pub fn draw_player(&mut self, position: Vector, collision_size: u16) {
+        self.draw_marker(position, collision_size, GREEN);
+    }
+
+    /// Joins up positions the player has been at with a line
+    pub fn draw_path(&mut self, path: &[Vector]) {
+        for pair in path.windows(2) {
+            let (from, to) = (pair[0] * self.scale, pair[1] * self.scale);
+            self.draw_line(from.x as i32, from.y as i32, to.x as i32, to.y as i32, PATH_COLOR);
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        save_png(path, self.width, self.height, &self.pixels)
+    }
+
+    fn draw_marker(&mut self, position: Vector, collision_size: u16, color: Rgb) {
+        let size = ((collision_size as f32 * self.scale) as u32).max(1);
+        let center = position * self.scale;
+        self.fill_rect(center.x as i32 - size as i32 / 2, center.y as i32 - size as i32 / 2, size, size, color);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Rgb) {
+        for pixel_y in y..y + height as i32 {
+            for pixel_x in x..x + width as i32 {
+                self.set_pixel(pixel_x, pixel_y, color);
+            }
+        }
+    }
+
+    /// Bresenham's line, which only ever steps one pixel at a time
+    fn draw_line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, color: Rgb) {
+        let (delta_x, delta_y) = ((x2 - x1).abs(), -(y2 - y1).abs());
+        let (step_x, step_y) = (if x1 < x2 { 1 } else { -1 }, if y1 < y2 { 1 } else { -1 });
+        let (mut x, mut y, mut error) = (x1, y1, delta_x + delta_y);
+        loop {
+            self.set_pixel(x, y, color);
+            if x == x2 && y == y2 {
+                break;
+            }
+
+            if error * 2 >= delta_y {
+                error += delta_y;
+                x += step_x;
+            }
+
+            if error * 2 <= delta_x {
+                error += delta_x;
+                y += step_y;
+            }
+        }
+    }
+
+    fn set_pixel(&mut self, x: i32, y: i32, color: Rgb) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+
+        let index = ((y as u32 * self.width + x as u32) * 3) as usize;
+        self.pixels[index..index + 3].copy_from_slice(&color);
+    }
+}
+
+/// The flat color a cell is drawn with on the overhead maps
+pub fn cell_color(cell: CellType) -> Rgb {
+    match cell {
+        CellType::BrickWall | CellType::SecretWall => RED,
+        CellType::BlueWall => BLUE,
+        CellType::WoodWall => YELLOW,
+        CellType::Door { lock: None } => GREY,
+        CellType::Door { lock: Some(KeyColor::Gold) } => GOLD,
+        CellType::Door { lock: Some(KeyColor::Silver) } => SILVER,
+        CellType::Exit => MAGENTA,
+        CellType::Empty => WHITE,
+    }
+}
+
+/// Enemies are black, keys match the doors they open, other pickups are cyan and everything else
+/// is grey
+fn entity_color(kind: EntityKind) -> Rgb {
+    match kind {
+        EntityKind::Rat | EntityKind::Guard | EntityKind::PatrolGuard | EntityKind::Mutant => BLACK,
+        EntityKind::GoldKey => GOLD,
+        EntityKind::SilverKey => SILVER,
+        EntityKind::HealthPack | EntityKind::AmmoClip | EntityKind::Treasure | EntityKind::MachineGun
+            | EntityKind::RocketLauncher => CYAN,
+        EntityKind::Barrel | EntityKind::Pillar | EntityKind::Lamp | EntityKind::Rocket | EntityKind::Fireball => GREY,
+    }
+}
+
+/// Writes 8 bit RGB pixels, a row at a time from the top, to a PNG file
+pub fn save_png(path: &str, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::game::map::DEFAULT_MAP_ID;
+use crate::game::generation::{Algorithm, DEFAULT_WIDTH, DEFAULT_HEIGHT, generated_map_id, parse_size};
+use crate::frame_limiter::FrameLimit;
+use crate::rendering::minimap::{Corner, MinimapOptions};
+
+/// Read before the command line arguments if it exists, unless another file is given by `--config`
+const DEFAULT_CONFIG_PATH: &str = "rustcaster.cfg";
+
+pub struct Options {
+    pub map_id: String,
+
+    /// A file listing the maps to play one after another, used instead of the single map
+    pub episode_path: Option<String>,
+
+    /// Generates the map from the seed instead of loading it, at the map size
+    pub generator: Option<Algorithm>,
+    pub map_size: (usize, usize),
+    pub seed: u64,
+    pub record_path: Option<String>,
+    pub replay_path: Option<String>,
+
+    /// Renders a single frame from this view to a file without opening a window
+    pub screenshot: Option<ScreenshotOptions>,
+    pub frame_limit: FrameLimit,
+    pub minimap: MinimapOptions,
+}
+
+/// Where the player is placed to take a screenshot, in map units and degrees clockwise from facing
+/// right
+pub struct ScreenshotOptions {
+    pub x: f32,
+    pub y: f32,
+    pub angle: f32,
+    pub path: String,
+}
+
+impl Options {
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let args = args.collect::<Vec<_>>();
+        let mut options = Options {
+            map_id: DEFAULT_MAP_ID.to_owned(),
+            episode_path: None,
+            generator: None,
+            map_size: (DEFAULT_WIDTH, DEFAULT_HEIGHT),
+            seed: default_seed(),
+            record_path: None,
+            replay_path: None,
+            screenshot: None,
+            frame_limit: FrameLimit::VSync,
+            minimap: MinimapOptions::default(),
+        };
+
+        // The config file is applied first so the command line can override anything in it
+        let config_path = match args.iter().position(|x| x == "--config") {
+            Some(index) => Some(next_value(&mut args.iter().skip(index + 1).cloned(), "--config")?),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Some(DEFAULT_CONFIG_PATH.to_owned()),
+            None => None,
+        };
+
+        if let Some(path) = config_path {
+            options.apply(config_args(&path)?.into_iter())?;
+        }
+
+        options.apply(args.into_iter())?;
+
+        // Generated maps are identified by everything needed to generate them again
+        if let Some(algorithm) = options.generator {
+            let (width, height) = options.map_size;
+            options.map_id = generated_map_id(algorithm, width, height, options.seed);
+        }
+
+        Ok(options)
+    }
+
+    fn apply<I: Iterator<Item = String>>(&mut self, mut args: I) -> Result<(), String> {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--map" => self.map_id = next_value(&mut args, &arg)?,
+                "--episode" => self.episode_path = Some(next_value(&mut args, &arg)?),
+                "--record" => self.record_path = Some(next_value(&mut args, &arg)?),
+                "--replay" => self.replay_path = Some(next_value(&mut args, &arg)?),
+                "--config" => {
+                    // Already read before any other arguments were applied
+                    next_value(&mut args, &arg)?;
+                }
+
+                "--screenshot" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let view = value.split(',').map(|x| x.trim().parse::<f32>()).collect::<Vec<_>>();
+                    let (x, y, angle) = match view.as_slice() {
+                        [Ok(x), Ok(y), Ok(angle)] => (*x, *y, *angle),
+                        _ => return Err(format!("Invalid screenshot view '{}', expected x,y,angle such as 12.5,7.5,90", value)),
+                    };
+
+                    let path = next_value(&mut args, &arg)?;
+                    self.screenshot = Some(ScreenshotOptions { x, y, angle, path });
+                }
+
+                "--generate" => {
+                    let value = next_value(&mut args, &arg)?;
+                    self.generator = match Algorithm::from_name(&value) {
+                        Some(x) => Some(x),
+                        None => return Err(format!("Invalid map generator '{}', expected maze, rooms or caves", value)),
+                    };
+                }
+
+                "--map-size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    self.map_size = match parse_size(&value) {
+                        Some(x) => x,
+                        None => return Err(format!("Invalid map size '{}', expected a width and height such as 48x32", value)),
+                    };
+                }
+
+                "--seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    self.seed = match value.parse() {
+                        Ok(x) => x,
+                        Err(_) => return Err(format!("Invalid seed '{}'", value)),
+                    };
+                }
+
+                "--frame-limit" => {
+                    let value = next_value(&mut args, &arg)?;
+                    self.frame_limit = match FrameLimit::parse(&value) {
+                        Some(x) => x,
+                        None => return Err(format!("Invalid frame limit '{}', expected vsync, uncapped or a frame rate", value)),
+                    };
+                }
+
+                "--minimap-corner" => {
+                    let value = next_value(&mut args, &arg)?;
+                    self.minimap.corner = match Corner::parse(&value) {
+                        Some(x) => x,
+                        None => return Err(format!("Invalid minimap corner '{}', expected top-left, top-right, bottom-left or bottom-right", value)),
+                    };
+                }
+
+                "--minimap-size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    self.minimap.size = match value.parse() {
+                        Ok(x) if x > 0 => x,
+                        _ => return Err(format!("Invalid minimap size '{}'", value)),
+                    };
+                }
+
+                x => return Err(format!("Unknown argument '{}'", x)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a config file as command line arguments.  Each line holds an option's name without the
+/// leading dashes followed by its value, such as `frame-limit 60`.  Blank lines and lines
+/// starting with `#` are ignored.
+fn config_args(path: &str) -> Result<Vec<String>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(err) => return Err(format!("Failed to read config file '{}': {}", path, err)),
+    };
+
+    let mut args = Vec::new();
+    for line in contents.lines().map(str::trim).filter(|x| !x.is_empty() && !x.starts_with('#')) {
+        let mut parts = line.split_whitespace();
+        if let Some(name) = parts.next() {
+            args.push(format!("--{}", name));
+            args.extend(parts.map(str::to_owned));
+        }
+    }
+
+    Ok(args)
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, arg: &str) -> Result<String, String> {
+    match args.next() {
+        Some(x) => Ok(x),
+        None => Err(format!("Argument '{}' requires a value", arg)),
+    }
+}
+
+fn default_seed() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(x) => x.as_nanos() as u64,
+        Err(_) => 0,
+    }
+}
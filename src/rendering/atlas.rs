@@ -43,6 +43,12 @@ impl Atlas {
     }
 
     pub fn get_rgb_at(&self, section_name: &str, x: u32, y: u32) -> Option<(u8, u8, u8)> {
+        self.get_rgba_at(section_name, x, y).map(|(r, g, b, _)| (r, g, b))
+    }
+
+    /// Returns the color at the specified pixel of the section.  Images without an alpha channel
+    /// are treated as fully opaque.
+    pub fn get_rgba_at(&self, section_name: &str, x: u32, y: u32) -> Option<(u8, u8, u8, u8)> {
         let section = match self.sections.get(section_name) {
             None => return None,
             Some(x) => x,
@@ -61,18 +67,29 @@ impl Atlas {
             panic!("Attempted to get pixel outside image boundary");
         }
 
-        match self.image_info.color_type {
-            ColorType::RGB => {
-                let first_byte_index = ((self.image_info.width * 3 * y) + (x * 3)) as usize;
-                Some((
-                    *self.image_data.get(first_byte_index).unwrap(),
-                    *self.image_data.get(first_byte_index + 1).unwrap(),
-                    *self.image_data.get(first_byte_index+ 2).unwrap(),
-                ))
-            }
-
+        let bytes_per_pixel = match self.image_info.color_type {
+            ColorType::RGB => 3,
+            ColorType::RGBA => 4,
             x => panic!("Unknown color type of {:?}", x),
-        }
+        };
+
+        let first_byte_index = ((self.image_info.width * bytes_per_pixel * y) + (x * bytes_per_pixel)) as usize;
+        let alpha = if bytes_per_pixel == 4 {
+            *self.image_data.get(first_byte_index + 3).unwrap()
+        } else {
+            255
+        };
+
+        Some((
+            *self.image_data.get(first_byte_index).unwrap(),
+            *self.image_data.get(first_byte_index + 1).unwrap(),
+            *self.image_data.get(first_byte_index+ 2).unwrap(),
+            alpha,
+        ))
+    }
+
+    pub fn get_width_and_height(&self) -> (u32, u32) {
+        (self.image_info.width, self.image_info.height)
     }
 
     pub fn get_section_width_and_height(&self, section_name: &str) -> Option<(u32, u32)> {
@@ -0,0 +1,50 @@
+use sdl2::rect::{Rect, Point};
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, RenderTarget};
+use crate::core::vector::Vector;
+use crate::game::GameState;
+use crate::rendering::map::cell_color;
+
+/// Map units are drawn this many pixels wide at the lowest zoom level
+const PIXELS_PER_UNIT: f32 = 2.0;
+
+/// How far in front of the player the facing indicator reaches, in map units
+const FACING_LINE_LENGTH: f32 = 4.0;
+
+/// Draws the cells the player has explored over the whole screen, centered on the player plus
+/// however far the map has been panned.  Unexplored cells are left black.
+pub fn render_automap<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState) {
+    let (width, height) = canvas.output_size().unwrap();
+    let scale = game_state.map_zoom_level as f32 * PIXELS_PER_UNIT;
+    let center = game_state.player.position + game_state.automap_offset;
+    let to_screen = |position: Vector| Point::new(
+        ((position.x - center.x) * scale + width as f32 / 2.0) as i32,
+        ((position.y - center.y) * scale + height as f32 / 2.0) as i32,
+    );
+
+    canvas.set_draw_color(Color::BLACK);
+    canvas.fill_rect(Rect::new(0, 0, width, height)).unwrap();
+
+    let units_per_cell = game_state.map.units_per_cell as f32;
+    let cell_size = (units_per_cell * scale).ceil() as u32;
+    for row in 0..game_state.map.height {
+        for col in 0..game_state.map.width {
+            if !game_state.explored.is_explored(row, col) {
+                continue;
+            }
+
+            let top_left = to_screen(Vector { x: col as f32 * units_per_cell, y: row as f32 * units_per_cell });
+            canvas.set_draw_color(cell_color(game_state.map.cell_at(row, col).unwrap()));
+            canvas.fill_rect(Rect::new(top_left.x(), top_left.y(), cell_size, cell_size)).unwrap();
+        }
+    }
+
+    let player = &game_state.player;
+    let player_size = (player.collision_size as f32 * scale).max(2.0) as u32;
+    let player_center = to_screen(player.position);
+    let facing = Vector { x: player.facing.0.cos(), y: player.facing.0.sin() };
+
+    canvas.set_draw_color(Color::GREEN);
+    canvas.fill_rect(Rect::from_center(player_center, player_size, player_size)).unwrap();
+    canvas.draw_line(player_center, to_screen(player.position + facing * FACING_LINE_LENGTH)).unwrap();
+}
@@ -0,0 +1,116 @@
+use sdl2::rect::Rect;
+use sdl2::pixels::Color;
+use sdl2::render::WindowCanvas;
+use crate::editor::{Editor, EditorLayout};
+use crate::game::map::{ParsedCell, SpawnType};
+use crate::rendering::Assets;
+use crate::rendering::map::cell_color;
+use crate::rendering::game_view::render_game_view;
+use crate::rendering::font::{TextStyle, Alignment};
+use crate::rendering::performance::RenderTimings;
+
+/// Text is scaled up by one for every this many pixels of screen height
+const TEXT_SCALE_DIVISOR: u32 = 300;
+
+const GRID_COLOR: Color = Color::RGB(60, 60, 60);
+const SPAWN_COLOR: Color = Color::RGB(60, 60, 140);
+const PLAYER_SPAWN_COLOR: Color = Color::RGB(40, 160, 40);
+
+/// Draws the map being edited with a palette showing the brush and controls along the bottom, or
+/// the 3D view from the cell being previewed
+pub fn render_editor(canvas: &mut WindowCanvas, editor: &Editor, assets: &Assets) {
+    let (width, height) = canvas.output_size().unwrap();
+    let layout = EditorLayout::new(&editor.map, width, height);
+    let text_scale = (height / TEXT_SCALE_DIVISOR).max(1);
+
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+
+    if let Some(preview) = &editor.preview {
+        let mut timings = RenderTimings::default();
+        render_game_view(canvas, preview, &assets.wall_atlas, &assets.sprite_atlas, &mut timings);
+
+        let style = TextStyle::new(Color::WHITE, text_scale, Alignment::Left);
+        let margin = assets.font.line_height(text_scale) as i32;
+        assets.font.draw_text(canvas, "Preview: A/D to turn, V to go back to editing", margin, margin, &style);
+        canvas.present();
+        return;
+    }
+
+    let glyph_scale = (layout.cell_size / assets.font.line_height(1)).max(1);
+    let glyph_style = TextStyle::new(Color::WHITE, glyph_scale, Alignment::Center);
+    for row in 0..editor.map.height {
+        for col in 0..editor.map.width {
+            let rect = layout.cell_rect(row, col);
+            match editor.map.contents_at(row, col).unwrap() {
+                ParsedCell::Environmental(cell_type) => {
+                    canvas.set_draw_color(cell_color(cell_type));
+                    canvas.fill_rect(rect).unwrap();
+                }
+
+                // Spawns are shown as the character they are written as in map files
+                contents @ ParsedCell::Spawn(spawn) => {
+                    let color = match spawn {
+                        SpawnType::Player => PLAYER_SPAWN_COLOR,
+                        _ => SPAWN_COLOR,
+                    };
+
+                    canvas.set_draw_color(color);
+                    canvas.fill_rect(rect).unwrap();
+
+                    let glyph_y = rect.center().y() - assets.font.line_height(glyph_scale) as i32 / 2;
+                    let glyph = contents.glyph().to_string();
+                    assets.font.draw_text(canvas, &glyph, rect.center().x(), glyph_y, &glyph_style);
+                }
+            }
+
+            canvas.set_draw_color(GRID_COLOR);
+            canvas.draw_rect(rect).unwrap();
+        }
+    }
+
+    if let Some(cell) = editor.hovered {
+        canvas.set_draw_color(Color::MAGENTA);
+        canvas.draw_rect(layout.cell_rect(cell.row, cell.col)).unwrap();
+    }
+
+    render_palette(canvas, editor, assets, &layout, text_scale);
+    canvas.present();
+}
+
+fn render_palette(canvas: &mut WindowCanvas, editor: &Editor, assets: &Assets, layout: &EditorLayout, text_scale: u32) {
+    let area = layout.palette_area;
+    canvas.set_draw_color(Color::RGB(30, 30, 30));
+    canvas.fill_rect(area).unwrap();
+
+    let line_height = assets.font.line_height(text_scale) as i32;
+    let margin = line_height / 2;
+    let brush = editor.brush_contents();
+    let swatch = Rect::new(area.x() + margin, area.y() + margin, line_height as u32 * 2, line_height as u32 * 2);
+    match brush {
+        ParsedCell::Environmental(cell_type) => canvas.set_draw_color(cell_color(cell_type)),
+        ParsedCell::Spawn(_) => canvas.set_draw_color(SPAWN_COLOR),
+    }
+
+    canvas.fill_rect(swatch).unwrap();
+
+    let text_x = swatch.right() + margin;
+    let brush_style = TextStyle::new(Color::WHITE, text_scale, Alignment::Left);
+    let brush_text = format!("Brush: '{}' {} ([ and ] to change)", brush.glyph(), brush.name().replace('_', " "));
+    assets.font.draw_text(canvas, &brush_text, text_x, area.y() + margin, &brush_style);
+
+    let help_style = TextStyle::new(Color::RGB(170, 170, 170), text_scale, Alignment::Left);
+    let help_text = match &editor.message {
+        Some(message) => message.clone(),
+        None => "Left click paints, right click erases, Ctrl+Z/Ctrl+Y undo and redo, Ctrl+S saves, \
+                 V previews the view from the cell under the mouse, F4 goes back to playing".to_owned(),
+    };
+
+    let help_top = area.y() + margin + line_height;
+    let help_area = Rect::new(text_x,
+                              help_top,
+                              (area.right() - margin - text_x) as u32,
+                              (area.bottom() - help_top) as u32);
+
+    assets.font.draw_wrapped(canvas, &help_text, help_area, &help_style);
+}
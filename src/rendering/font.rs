@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, BlendMode};
+use sdl2::pixels::Color;
+use crate::rendering::atlas::Atlas;
+use crate::game::text_format::invalid_data;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Alignment { Left, Center, Right }
+
+#[derive(Copy, Clone)]
+pub struct TextStyle {
+    /// Glyph pixels are multiplied by this color, so white glyphs come out in exactly this color
+    pub color: Color,
+
+    /// Every glyph pixel is drawn as a square this many pixels wide
+    pub scale: u32,
+    pub alignment: Alignment,
+}
+
+/// A bitmap font whose glyphs are sections of an atlas
+pub struct Font {
+    atlas: Atlas,
+    glyphs: HashMap<char, Glyph>,
+    line_height: u32,
+}
+
+struct Glyph {
+    /// Name of the glyph's atlas section, or `None` for glyphs with nothing to draw, like spaces
+    section: Option<String>,
+    x_offset: i32,
+    y_offset: i32,
+    advance: u32,
+}
+
+impl TextStyle {
+    pub fn new(color: Color, scale: u32, alignment: Alignment) -> Self {
+        TextStyle { color, scale, alignment }
+    }
+}
+
+impl Font {
+    /// Loads a font from a BMFont descriptor in the text format.  Only single page fonts are
+    /// supported, and the page's image is loaded relative to the descriptor.
+    pub fn load_bmfont(path: &str) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut line_height = None;
+        let mut page_file = None;
+        let mut chars = Vec::new();
+
+        for line in contents.lines() {
+            let mut tokens = tokenize(line).into_iter();
+            let tag = match tokens.next() {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let attributes = tokens
+                .filter_map(|token| {
+                    let mut parts = token.splitn(2, '=');
+                    Some((parts.next()?.to_owned(), parts.next()?.trim_matches('"').to_owned()))
+                })
+                .collect::<HashMap<_, _>>();
+
+            match tag.as_str() {
+                "common" => {
+                    line_height = Some(attribute(&attributes, "lineHeight")?);
+                    if attribute::<u32>(&attributes, "pages")? != 1 {
+                        return Err(invalid_data("Only fonts with a single page are supported".to_owned()));
+                    }
+                }
+
+                "page" => page_file = attributes.get("file").cloned(),
+                "char" => chars.push(attributes),
+                _ => (),
+            }
+        }
+
+        let line_height = line_height.ok_or_else(|| invalid_data(format!("'{}' has no common line", path)))?;
+        let page_file = page_file.ok_or_else(|| invalid_data(format!("'{}' has no page line", path)))?;
+        let page_path = Path::new(path).with_file_name(page_file);
+        let mut atlas = Atlas::new(page_path.to_string_lossy().into_owned())?;
+
+        let (page_width, page_height) = atlas.get_width_and_height();
+        let mut glyphs = HashMap::new();
+        for attributes in chars {
+            let id: u32 = attribute(&attributes, "id")?;
+            let character = std::char::from_u32(id).ok_or_else(|| invalid_data(format!("Invalid char id {}", id)))?;
+            let width: u32 = attribute(&attributes, "width")?;
+            let height: u32 = attribute(&attributes, "height")?;
+
+            let section = if width > 0 && height > 0 {
+                let x: u32 = attribute(&attributes, "x")?;
+                let y: u32 = attribute(&attributes, "y")?;
+                if x.saturating_add(width) > page_width || y.saturating_add(height) > page_height {
+                    return Err(invalid_data(format!("Char {} at {},{} sized {}x{} goes beyond the {}x{} page",
+                                                    id, x, y, width, height, page_width, page_height)));
+                }
+
+                let name = format!("glyph_{}", id);
+                atlas.create_section(name.clone(), x, y, height, width);
+                Some(name)
+            } else {
+                None
+            };
+
+            glyphs.insert(character, Glyph {
+                section,
+                x_offset: attribute(&attributes, "xoffset")?,
+                y_offset: attribute(&attributes, "yoffset")?,
+                advance: attribute(&attributes, "xadvance")?,
+            });
+        }
+
+        Ok(Font { atlas, glyphs, line_height })
+    }
+
+    pub fn line_height(&self, scale: u32) -> u32 {
+        self.line_height * scale
+    }
+
+    /// How many pixels wide the text is when drawn on a single line
+    pub fn text_width(&self, text: &str, scale: u32) -> u32 {
+        text.chars()
+            .filter_map(|x| self.glyph(x))
+            .map(|x| x.advance * scale)
+            .sum()
+    }
+
+    /// Breaks the text into lines no wider than the maximum width, breaking between words where
+    /// possible.  Line breaks already in the text are kept.
+    pub fn wrap(&self, text: &str, max_width: u32, scale: u32) -> Vec<String> {
+        let space_width = self.text_width(" ", scale);
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            let mut line_width = 0;
+            for word in paragraph.split_whitespace() {
+                let word_width = self.text_width(word, scale);
+                if !line.is_empty() && line_width + space_width + word_width > max_width {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0;
+                }
+
+                if !line.is_empty() {
+                    line.push(' ');
+                    line_width += space_width;
+                }
+
+                // Words wider than a whole line are left to overflow it
+                line.push_str(word);
+                line_width += word_width;
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    /// Draws a single line of text.  The position is the top of the text, and its left edge,
+    /// center or right edge depending on the alignment.
+    pub fn draw_text<T: RenderTarget>(&self, canvas: &mut Canvas<T>, text: &str, x: i32, y: i32, style: &TextStyle) {
+        let width = self.text_width(text, style.scale) as i32;
+        let mut pen_x = match style.alignment {
+            Alignment::Left => x,
+            Alignment::Center => x - width / 2,
+            Alignment::Right => x - width,
+        };
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        for character in text.chars() {
+            let glyph = match self.glyph(character) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            if let Some(section) = &glyph.section {
+                let glyph_x = pen_x + glyph.x_offset * style.scale as i32;
+                let glyph_y = y + glyph.y_offset * style.scale as i32;
+                self.draw_glyph(canvas, section, glyph_x, glyph_y, style);
+            }
+
+            pen_x += (glyph.advance * style.scale) as i32;
+        }
+
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    /// Word wraps the text to fit the width of the area and draws it from the top of the area,
+    /// aligning each line within it.  Lines that don't fit in the area's height are not drawn.
+    pub fn draw_wrapped<T: RenderTarget>(&self, canvas: &mut Canvas<T>, text: &str, area: Rect, style: &TextStyle) {
+        let x = match style.alignment {
+            Alignment::Left => area.left(),
+            Alignment::Center => area.left() + area.width() as i32 / 2,
+            Alignment::Right => area.right(),
+        };
+
+        let line_height = self.line_height(style.scale) as i32;
+        let mut y = area.top();
+        for line in self.wrap(text, area.width(), style.scale) {
+            if y + line_height > area.bottom() {
+                break;
+            }
+
+            self.draw_text(canvas, &line, x, y, style);
+            y += line_height;
+        }
+    }
+
+    /// Characters missing from the font are drawn as question marks
+    fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn draw_glyph<T: RenderTarget>(&self, canvas: &mut Canvas<T>, section: &str, x: i32, y: i32, style: &TextStyle) {
+        let (width, height) = self.atlas.get_section_width_and_height(section).unwrap();
+        let color = style.color;
+        for image_y in 0..height {
+            for image_x in 0..width {
+                let (r, g, b, a) = self.atlas.get_rgba_at(section, image_x, image_y).unwrap();
+                if a == 0 {
+                    continue;
+                }
+
+                canvas.set_draw_color(Color::RGBA(
+                    (r as u32 * color.r as u32 / 255) as u8,
+                    (g as u32 * color.g as u32 / 255) as u8,
+                    (b as u32 * color.b as u32 / 255) as u8,
+                    (a as u32 * color.a as u32 / 255) as u8,
+                ));
+
+                let pixel = Rect::new(x + (image_x * style.scale) as i32,
+                                      y + (image_y * style.scale) as i32,
+                                      style.scale,
+                                      style.scale);
+                canvas.fill_rect(pixel).unwrap();
+            }
+        }
+    }
+}
+
+/// Splits a descriptor line on whitespace, except for whitespace inside quoted values
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for character in line.chars() {
+        match character {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(character);
+            }
+
+            x if x.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+
+            x => current.push(x),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+fn attribute<T: std::str::FromStr>(attributes: &HashMap<String, String>, name: &str) -> io::Result<T> {
+    attributes.get(name)
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| invalid_data(format!("Missing or invalid value for '{}'", name)))
+}
@@ -1,28 +1,43 @@
+use std::time::Instant;
 use sdl2::rect::{Rect, Point};
-use sdl2::render::WindowCanvas;
+use sdl2::render::{Canvas, RenderTarget, BlendMode};
 use sdl2::pixels::Color;
 use crate::game::GameState;
-use crate::{SCREEN_HEIGHT, SCREEN_WIDTH, BRICK_WALL_SECTION, WOOD_WALL_SECTION, BLUE_WALL_SECTION};
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::textures::{self, BRICK_WALL_SECTION};
 use crate::rendering::{shoot_ray, FOV_DEGREES};
 use crate::rendering::atlas::Atlas;
+use crate::rendering::sprites::render_sprites;
+use crate::rendering::performance::RenderTimings;
 use crate::game::map::CellType;
+use crate::game::triggers::MAX_LIGHT_LEVEL;
 
-pub fn render_game_view(canvas: &mut WindowCanvas, game_state: &GameState, wall_atlas: &Atlas) {
+pub fn render_game_view<T: RenderTarget>(canvas: &mut Canvas<T>,
+                                         game_state: &GameState,
+                                         wall_atlas: &Atlas,
+                                         sprite_atlas: &Atlas,
+                                         timings: &mut RenderTimings) {
+    let started_at = Instant::now();
     canvas.set_draw_color(Color::GRAY);
     canvas.clear();
 
     canvas.set_draw_color(Color::WHITE);
     canvas.fill_rect(Rect::new(0, SCREEN_HEIGHT as i32 / 2, SCREEN_WIDTH, SCREEN_HEIGHT / 2)).unwrap();
+    render_floor_and_ceiling(canvas, game_state, wall_atlas);
 
     let first_ray_at = game_state.player.facing - FOV_DEGREES.to_radians() / 2.0;
-    let ray_count = canvas.window().size().0;
+    let ray_count = canvas.output_size().unwrap().0;
     let radians_per_ray = FOV_DEGREES.to_radians() / ray_count as f32;
 
     let (section_width, section_height) = wall_atlas.get_section_width_and_height(BRICK_WALL_SECTION).unwrap();
+    let mut depth_buffer = vec![f32::INFINITY; ray_count as usize];
 
     for x in 0..ray_count {
         let angle = first_ray_at + (radians_per_ray * x as f32);
+        let ray_started_at = Instant::now();
         let ray = shoot_ray(game_state, angle);
+        timings.raycasting += ray_started_at.elapsed();
+        timings.rays_cast += 1;
 
         let mut distance = ray.distance;
         if distance <= 0.0 {
@@ -36,6 +51,10 @@ pub fn render_game_view(canvas: &mut WindowCanvas, game_state: &GameState, wall_
             adjusted_distance = 1.0;
         }
 
+        if ray.distance > 0.0 {
+            depth_buffer[x as usize] = adjusted_distance;
+        }
+
         let mut height = SCREEN_HEIGHT as f32 / (adjusted_distance / 1.5);
         if height > SCREEN_HEIGHT as f32 {
             height = SCREEN_HEIGHT as f32;
@@ -46,17 +65,86 @@ pub fn render_game_view(canvas: &mut WindowCanvas, game_state: &GameState, wall_
             let image_x = (ray.units_from_cell_start / game_state.map.units_per_cell as f32) * section_width as f32;
             let image_y = ((y - start_y as u32) as f32 / height) * section_height as f32;
 
-            let section_name = match ray.cell_type {
-                CellType::BrickWall => BRICK_WALL_SECTION,
-                CellType::WoodWall => WOOD_WALL_SECTION,
-                CellType::BlueWall => BLUE_WALL_SECTION,
-                x => panic!("Can't handle cell type {:?}", x),
-            };
-
+            let section_name = wall_section_name(ray.cell_type);
             let rgb = wall_atlas.get_rgb_at(section_name, image_x as u32, image_y as u32).unwrap();
 
             canvas.set_draw_color(Color::RGB(rgb.0, rgb.1, rgb.2));
             canvas.draw_point(Point::new(x as i32, y as i32)).unwrap();
         }
     }
-}
\ No newline at end of file
+
+    render_sprites(canvas, game_state, sprite_atlas, &depth_buffer);
+    render_light_level(canvas, game_state);
+
+    // Everything other than casting rays is spent drawing textures
+    timings.texturing += started_at.elapsed() - timings.raycasting;
+}
+
+/// Darkens the whole view when the light level is below full brightness
+fn render_light_level<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState) {
+    if game_state.light_level >= MAX_LIGHT_LEVEL {
+        return;
+    }
+
+    let darkness = 255 * (MAX_LIGHT_LEVEL - game_state.light_level) / MAX_LIGHT_LEVEL;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, darkness as u8));
+    canvas.fill_rect(Rect::new(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT)).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+}
+
+/// Draws the floor and ceiling with the wall textures the map asks for.  Either one without a
+/// texture is left as the flat color already drawn.
+fn render_floor_and_ceiling<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, wall_atlas: &Atlas) {
+    let floor_section = game_state.map.floor_texture.map(wall_section_name);
+    let ceiling_section = game_state.map.ceiling_texture.map(wall_section_name);
+    if floor_section.is_none() && ceiling_section.is_none() {
+        return;
+    }
+
+    let player = &game_state.player;
+    let units_per_cell = game_state.map.units_per_cell as f32;
+    let first_ray_at = player.facing - FOV_DEGREES.to_radians() / 2.0;
+    let radians_per_ray = FOV_DEGREES.to_radians() / SCREEN_WIDTH as f32;
+
+    // How far each column's ray travels for every unit of distance straight ahead, which undoes
+    // the fish-eye correction the same way walls do
+    let column_steps = (0..SCREEN_WIDTH)
+        .map(|x| {
+            let angle = first_ray_at + (radians_per_ray * x as f32);
+            let correction = (player.facing - angle).0.cos();
+            (angle.0.cos() / correction, angle.0.sin() / correction)
+        })
+        .collect::<Vec<_>>();
+
+    for row in 0..SCREEN_HEIGHT / 2 {
+        // Walls are drawn 1.5 * SCREEN_HEIGHT / distance high, so the floor at their base is this
+        // far away, and the ceiling mirrors it
+        let rows_from_horizon = SCREEN_HEIGHT as f32 / 2.0 - row as f32 - 0.5;
+        let distance = 0.75 * SCREEN_HEIGHT as f32 / rows_from_horizon;
+        for (x, (step_x, step_y)) in column_steps.iter().enumerate() {
+            let position_x = (player.position.x + step_x * distance).rem_euclid(units_per_cell) / units_per_cell;
+            let position_y = (player.position.y + step_y * distance).rem_euclid(units_per_cell) / units_per_cell;
+            let sections = [(ceiling_section, row), (floor_section, SCREEN_HEIGHT - 1 - row)];
+            for (section, y) in sections.iter() {
+                if let Some(section) = section {
+                    let (section_width, section_height) = wall_atlas.get_section_width_and_height(section).unwrap();
+                    let image_x = ((position_x * section_width as f32) as u32).min(section_width - 1);
+                    let image_y = ((position_y * section_height as f32) as u32).min(section_height - 1);
+                    let (r, g, b) = wall_atlas.get_rgb_at(section, image_x, image_y).unwrap();
+
+                    canvas.set_draw_color(Color::RGB(r, g, b));
+                    canvas.draw_point(Point::new(x as i32, *y as i32)).unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// The wall atlas section a cell is textured with
+pub fn wall_section_name(cell_type: CellType) -> &'static str {
+    match textures::wall_section_name(cell_type) {
+        Some(x) => x,
+        None => panic!("Can't handle cell type {:?}", cell_type),
+    }
+}
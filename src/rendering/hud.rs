@@ -0,0 +1,210 @@
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, BlendMode};
+use sdl2::pixels::Color;
+use crate::game::GameState;
+use crate::game::levels::{LevelStats, CONTINUE_DELAY_SECONDS};
+use crate::game::items::KeyColor;
+use crate::game::weapons::WeaponKind;
+use crate::{HUD_STATUS_BAR_SECTION, HUD_DIGIT_SECTIONS, HUD_HEALTH_SECTION, HUD_AMMO_SECTION, HUD_TREASURE_SECTION};
+use crate::{HUD_GOLD_KEY_SECTION, HUD_SILVER_KEY_SECTION, HUD_CROSSHAIR_SECTION};
+use crate::rendering::atlas::Atlas;
+use crate::rendering::draw_section;
+use crate::rendering::font::{Font, TextStyle, Alignment};
+use crate::rendering::weapon::render_weapon;
+
+const DAMAGE_FLASH_SECONDS: f32 = 0.3;
+const PICKUP_FLASH_SECONDS: f32 = 0.3;
+
+/// The status bar is this fraction of the screen's height
+const STATUS_BAR_HEIGHT_DIVISOR: u32 = 8;
+
+/// The crosshair is this fraction of the screen's height
+const CROSSHAIR_SIZE_DIVISOR: u32 = 40;
+
+/// Text is scaled up by one for every this many pixels of screen height
+const TEXT_SCALE_DIVISOR: u32 = 200;
+
+/// Draws everything that sits on top of the game view: the weapon, crosshair, status bar and
+/// screen flashes.  Everything is sized relative to the render resolution.
+pub fn render_hud<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, sprite_atlas: &Atlas, hud_atlas: &Atlas, font: &Font) {
+    let (width, height) = canvas.output_size().unwrap();
+    let text_scale = (height / TEXT_SCALE_DIVISOR).max(1);
+    let view = view_area(width, height);
+
+    render_weapon(canvas, game_state, sprite_atlas, view);
+    if game_state.player.is_alive() {
+        // Shots go straight down the middle of the screen, not the middle of the view
+        let size = (height / CROSSHAIR_SIZE_DIVISOR).max(8);
+        let crosshair = Rect::new((width - size) as i32 / 2, (height - size) as i32 / 2, size, size);
+        draw_section(canvas, hud_atlas, HUD_CROSSHAIR_SECTION, crosshair);
+    }
+
+    render_flashes(canvas, game_state, view);
+    if !game_state.player.is_alive() {
+        let style = TextStyle::new(Color::WHITE, text_scale, Alignment::Center);
+        let message_area = Rect::new(view.width() as i32 / 4,
+                                     view.height() as i32 / 3,
+                                     view.width() / 2,
+                                     view.height() / 3);
+
+        font.draw_wrapped(canvas, "You died. Press fire to restart the level.", message_area, &style);
+    }
+
+    if let Some(message) = &game_state.message {
+        let style = TextStyle::new(Color::RGB(240, 220, 120), text_scale, Alignment::Center);
+        let line_height = font.line_height(text_scale);
+        let message_area = Rect::new(view.width() as i32 / 8, line_height as i32, view.width() * 3 / 4, line_height * 4);
+        font.draw_wrapped(canvas, message, message_area, &style);
+    }
+
+    if let Some(stats) = &game_state.completed {
+        render_level_complete(canvas, game_state, stats, font, text_scale, view);
+    }
+
+    let bar = Rect::new(0, view.bottom(), width, height - view.height());
+    render_status_bar(canvas, game_state, hud_atlas, font, text_scale, bar);
+}
+
+/// The part of the screen above the status bar, where the game world is drawn
+pub fn view_area(width: u32, height: u32) -> Rect {
+    Rect::new(0, 0, width, height - height / STATUS_BAR_HEIGHT_DIVISOR)
+}
+
+fn render_status_bar<T: RenderTarget>(canvas: &mut Canvas<T>,
+                                      game_state: &GameState,
+                                      hud_atlas: &Atlas,
+                                      font: &Font,
+                                      text_scale: u32,
+                                      bar: Rect) {
+    draw_section(canvas, hud_atlas, HUD_STATUS_BAR_SECTION, bar);
+
+    let icon_size = bar.height() * 3 / 4;
+    let padding = ((bar.height() - icon_size) / 2) as i32;
+    let top = bar.y() + padding;
+    let player = &game_state.player;
+
+    let mut x = bar.x() + padding * 2;
+    let counters = [
+        (HUD_HEALTH_SECTION, player.health.max(0) as u32),
+        (HUD_AMMO_SECTION, player.ammo),
+        (HUD_TREASURE_SECTION, player.inventory.treasure),
+    ];
+
+    for (icon, value) in &counters {
+        draw_section(canvas, hud_atlas, icon, Rect::new(x, top, icon_size, icon_size));
+        x += icon_size as i32 + padding;
+        x = draw_number(canvas, hud_atlas, *value, x, top, icon_size) + padding * 4;
+    }
+
+    let text_y = bar.y() + (bar.height() - font.line_height(text_scale)) as i32 / 2;
+    let weapon_style = TextStyle::new(Color::RGB(240, 220, 120), text_scale, Alignment::Left);
+    font.draw_text(canvas, &weapon_name(player.weapon), x, text_y, &weapon_style);
+
+    // Keys are lined up against the right hand side of the bar
+    let mut key_x = bar.right() - padding * 2;
+    for key in player.inventory.keys.iter().rev() {
+        let section = match key {
+            KeyColor::Gold => HUD_GOLD_KEY_SECTION,
+            KeyColor::Silver => HUD_SILVER_KEY_SECTION,
+        };
+
+        key_x -= icon_size as i32;
+        draw_section(canvas, hud_atlas, section, Rect::new(key_x, top, icon_size, icon_size));
+        key_x -= padding;
+    }
+
+    let level_style = TextStyle::new(Color::RGB(180, 180, 200), text_scale, Alignment::Right);
+    let level_name = match game_state.levels.len() {
+        1 => game_state.map.id.clone(),
+        count => format!("Level {}/{}: {}", game_state.level + 1, count, game_state.map.id),
+    };
+
+    font.draw_text(canvas, &level_name, key_x - padding * 2, text_y, &level_style);
+}
+
+/// Darkens the view and lists how the player did on the level they just finished
+fn render_level_complete<T: RenderTarget>(canvas: &mut Canvas<T>,
+                                          game_state: &GameState,
+                                          stats: &LevelStats,
+                                          font: &Font,
+                                          text_scale: u32,
+                                          view: Rect) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 200));
+    canvas.fill_rect(view).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    let (title, prompt) = if game_state.has_next_level() {
+        ("Level complete", "Press fire to continue")
+    } else {
+        ("Episode complete", "Press escape to quit")
+    };
+
+    let seconds = stats.seconds as u32;
+    let mut lines = vec![
+        title.to_owned(),
+        String::new(),
+        format!("Time {}:{:02}", seconds / 60, seconds % 60),
+        format!("Kills {}/{}", stats.kills, stats.total_kills),
+        format!("Secrets {}/{}", stats.secrets_found, stats.total_secrets),
+        String::new(),
+    ];
+
+    if game_state.seconds_since_completed >= CONTINUE_DELAY_SECONDS {
+        lines.push(prompt.to_owned());
+    }
+
+    let style = TextStyle::new(Color::WHITE, text_scale * 2, Alignment::Center);
+    let line_height = font.line_height(text_scale * 2) as i32;
+    let mut y = view.center().y() - line_height * lines.len() as i32 / 2;
+    for line in &lines {
+        font.draw_text(canvas, line, view.center().x(), y, &style);
+        y += line_height;
+    }
+}
+
+/// Turns the weapon's name into something readable, such as "Machine gun"
+fn weapon_name(weapon: WeaponKind) -> String {
+    let name = weapon.name().replace('_', " ");
+    let mut characters = name.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => name,
+    }
+}
+
+/// Draws the number with digits half as wide as they are high, returning where the last digit ends
+fn draw_number<T: RenderTarget>(canvas: &mut Canvas<T>, hud_atlas: &Atlas, value: u32, x: i32, y: i32, height: u32) -> i32 {
+    let digit_width = height / 2;
+    let mut x = x;
+    for digit in value.to_string().bytes() {
+        let section = HUD_DIGIT_SECTIONS[(digit - b'0') as usize];
+        draw_section(canvas, hud_atlas, section, Rect::new(x, y, digit_width, height));
+        x += digit_width as i32 + 1;
+    }
+
+    x
+}
+
+/// Tints the view red when the player is hurt or dead, and gold when they pick something up
+fn render_flashes<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, view: Rect) {
+    let player = &game_state.player;
+    let tint = if !player.is_alive() {
+        Some(Color::RGBA(160, 0, 0, 140))
+    } else if player.seconds_since_damaged < DAMAGE_FLASH_SECONDS {
+        let alpha = 120.0 * (1.0 - player.seconds_since_damaged / DAMAGE_FLASH_SECONDS);
+        Some(Color::RGBA(200, 0, 0, alpha as u8))
+    } else if player.seconds_since_pickup < PICKUP_FLASH_SECONDS {
+        let alpha = 80.0 * (1.0 - player.seconds_since_pickup / PICKUP_FLASH_SECONDS);
+        Some(Color::RGBA(255, 220, 80, alpha as u8))
+    } else {
+        None
+    };
+
+    if let Some(color) = tint {
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(color);
+        canvas.fill_rect(view).unwrap();
+        canvas.set_blend_mode(BlendMode::None);
+    }
+}
@@ -1,12 +1,26 @@
 use sdl2::rect::{Rect, Point};
 use sdl2::pixels::Color;
-use sdl2::render::WindowCanvas;
+use sdl2::render::{Canvas, RenderTarget, BlendMode};
 use crate::game::GameState;
 use crate::game::map::CellType;
-use crate::rendering::{shoot_ray, FOV_DEGREES};
+use crate::map_image;
+use crate::rendering::{shoot_ray, draw_section, fill_polygon, FOV_DEGREES};
+use crate::rendering::atlas::Atlas;
+use crate::rendering::game_view::wall_section_name;
+use crate::rendering::sprites::sprite_section_name;
 
-pub fn render_overhead_map(canvas: &mut WindowCanvas, game_state: &GameState) {
+/// How many rays make up the edge of the visible area drawn on the textured map
+const VIEW_POLYGON_RAY_COUNT: u32 = 120;
+
+/// Floors are drawn dark on the textured map so the view's polygon stands out against them
+const TEXTURED_FLOOR_COLOR: Color = Color::RGB(40, 40, 40);
+
+/// Draws the whole map from the top left of the screen.  Cells are flat colors unless the map is
+/// textured, in which case they are shrunk down copies of their wall textures and entities are
+/// drawn with their sprites.
+pub fn render_overhead_map<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, wall_atlas: &Atlas, sprite_atlas: &Atlas) {
     let zoom = game_state.map_zoom_level as f32;
+    let mut textures = Vec::new();
 
     for row in 0..game_state.map.height as i32 {
         for col in 0..game_state.map.width as i32 {
@@ -16,17 +30,56 @@ pub fn render_overhead_map(canvas: &mut WindowCanvas, game_state: &GameState) {
             let height = (game_state.map.units_per_cell as f32 * zoom) as u32;
 
             let rect = Rect::new(x1, y1, width, height);
-            let cell = game_state.map.cell_at(row as usize, col as usize);
-
+            let cell = game_state.map.cell_at(row as usize, col as usize).unwrap();
             match cell {
-                None => unreachable!(),
-                Some(CellType::BrickWall) => canvas.set_draw_color(Color::RED),
-                Some(CellType::BlueWall) => canvas.set_draw_color(Color::BLUE),
-                Some(CellType::WoodWall) => canvas.set_draw_color(Color::YELLOW),
-                Some(CellType::Empty) => canvas.set_draw_color(Color::WHITE)
+                CellType::Empty if game_state.textured_map => {
+                    canvas.set_draw_color(TEXTURED_FLOOR_COLOR);
+                    canvas.fill_rect(rect).unwrap();
+                }
+
+                _ if game_state.textured_map => draw_cell_texture(canvas, wall_atlas, &mut textures, cell, rect),
+                _ => {
+                    canvas.set_draw_color(cell_color(cell));
+                    canvas.fill_rect(rect).unwrap();
+                }
             }
+        }
+    }
+
+    canvas.set_draw_color(Color::MAGENTA);
+    for entity in game_state.entities.iter() {
+        if game_state.textured_map {
+            // Sprites only fill part of their image, so icons are drawn a whole cell wide
+            let icon_size = game_state.map.units_per_cell as f32 * zoom;
+            let center = Point::new((entity.position.x * zoom) as i32, (entity.position.y * zoom) as i32);
+            let icon = Rect::from_center(center, icon_size as u32, icon_size as u32);
+            draw_section(canvas, sprite_atlas, sprite_section_name(entity.sprite), icon);
+            continue;
+        }
 
-            canvas.fill_rect(rect).unwrap();
+        let entity_size = entity.collision_size as f32 * zoom;
+        let x1 = entity.position.x * zoom - (entity_size / 2.0);
+        let y1 = entity.position.y * zoom - (entity_size / 2.0);
+
+        canvas.fill_rect(Rect::new(x1 as i32, y1 as i32, entity_size as u32, entity_size as u32)).unwrap();
+    }
+
+    if game_state.display_paths {
+        canvas.set_draw_color(Color::CYAN);
+        let half_cell = game_state.map.units_per_cell as f32 / 2.0;
+        for entity in game_state.entities.iter() {
+            let points = entity.path
+                .iter()
+                .map(|cell| {
+                    let x = (cell.col as f32 * game_state.map.units_per_cell as f32 + half_cell) * zoom;
+                    let y = (cell.row as f32 * game_state.map.units_per_cell as f32 + half_cell) * zoom;
+                    Point::new(x as i32, y as i32)
+                })
+                .collect::<Vec<_>>();
+
+            if points.len() > 1 {
+                canvas.draw_lines(points.as_slice()).unwrap();
+            }
         }
     }
 
@@ -41,14 +94,22 @@ pub fn render_overhead_map(canvas: &mut WindowCanvas, game_state: &GameState) {
 
         let rect = Rect::new(x1 as i32, y1 as i32, player_size as u32, player_size as u32);
 
+        if game_state.textured_map {
+            // The player is drawn on top of the shaded view
+            render_view_polygon(canvas, game_state, pos_x, pos_y, zoom);
+        }
+
         canvas.set_draw_color(Color::GREEN);
         canvas.fill_rect(rect).unwrap();
+        if game_state.textured_map {
+            return;
+        }
 
         // Show rays for all pixel columns
         canvas.set_draw_color(Color::BLACK);
 
         let first_ray_at = game_state.player.facing - FOV_DEGREES.to_radians() / 2.0;
-        let ray_count = canvas.window().size().0;
+        let ray_count = canvas.output_size().unwrap().0;
         let radians_per_ray = FOV_DEGREES.to_radians() / ray_count as f32;
         for x in 0..ray_count {
             let angle = first_ray_at + (radians_per_ray * x as f32);
@@ -61,4 +122,82 @@ pub fn render_overhead_map(canvas: &mut WindowCanvas, game_state: &GameState) {
                              Point::new(line_end_x as i32, line_end_y as i32)).unwrap();
         }
     }
-}
\ No newline at end of file
+}
+
+/// Shades the area the player can see as a single translucent polygon running along the walls
+fn render_view_polygon<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, pos_x: f32, pos_y: f32, zoom: f32) {
+    let first_ray_at = game_state.player.facing - FOV_DEGREES.to_radians() / 2.0;
+    let radians_per_ray = FOV_DEGREES.to_radians() / VIEW_POLYGON_RAY_COUNT as f32;
+    let mut points = vec![Point::new(pos_x as i32, pos_y as i32)];
+    for index in 0..=VIEW_POLYGON_RAY_COUNT {
+        let angle = first_ray_at + (radians_per_ray * index as f32);
+        let ray = shoot_ray(game_state, angle);
+        points.push(Point::new(((angle.0.cos() * ray.distance * zoom) + pos_x) as i32,
+                               ((angle.0.sin() * ray.distance * zoom) + pos_y) as i32));
+    }
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(255, 255, 150, 90));
+    fill_polygon(canvas, &points);
+    canvas.set_blend_mode(BlendMode::None);
+}
+
+/// Draws a wall texture shrunk down to fill the cell.  Each cell type is only shrunk once per
+/// frame, with the results kept in `textures`.
+fn draw_cell_texture<T: RenderTarget>(canvas: &mut Canvas<T>,
+                                      wall_atlas: &Atlas,
+                                      textures: &mut Vec<(CellType, Vec<Color>)>,
+                                      cell: CellType,
+                                      rect: Rect) {
+    let index = match textures.iter().position(|(x, _)| *x == cell) {
+        Some(x) => x,
+        None => {
+            textures.push((cell, downscale_section(wall_atlas, wall_section_name(cell), rect.width(), rect.height())));
+            textures.len() - 1
+        }
+    };
+
+    let pixels = &textures[index].1;
+    for y in 0..rect.height() {
+        for x in 0..rect.width() {
+            canvas.set_draw_color(pixels[(y * rect.width() + x) as usize]);
+            canvas.draw_point(Point::new(rect.x() + x as i32, rect.y() + y as i32)).unwrap();
+        }
+    }
+}
+
+/// Shrinks an atlas section to the given size, averaging every texel each pixel covers so the
+/// texture doesn't shimmer the way skipping texels would
+fn downscale_section(atlas: &Atlas, section_name: &str, width: u32, height: u32) -> Vec<Color> {
+    let (section_width, section_height) = atlas.get_section_width_and_height(section_name).unwrap();
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        let first_y = y * section_height / height;
+        let last_y = ((y + 1) * section_height / height).max(first_y + 1);
+        for x in 0..width {
+            let first_x = x * section_width / width;
+            let last_x = ((x + 1) * section_width / width).max(first_x + 1);
+
+            let mut totals = [0_u32; 3];
+            for image_y in first_y..last_y {
+                for image_x in first_x..last_x {
+                    let (r, g, b) = atlas.get_rgb_at(section_name, image_x, image_y).unwrap();
+                    totals[0] += r as u32;
+                    totals[1] += g as u32;
+                    totals[2] += b as u32;
+                }
+            }
+
+            let count = (last_y - first_y) * (last_x - first_x);
+            pixels.push(Color::RGB((totals[0] / count) as u8, (totals[1] / count) as u8, (totals[2] / count) as u8));
+        }
+    }
+
+    pixels
+}
+
+/// The flat color a cell is drawn with on the overhead maps
+pub fn cell_color(cell: CellType) -> Color {
+    let [red, green, blue] = map_image::cell_color(cell);
+    Color::RGB(red, green, blue)
+}
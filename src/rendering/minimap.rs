@@ -0,0 +1,216 @@
+use std::f32::consts::PI;
+use sdl2::rect::{Rect, Point};
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, RenderTarget, BlendMode};
+use crate::core::vector::Vector;
+use crate::game::GameState;
+use crate::rendering::map::cell_color;
+use crate::rendering::hud::view_area;
+
+/// Map units are drawn this many pixels wide at the lowest zoom level
+const PIXELS_PER_UNIT: f32 = 3.0;
+
+/// Gap between the minimap's border and the edges of the view
+const MARGIN: i32 = 10;
+
+/// How opaque the map is drawn over the game view
+const MAP_ALPHA: u8 = 170;
+
+/// How far in front of the player the facing indicator reaches, in map units
+const FACING_LINE_LENGTH: f32 = 4.0;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Corner { TopLeft, TopRight, BottomLeft, BottomRight }
+
+#[derive(Copy, Clone)]
+pub struct MinimapOptions {
+    pub corner: Corner,
+
+    /// Width and height of the minimap in pixels, before it is shrunk to fit the view
+    pub size: u32,
+}
+
+impl Corner {
+    /// Parses `top-left`, `top-right`, `bottom-left` or `bottom-right`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "top-left" => Some(Corner::TopLeft),
+            "top-right" => Some(Corner::TopRight),
+            "bottom-left" => Some(Corner::BottomLeft),
+            "bottom-right" => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+impl Default for MinimapOptions {
+    fn default() -> Self {
+        MinimapOptions {
+            corner: Corner::TopRight,
+            size: 180,
+        }
+    }
+}
+
+/// Draws a small map centered on the player in a corner of the game view.  When the map is
+/// rotated the player always faces the top of the minimap.
+pub fn render_minimap<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, options: &MinimapOptions) {
+    let (width, height) = canvas.output_size().unwrap();
+    let view = view_area(width, height);
+    let size = options.size.min(view.width() - MARGIN as u32 * 2).min(view.height() - MARGIN as u32 * 2);
+    let x = match options.corner {
+        Corner::TopLeft | Corner::BottomLeft => view.left() + MARGIN,
+        Corner::TopRight | Corner::BottomRight => view.right() - MARGIN - size as i32,
+    };
+
+    let y = match options.corner {
+        Corner::TopLeft | Corner::TopRight => view.top() + MARGIN,
+        Corner::BottomLeft | Corner::BottomRight => view.bottom() - MARGIN - size as i32,
+    };
+
+    let area = Rect::new(x, y, size, size);
+    let transform = MinimapTransform::new(game_state, area);
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, MAP_ALPHA));
+    canvas.fill_rect(area).unwrap();
+
+    // Every pixel is looked up in the map, since cells aren't axis aligned once the map rotates.
+    // Pixels are batched by color so each color only needs one draw call.
+    let units_per_cell = game_state.map.units_per_cell as f32;
+    let mut batches: Vec<(Color, Vec<Point>)> = Vec::new();
+    for pixel_y in area.top()..area.bottom() {
+        for pixel_x in area.left()..area.right() {
+            let position = transform.to_world(pixel_x as f32 + 0.5, pixel_y as f32 + 0.5);
+            if position.x < 0.0 || position.y < 0.0 {
+                continue;
+            }
+
+            let row = (position.y / units_per_cell) as usize;
+            let col = (position.x / units_per_cell) as usize;
+            let color = match game_state.map.cell_at(row, col) {
+                Some(cell) => cell_color(cell),
+                None => continue,
+            };
+
+            let point = Point::new(pixel_x, pixel_y);
+            match batches.iter_mut().find(|(x, _)| *x == color) {
+                Some((_, points)) => points.push(point),
+                None => batches.push((color, vec![point])),
+            }
+        }
+    }
+
+    for (color, points) in batches {
+        canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, MAP_ALPHA));
+        canvas.draw_points(points.as_slice()).unwrap();
+    }
+
+    canvas.set_clip_rect(Some(area));
+    canvas.set_draw_color(Color::RGBA(255, 0, 255, MAP_ALPHA));
+    for entity in game_state.entities.iter() {
+        let entity_size = (entity.collision_size as f32 * transform.scale).max(2.0);
+        let center = transform.to_screen(entity.position);
+        let rect = Rect::new((center.x - entity_size / 2.0) as i32,
+                             (center.y - entity_size / 2.0) as i32,
+                             entity_size as u32,
+                             entity_size as u32);
+
+        canvas.fill_rect(rect).unwrap();
+    }
+
+    if game_state.display_paths {
+        canvas.set_draw_color(Color::RGBA(0, 255, 255, MAP_ALPHA));
+        let half_cell = units_per_cell / 2.0;
+        for entity in game_state.entities.iter() {
+            let points = entity.path
+                .iter()
+                .map(|cell| {
+                    let center = Vector {
+                        x: cell.col as f32 * units_per_cell + half_cell,
+                        y: cell.row as f32 * units_per_cell + half_cell,
+                    };
+
+                    let screen = transform.to_screen(center);
+                    Point::new(screen.x as i32, screen.y as i32)
+                })
+                .collect::<Vec<_>>();
+
+            if points.len() > 1 {
+                canvas.draw_lines(points.as_slice()).unwrap();
+            }
+        }
+    }
+
+    {
+        let player = &game_state.player;
+        let player_size = (player.collision_size as f32 * transform.scale).max(2.0);
+        let center = transform.to_screen(player.position);
+        let facing = Vector { x: player.facing.0.cos(), y: player.facing.0.sin() };
+        let facing_end = transform.to_screen(player.position + facing * FACING_LINE_LENGTH);
+
+        canvas.set_draw_color(Color::GREEN);
+        canvas.fill_rect(Rect::new((center.x - player_size / 2.0) as i32,
+                                   (center.y - player_size / 2.0) as i32,
+                                   player_size as u32,
+                                   player_size as u32)).unwrap();
+
+        canvas.draw_line(Point::new(center.x as i32, center.y as i32),
+                         Point::new(facing_end.x as i32, facing_end.y as i32)).unwrap();
+    }
+
+    canvas.set_clip_rect(None);
+    canvas.set_blend_mode(BlendMode::None);
+
+    let border = Rect::new(area.x() - 1, area.y() - 1, area.width() + 2, area.height() + 2);
+    canvas.set_draw_color(Color::RGB(200, 200, 200));
+    canvas.draw_rect(border).unwrap();
+}
+
+/// Converts between map positions and pixels on the minimap
+struct MinimapTransform {
+    /// The map position drawn in the middle of the minimap
+    center: Vector,
+    screen_center: Vector,
+    scale: f32,
+
+    /// Cosine and sine of the angle the map is rotated by
+    cos: f32,
+    sin: f32,
+}
+
+impl MinimapTransform {
+    fn new(game_state: &GameState, area: Rect) -> Self {
+        // Rotating by a quarter turn past the player's facing puts their facing at the top
+        let angle = if game_state.rotate_minimap {
+            game_state.player.facing.0 + PI / 2.0
+        } else {
+            0.0
+        };
+
+        let center = area.center();
+        MinimapTransform {
+            center: game_state.player.position,
+            screen_center: Vector { x: center.x() as f32, y: center.y() as f32 },
+            scale: game_state.map_zoom_level as f32 * PIXELS_PER_UNIT,
+            cos: angle.cos(),
+            sin: angle.sin(),
+        }
+    }
+
+    fn to_world(&self, x: f32, y: f32) -> Vector {
+        let offset = Vector { x: x - self.screen_center.x, y: y - self.screen_center.y } * (1.0 / self.scale);
+        Vector {
+            x: self.center.x + offset.x * self.cos - offset.y * self.sin,
+            y: self.center.y + offset.x * self.sin + offset.y * self.cos,
+        }
+    }
+
+    fn to_screen(&self, position: Vector) -> Vector {
+        let offset = position - self.center;
+        Vector {
+            x: self.screen_center.x + (offset.x * self.cos + offset.y * self.sin) * self.scale,
+            y: self.screen_center.y + (offset.y * self.cos - offset.x * self.sin) * self.scale,
+        }
+    }
+}
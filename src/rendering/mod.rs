@@ -1,122 +1,135 @@
 pub mod atlas;
 mod map;
+pub mod minimap;
+mod automap;
 mod game_view;
-
-use sdl2::render::WindowCanvas;
-use sdl2::pixels::Color;
-use crate::game::GameState;
+mod sprites;
+mod weapon;
+mod hud;
+pub mod font;
+pub mod performance;
+pub mod editor;
+
+use std::time::Instant;
+use sdl2::rect::{Rect, Point};
+use sdl2::render::{Canvas, RenderTarget, WindowCanvas};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use crate::game::{GameState, MapView, FOV_DEGREES};
 use crate::core::radians::Radians;
-use crate::game::map::CellType;
-use crate::core::vector::Vector;
-use crate::core::degrees::Degrees;
+use crate::game::raycast::{cast_ray, RayResult};
 use crate::rendering::atlas::Atlas;
+use crate::rendering::font::Font;
+use crate::rendering::performance::{PerformanceStats, RenderTimings, render_performance_overlay};
+use crate::rendering::minimap::{MinimapOptions, render_minimap};
+use crate::map_image::save_png;
 use map::render_overhead_map;
+use automap::render_automap;
 use game_view::render_game_view;
+use hud::render_hud;
+
+/// Everything loaded from disk that is needed to draw a frame
+pub struct Assets {
+    pub wall_atlas: Atlas,
+    pub sprite_atlas: Atlas,
+    pub hud_atlas: Atlas,
+    pub font: Font,
+}
 
-pub fn render(canvas: &mut WindowCanvas, game_state: &GameState, wall_atlas: &Atlas) {
+/// Draws the frame without showing it, so it can be saved before it is presented
+pub fn render<T: RenderTarget>(canvas: &mut Canvas<T>,
+                               game_state: &GameState,
+                               assets: &Assets,
+                               minimap_options: &MinimapOptions,
+                               performance: &PerformanceStats) -> RenderTimings {
+    let mut timings = RenderTimings::default();
     canvas.set_draw_color(Color::BLACK);
     canvas.clear();
 
-    render_game_view(canvas, game_state, wall_atlas);
+    render_game_view(canvas, game_state, &assets.wall_atlas, &assets.sprite_atlas, &mut timings);
+    render_hud(canvas, game_state, &assets.sprite_atlas, &assets.hud_atlas, &assets.font);
     if game_state.display_map {
-        render_overhead_map(canvas, game_state);
+        match game_state.map_view {
+            MapView::Overview => render_overhead_map(canvas, game_state, &assets.wall_atlas, &assets.sprite_atlas),
+            MapView::Minimap => render_minimap(canvas, game_state, minimap_options),
+            MapView::Automap => render_automap(canvas, game_state),
+        }
     }
 
-    canvas.present();
+    if game_state.display_performance {
+        render_performance_overlay(canvas, performance, &assets.font);
+    }
+
+    timings
 }
 
-const FOV_DEGREES: Degrees = Degrees(90.0);
+/// Shows the rendered frame in the window
+pub fn present(canvas: &mut WindowCanvas, timings: &mut RenderTimings) {
+    let present_started_at = Instant::now();
+    canvas.present();
+    timings.present = present_started_at.elapsed();
+}
 
-struct RayResult {
-    distance: f32,
-    units_from_cell_start: f32,
-    cell_type: CellType,
+/// Saves what has been drawn to a PNG file.  A window's canvas only keeps the frame until it is
+/// presented.
+pub fn save_screenshot<T: RenderTarget>(canvas: &Canvas<T>, path: &str) -> Result<(), String> {
+    let (width, height) = canvas.output_size()?;
+    let pixels = canvas.read_pixels(None, PixelFormatEnum::RGB24)?;
+    save_png(path, width, height, &pixels).map_err(|err| err.to_string())
 }
 
 fn shoot_ray(game_state: &GameState, angle: Radians) -> RayResult {
-    // If the player is off the map in the negative region, don't shoot the array
-    if game_state.player.position.x < 0.0 || game_state.player.position.y < 0.0 {
-        return RayResult {distance: 0.0, units_from_cell_start: 0.0, cell_type: CellType::Empty};
-    }
-
-    let rise = angle.0.sin();
-    let run = angle.0.cos();
-    let slope = rise / run;
-
-    // compute y intercept for full y=mx+b equation
-    let y_offset = (-slope * game_state.player.position.x) + game_state.player.position.y;
-
-    let y_adjustment = if rise < 0.0 { 0 } else { game_state.map.units_per_cell };
-    let x_adjustment = if run < 0.0 { 0 } else { game_state.map.units_per_cell };
-    let current_row = game_state.player.position.y as u32 / game_state.map.units_per_cell;
-    let current_col = game_state.player.position.x as u32 / game_state.map.units_per_cell;
-
-    let mut y_value = (current_row * game_state.map.units_per_cell + y_adjustment) as f32;
-    let mut x_value = (current_col * game_state.map.units_per_cell + x_adjustment) as f32;
+    cast_ray(&game_state.map, game_state.player.position, angle)
+}
 
-    loop {
-        let horizontally_out_of_map = x_value < 0.0 || x_value > game_state.map.width as f32 * game_state.map.units_per_cell as f32;
-        let vertically_out_of_map = y_value < 0.0 || y_value > game_state.map.height as f32 * game_state.map.units_per_cell as f32;
+/// Draws an atlas section stretched to fill the destination, skipping transparent pixels
+fn draw_section<T: RenderTarget>(canvas: &mut Canvas<T>, atlas: &Atlas, section_name: &str, destination: Rect) {
+    let (section_width, section_height) = atlas.get_section_width_and_height(section_name).unwrap();
+    for x in 0..destination.width() {
+        let image_x = (x * section_width / destination.width()).min(section_width - 1);
+        for y in 0..destination.height() {
+            let image_y = (y * section_height / destination.height()).min(section_height - 1);
+            let (r, g, b, a) = atlas.get_rgba_at(section_name, image_x, image_y).unwrap();
+            if a == 0 {
+                continue;
+            }
 
-        if horizontally_out_of_map && vertically_out_of_map {
-            return RayResult { distance: 0.0, units_from_cell_start: 0.0, cell_type: CellType::Empty };
+            canvas.set_draw_color(Color::RGB(r, g, b));
+            canvas.draw_point(Point::new(destination.x() + x as i32, destination.y() + y as i32)).unwrap();
         }
+    }
+}
 
-        let x_at_y_value_intercept = (y_value - y_offset) / slope;
-        let y_at_x_value_intercept = slope * x_value + y_offset;
-
-        let distance_to_y_value = calc_distance(&game_state.player.position, x_at_y_value_intercept, y_value);
-        let distance_to_x_value = calc_distance(&game_state.player.position, x_value, y_at_x_value_intercept);
-
-        if distance_to_y_value.is_finite() && distance_to_y_value < distance_to_x_value {
-            // since we are on the border of two cells we need to look up the next cell over, depending on if we are going up or down
-            let adjustment = if rise > 0.0 { 0.1 } else { -0.1 };
-            let adjusted_value = y_value + adjustment;
-
-            let row = adjusted_value as u32 / game_state.map.units_per_cell;
-            let col = x_at_y_value_intercept as u32 / game_state.map.units_per_cell;
+/// Fills the polygon using the even-odd rule.  Every pixel is drawn once, so translucent colors
+/// come out evenly.
+fn fill_polygon<T: RenderTarget>(canvas: &mut Canvas<T>, points: &[Point]) {
+    if points.len() < 3 {
+        return;
+    }
 
-            match get_ray_result(game_state, row as usize, col as usize, distance_to_y_value, x_at_y_value_intercept) {
-                Some(x) => return x,
-                _ => (),
+    let top = points.iter().map(|x| x.y()).min().unwrap();
+    let bottom = points.iter().map(|x| x.y()).max().unwrap();
+    let mut crossings = Vec::new();
+    for y in top..bottom {
+        // Edges are tested against the middle of the pixel row, so vertices never land exactly on it
+        let scan_y = y as f32 + 0.5;
+        crossings.clear();
+        for (index, start) in points.iter().enumerate() {
+            let end = points[(index + 1) % points.len()];
+            let (start_y, end_y) = (start.y() as f32, end.y() as f32);
+            if (start_y <= scan_y) != (end_y <= scan_y) {
+                let along = (scan_y - start_y) / (end_y - start_y);
+                crossings.push(start.x() as f32 + along * (end.x() - start.x()) as f32);
             }
+        }
 
-            // hit empty space
-            y_value += game_state.map.units_per_cell as f32 * if rise < 0.0 { -1.0 } else { 1.0 };
-        } else {
-            // since we are on the border of two cells we need to look up the next cell over, depending on if we are going right or left
-            let adjustment = if run > 0.0 { 0.1 } else { -0.1 };
-            let adjusted_value = x_value + adjustment;
-
-            let row = y_at_x_value_intercept as u32 / game_state.map.units_per_cell;
-            let col = adjusted_value as u32 / game_state.map.units_per_cell;
-
-            match get_ray_result(game_state, row as usize, col as usize, distance_to_x_value, y_at_x_value_intercept) {
-                Some(x) => return x,
-                _ => (),
+        crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        for span in crossings.chunks(2) {
+            if let [left, right] = span {
+                let (left, right) = (left.round() as i32, right.round() as i32);
+                if right > left {
+                    canvas.fill_rect(Rect::new(left, y, (right - left) as u32, 1)).unwrap();
+                }
             }
-
-            // hit empty space
-            x_value += game_state.map.units_per_cell as f32 * if run < 0.0 { -1.0 } else { 1.0 };
         }
     }
 }
-
-fn calc_distance(first: &Vector, second_x: f32, second_y: f32) -> f32 {
-    let a = (first.y - second_y).abs();
-    let b = (first.x - second_x).abs();
-
-    (a * a + b * b).sqrt()
-}
-
-fn get_ray_result(game_state: &GameState, row: usize, col: usize, distance: f32, position: f32) -> Option<RayResult> {
-    match game_state.map.cell_at(row, col) {
-        None => None,
-        Some(CellType::Empty) => None,
-        Some(cell_type) => Some(RayResult {
-            distance,
-            units_from_cell_start: position % game_state.map.units_per_cell as f32,
-            cell_type,
-        }),
-    }
-}
\ No newline at end of file
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, BlendMode};
+use sdl2::pixels::Color;
+use crate::rendering::font::{Font, TextStyle, Alignment};
+
+/// How many of the most recent frames the overlay's numbers are based on
+const SAMPLE_COUNT: usize = 120;
+
+/// Where the time went while rendering a single frame
+#[derive(Copy, Clone, Default)]
+pub struct RenderTimings {
+    pub raycasting: Duration,
+
+    /// Drawing textured walls and sprites
+    pub texturing: Duration,
+    pub present: Duration,
+    pub rays_cast: u32,
+}
+
+/// Keeps track of recent frames for the performance overlay
+pub struct PerformanceStats {
+    samples: VecDeque<(Duration, RenderTimings)>,
+    frame_count: u64,
+}
+
+impl PerformanceStats {
+    pub fn new() -> Self {
+        PerformanceStats {
+            samples: VecDeque::with_capacity(SAMPLE_COUNT),
+            frame_count: 0,
+        }
+    }
+
+    /// Records how long the whole frame took, and how long parts of rendering it took
+    pub fn record(&mut self, frame_time: Duration, timings: RenderTimings) {
+        if self.samples.len() == SAMPLE_COUNT {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back((frame_time, timings));
+        self.frame_count += 1;
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        self.average(|(frame_time, _)| *frame_time)
+    }
+
+    pub fn worst_frame_time(&self) -> Duration {
+        self.samples.iter().map(|(frame_time, _)| *frame_time).max().unwrap_or_default()
+    }
+
+    pub fn frames_per_second(&self) -> f32 {
+        let average = self.average_frame_time().as_secs_f32();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+
+    fn average<F: Fn(&(Duration, RenderTimings)) -> Duration>(&self, value: F) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::default();
+        }
+
+        self.samples.iter().map(value).sum::<Duration>() / self.samples.len() as u32
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let milliseconds = |x: Duration| x.as_secs_f32() * 1000.0;
+        let rays = match self.samples.back() {
+            Some((_, timings)) => timings.rays_cast,
+            None => 0,
+        };
+
+        vec![
+            format!("FPS: {:.1}", self.frames_per_second()),
+            format!("Frame: {:.2}ms avg, {:.2}ms worst", milliseconds(self.average_frame_time()), milliseconds(self.worst_frame_time())),
+            format!("Ray casting: {:.2}ms", milliseconds(self.average(|(_, x)| x.raycasting))),
+            format!("Texturing: {:.2}ms", milliseconds(self.average(|(_, x)| x.texturing))),
+            format!("Present: {:.2}ms", milliseconds(self.average(|(_, x)| x.present))),
+            format!("Rays per frame: {}", rays),
+            format!("Frames: {}", self.frame_count),
+        ]
+    }
+}
+
+/// Draws the performance numbers in the top right corner of the screen
+pub fn render_performance_overlay<T: RenderTarget>(canvas: &mut Canvas<T>, stats: &PerformanceStats, font: &Font) {
+    let (width, height) = canvas.output_size().unwrap();
+    let style = TextStyle::new(Color::RGB(120, 255, 120), (height / 300).max(1), Alignment::Right);
+    let lines = stats.lines();
+
+    let line_height = font.line_height(style.scale);
+    let text_width = lines.iter().map(|x| font.text_width(x, style.scale)).max().unwrap_or(0);
+    let margin = line_height as i32 / 2;
+    let background = Rect::new(width as i32 - text_width as i32 - margin * 3,
+                               margin,
+                               text_width + margin as u32 * 2,
+                               line_height * lines.len() as u32 + margin as u32 * 2);
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(background).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    let right = background.right() - margin;
+    for (index, line) in lines.iter().enumerate() {
+        let y = background.top() + margin + (index as u32 * line_height) as i32;
+        font.draw_text(canvas, line, right, y, &style);
+    }
+}
@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+use sdl2::rect::Point;
+use sdl2::render::{Canvas, RenderTarget};
+use sdl2::pixels::Color;
+use crate::game::GameState;
+use crate::game::entities::Sprite;
+use crate::{SCREEN_HEIGHT, BARREL_SPRITE_SECTION, PILLAR_SPRITE_SECTION, LAMP_SPRITE_SECTION, RAT_SPRITE_SECTION};
+use crate::{GUARD_STAND_SPRITE_SECTION, GUARD_WALK1_SPRITE_SECTION, GUARD_WALK2_SPRITE_SECTION, GUARD_AIM_SPRITE_SECTION};
+use crate::{GUARD_FIRE_SPRITE_SECTION, GUARD_PAIN_SPRITE_SECTION, GUARD_DYING_SPRITE_SECTION, GUARD_DEAD_SPRITE_SECTION};
+use crate::{MUTANT_STAND_SPRITE_SECTION, MUTANT_WALK1_SPRITE_SECTION, MUTANT_WALK2_SPRITE_SECTION, MUTANT_AIM_SPRITE_SECTION};
+use crate::{MUTANT_FIRE_SPRITE_SECTION, MUTANT_PAIN_SPRITE_SECTION, MUTANT_DYING_SPRITE_SECTION, MUTANT_DEAD_SPRITE_SECTION};
+use crate::{ROCKET_SPRITE_SECTION, FIREBALL_SPRITE_SECTION, EXPLOSION1_SPRITE_SECTION, EXPLOSION2_SPRITE_SECTION};
+use crate::{HEALTH_PACK_SPRITE_SECTION, AMMO_CLIP_SPRITE_SECTION, GOLD_KEY_SPRITE_SECTION, SILVER_KEY_SPRITE_SECTION};
+use crate::{TREASURE_SPRITE_SECTION, MACHINE_GUN_PICKUP_SPRITE_SECTION, ROCKET_LAUNCHER_PICKUP_SPRITE_SECTION};
+use crate::rendering::FOV_DEGREES;
+use crate::rendering::atlas::Atlas;
+
+/// Draws every entity as a camera facing sprite.  The depth buffer holds the distance to the wall
+/// drawn in each pixel column, so sprites behind walls are hidden.
+pub fn render_sprites<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, sprite_atlas: &Atlas, depth_buffer: &[f32]) {
+    let fov = FOV_DEGREES.to_radians().0;
+    let radians_per_column = fov / depth_buffer.len() as f32;
+    let player = &game_state.player;
+
+    // Draw the furthest sprites first so closer ones overlap them
+    let mut visible_entities = game_state.entities
+        .iter()
+        .map(|entity| {
+            (entity, (entity.position - player.position).length())
+        })
+        .collect::<Vec<_>>();
+
+    visible_entities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    for (entity, distance) in visible_entities {
+        let delta = entity.position - player.position;
+        let mut angle_from_facing = delta.y.atan2(delta.x) - player.facing.0;
+        if angle_from_facing > PI {
+            angle_from_facing -= 2.0 * PI;
+        } else if angle_from_facing < -PI {
+            angle_from_facing += 2.0 * PI;
+        }
+
+        // Adjust the distance to prevent fish-eye distortion, same as walls
+        let adjusted_distance = distance * angle_from_facing.cos();
+        if adjusted_distance < 0.5 {
+            continue;
+        }
+
+        let section_name = sprite_section_name(entity.sprite);
+        let (section_width, section_height) = sprite_atlas.get_section_width_and_height(section_name).unwrap();
+
+        let height = SCREEN_HEIGHT as f32 / (adjusted_distance / 1.5);
+        let width = (game_state.map.units_per_cell as f32 / adjusted_distance) / radians_per_column;
+        let center_x = (angle_from_facing + fov / 2.0) / radians_per_column;
+        let start_x = center_x - width / 2.0;
+        let start_y = SCREEN_HEIGHT as f32 / 2.0 - height / 2.0;
+
+        let first_column = start_x.max(0.0) as usize;
+        let last_column = (start_x + width).min(depth_buffer.len() as f32).max(0.0) as usize;
+        let first_row = start_y.max(0.0) as u32;
+        let last_row = (start_y + height).min(SCREEN_HEIGHT as f32) as u32;
+
+        for (x, wall_distance) in depth_buffer.iter().enumerate().take(last_column).skip(first_column) {
+            if *wall_distance <= adjusted_distance {
+                continue;
+            }
+
+            let image_x = (((x as f32 - start_x) / width) * section_width as f32) as u32;
+            for y in first_row..last_row {
+                let image_y = (((y as f32 - start_y) / height) * section_height as f32) as u32;
+                let (r, g, b, a) = sprite_atlas
+                    .get_rgba_at(section_name, image_x.min(section_width - 1), image_y.min(section_height - 1))
+                    .unwrap();
+
+                if a == 0 {
+                    continue;
+                }
+
+                canvas.set_draw_color(Color::RGB(r, g, b));
+                canvas.draw_point(Point::new(x as i32, y as i32)).unwrap();
+            }
+        }
+    }
+}
+
+pub fn sprite_section_name(sprite: Sprite) -> &'static str {
+    match sprite {
+        Sprite::Barrel => BARREL_SPRITE_SECTION,
+        Sprite::Pillar => PILLAR_SPRITE_SECTION,
+        Sprite::Lamp => LAMP_SPRITE_SECTION,
+        Sprite::Rat => RAT_SPRITE_SECTION,
+        Sprite::GuardStand => GUARD_STAND_SPRITE_SECTION,
+        Sprite::GuardWalk1 => GUARD_WALK1_SPRITE_SECTION,
+        Sprite::GuardWalk2 => GUARD_WALK2_SPRITE_SECTION,
+        Sprite::GuardAim => GUARD_AIM_SPRITE_SECTION,
+        Sprite::GuardFire => GUARD_FIRE_SPRITE_SECTION,
+        Sprite::GuardPain => GUARD_PAIN_SPRITE_SECTION,
+        Sprite::GuardDying => GUARD_DYING_SPRITE_SECTION,
+        Sprite::GuardDead => GUARD_DEAD_SPRITE_SECTION,
+        Sprite::MutantStand => MUTANT_STAND_SPRITE_SECTION,
+        Sprite::MutantWalk1 => MUTANT_WALK1_SPRITE_SECTION,
+        Sprite::MutantWalk2 => MUTANT_WALK2_SPRITE_SECTION,
+        Sprite::MutantAim => MUTANT_AIM_SPRITE_SECTION,
+        Sprite::MutantFire => MUTANT_FIRE_SPRITE_SECTION,
+        Sprite::MutantPain => MUTANT_PAIN_SPRITE_SECTION,
+        Sprite::MutantDying => MUTANT_DYING_SPRITE_SECTION,
+        Sprite::MutantDead => MUTANT_DEAD_SPRITE_SECTION,
+        Sprite::Rocket => ROCKET_SPRITE_SECTION,
+        Sprite::Fireball => FIREBALL_SPRITE_SECTION,
+        Sprite::Explosion1 => EXPLOSION1_SPRITE_SECTION,
+        Sprite::Explosion2 => EXPLOSION2_SPRITE_SECTION,
+        Sprite::HealthPack => HEALTH_PACK_SPRITE_SECTION,
+        Sprite::AmmoClip => AMMO_CLIP_SPRITE_SECTION,
+        Sprite::GoldKey => GOLD_KEY_SPRITE_SECTION,
+        Sprite::SilverKey => SILVER_KEY_SPRITE_SECTION,
+        Sprite::Treasure => TREASURE_SPRITE_SECTION,
+        Sprite::MachineGun => MACHINE_GUN_PICKUP_SPRITE_SECTION,
+        Sprite::RocketLauncher => ROCKET_LAUNCHER_PICKUP_SPRITE_SECTION,
+    }
+}
@@ -0,0 +1,36 @@
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget};
+use crate::game::GameState;
+use crate::game::weapons::{WeaponKind, FIRE_ANIMATION_SECONDS};
+use crate::{KNIFE_SPRITE_SECTIONS, PISTOL_SPRITE_SECTIONS, MACHINE_GUN_SPRITE_SECTIONS};
+use crate::ROCKET_LAUNCHER_SPRITE_SECTIONS;
+use crate::rendering::atlas::Atlas;
+use crate::rendering::draw_section;
+
+/// Draws the player's current weapon at the bottom center of the view
+pub fn render_weapon<T: RenderTarget>(canvas: &mut Canvas<T>, game_state: &GameState, sprite_atlas: &Atlas, view: Rect) {
+    let player = &game_state.player;
+    if !player.is_alive() {
+        return;
+    }
+
+    let sections = match player.weapon {
+        WeaponKind::Knife => KNIFE_SPRITE_SECTIONS,
+        WeaponKind::Pistol => PISTOL_SPRITE_SECTIONS,
+        WeaponKind::MachineGun => MACHINE_GUN_SPRITE_SECTIONS,
+        WeaponKind::RocketLauncher => ROCKET_LAUNCHER_SPRITE_SECTIONS,
+    };
+
+    let section_name = if player.seconds_since_fired < FIRE_ANIMATION_SECONDS / 2.0 {
+        sections[1]
+    } else if player.seconds_since_fired < FIRE_ANIMATION_SECONDS {
+        sections[2]
+    } else {
+        sections[0]
+    };
+
+    let size = view.height() / 2;
+    let start_x = view.x() + (view.width() - size) as i32 / 2;
+    let start_y = view.bottom() - size as i32;
+    draw_section(canvas, sprite_atlas, section_name, Rect::new(start_x, start_y, size, size));
+}
@@ -0,0 +1,38 @@
+//! Where each wall's texture is cut out of the wall atlas
+
+use crate::game::map::CellType;
+use crate::game::items::KeyColor;
+
+pub const WALL_ATLAS_PATH: &str = "assets/wolftextures.png";
+pub const BRICK_WALL_SECTION: &str = "BRICK";
+pub const WOOD_WALL_SECTION: &str = "WOOD";
+pub const BLUE_WALL_SECTION: &str = "BLUE";
+pub const DOOR_SECTION: &str = "DOOR";
+pub const GOLD_DOOR_SECTION: &str = "GOLD_DOOR";
+pub const SILVER_DOOR_SECTION: &str = "SILVER_DOOR";
+pub const EXIT_SECTION: &str = "EXIT";
+
+/// Every section of the wall atlas, along with its x, y, width and height in the image
+pub const WALL_SECTIONS: [(&str, u32, u32, u32, u32); 7] = [
+    (BRICK_WALL_SECTION, 64, 0, 64, 64),
+    (BLUE_WALL_SECTION, 256, 0, 64, 64),
+    (WOOD_WALL_SECTION, 384, 0, 64, 64),
+    (DOOR_SECTION, 512, 0, 64, 64),
+    (GOLD_DOOR_SECTION, 576, 0, 64, 64),
+    (SILVER_DOOR_SECTION, 640, 0, 64, 64),
+    (EXIT_SECTION, 0, 0, 64, 64),
+];
+
+/// The wall atlas section a cell is textured with, or `None` if the cell isn't drawn
+pub fn wall_section_name(cell_type: CellType) -> Option<&'static str> {
+    match cell_type {
+        CellType::BrickWall | CellType::SecretWall => Some(BRICK_WALL_SECTION),
+        CellType::WoodWall => Some(WOOD_WALL_SECTION),
+        CellType::BlueWall => Some(BLUE_WALL_SECTION),
+        CellType::Door { lock: None } => Some(DOOR_SECTION),
+        CellType::Door { lock: Some(KeyColor::Gold) } => Some(GOLD_DOOR_SECTION),
+        CellType::Door { lock: Some(KeyColor::Silver) } => Some(SILVER_DOOR_SECTION),
+        CellType::Exit => Some(EXIT_SECTION),
+        CellType::Empty => None,
+    }
+}